@@ -0,0 +1,86 @@
+//! Compares decoding packets by walking the type tree with running compiled plans.
+//!
+//! Run with `cargo bench`.
+
+use protodef_parser::codec::Decoder;
+use protodef_parser::read_protocol;
+use std::fs::File;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20_000;
+
+/// Packets of the 1.16.2 spec by namespace, each with its bytes.
+const PACKETS: &[(&str, &[u8])] = &[
+    (
+        "handshaking.toServer",
+        &[
+            0x00, 0xf2, 0x03, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63,
+            0xdd, 0x01,
+        ],
+    ),
+    ("play.toClient", &[0x47, 0x01, 0x80, 0x00, 0x05, 0x00]),
+    (
+        "play.toClient",
+        &[
+            0x19, 0x0d, b'{', b'"', b't', b'e', b'x', b't', b'"', b':', b'"', b'h', b'i', b'"',
+            b'}',
+        ],
+    ),
+    (
+        "play.toClient",
+        &[
+            0x4e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x17, 0x70,
+        ],
+    ),
+];
+
+fn measure(mut decode: impl FnMut()) -> Duration {
+    for _ in 0..ITERATIONS / 10 {
+        decode();
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        decode();
+    }
+
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let file = File::open("test/minecraft_v1662.json").expect("Failed to open protocol");
+    let protocol = read_protocol(file).expect("Failed to read protocol");
+
+    println!(
+        "{:<24} {:>6} {:>12} {:>12} {:>8}",
+        "namespace", "bytes", "tree", "plan", "speedup"
+    );
+
+    for (namespace, bytes) in PACKETS {
+        let decoder = Decoder::with_namespace(&protocol, namespace).expect("namespace");
+        let plan = decoder.compile("packet").expect("packet plan");
+
+        let mut input = *bytes;
+        let expected = decoder.decode_type("packet", *bytes).expect("packet");
+        assert_eq!(plan.decode(&mut input).expect("packet"), expected);
+
+        let tree = measure(|| {
+            black_box(decoder.decode_type("packet", black_box(*bytes)).unwrap());
+        });
+        let compiled = measure(|| {
+            let mut input = black_box(*bytes);
+            black_box(plan.decode(&mut input).unwrap());
+        });
+
+        println!(
+            "{:<24} {:>6} {:>12?} {:>12?} {:>7.1}x",
+            namespace,
+            bytes.len(),
+            tree,
+            compiled,
+            tree.as_secs_f64() / compiled.as_secs_f64()
+        );
+    }
+}
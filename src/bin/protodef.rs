@@ -0,0 +1,371 @@
+//! Command-line tool for inspecting protodef files.
+
+use protodef_parser::codec::{Decoder, Span};
+use protodef_parser::resolve::{expand, resolve, Scope, TypeId};
+use protodef_parser::validate::validate;
+use protodef_parser::{read_protocol, Protocol, TypeDefinition};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::process;
+
+const USAGE: &str = "\
+Usage: protodef <command> [options] <file> [type]
+
+Commands:
+    validate <file>              Check a protocol for errors
+    types <file>                 List namespaces and their types
+    show <file> <type>           Print the definition of a type with referenced types expanded
+    uses <file> <type>           List where a type is referred to
+    decode --protocol <file> --type <type> <hexfile>
+                                 Decode hex-encoded bytes, `-` for stdin, showing
+                                 the bytes of every field
+
+Options:
+    --ns <namespace>             Namespace to look up the type from, e.g. play.toClient";
+
+/// Bytes shown per span before eliding the rest.
+const MAX_SHOWN_BYTES: usize = 16;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Arguments of a command: positional ones in order, and `--name value` options.
+#[derive(Debug, PartialEq)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I, known: &[&str]) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if known.contains(&name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("missing value of option --{}", name))?;
+                    options.insert(name.to_owned(), value);
+                }
+                Some(name) => return Err(format!("unknown option --{}", name).into()),
+                None => positional.push(arg),
+            }
+        }
+
+        Ok(Args {
+            positional,
+            options,
+        })
+    }
+
+    /// The positional arguments, failing unless there are exactly `N`.
+    fn expect<const N: usize>(&self) -> Result<[&str; N]> {
+        let positional: Vec<&str> = self.positional.iter().map(String::as_str).collect();
+
+        positional
+            .try_into()
+            .map_err(|_| format!("expected {} arguments\n\n{}", N, USAGE).into())
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+}
+
+fn main() {
+    match run(std::env::args().skip(1)) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("protodef: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+/// Runs a command, returning whether the protocol has no problems.
+fn run<I: Iterator<Item = String>>(mut args: I) -> Result<bool> {
+    let command = args.next().unwrap_or_default();
+
+    match command.as_str() {
+        "validate" => {
+            let args = Args::parse(args, &[])?;
+            let [file] = args.expect()?;
+            check(file)
+        }
+        "types" => {
+            let args = Args::parse(args, &[])?;
+            let [file] = args.expect()?;
+            list_types(&load(file)?);
+            Ok(true)
+        }
+        "show" => {
+            let args = Args::parse(args, &["ns"])?;
+            let [file, name] = args.expect()?;
+            let protocol = load(file)?;
+            let id = find(&protocol, args.option("ns"), name)?;
+            let definition = expand(&protocol, &id).ok_or_else(|| unknown(&id.to_string()))?;
+
+            println!("{}", serde_json::to_string_pretty(&definition)?);
+            Ok(true)
+        }
+        "uses" => {
+            let args = Args::parse(args, &["ns"])?;
+            let [file, name] = args.expect()?;
+            let protocol = load(file)?;
+            let id = find(&protocol, args.option("ns"), name)?;
+
+            let resolution = resolve(&protocol).map_err(|dangling| {
+                let errors: Vec<String> = dangling.iter().map(ToString::to_string).collect();
+                errors.join("\n")
+            })?;
+
+            for (referrer, reference) in resolution.referrers(&id) {
+                println!("{} at {}", referrer, reference.path);
+            }
+            Ok(true)
+        }
+        "decode" => {
+            let args = Args::parse(args, &["protocol", "ns", "type"])?;
+            let [input] = args.expect()?;
+            let protocol = load(args.option("protocol").ok_or("missing option --protocol")?)?;
+            let name = args.option("type").ok_or("missing option --type")?;
+
+            let decoder = match args.option("ns") {
+                Some(namespace) => Decoder::with_namespace(&protocol, namespace)?,
+                None => Decoder::new(&protocol),
+            };
+
+            let text = if input == "-" {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                text
+            } else {
+                fs::read_to_string(input).map_err(|err| format!("{}: {}", input, err))?
+            };
+
+            let bytes = parse_hex(&text)?;
+            let span = decoder.trace_type(name, &bytes[..])?;
+            let mut tree = String::new();
+            render(&span, name, 0, &bytes, &mut tree);
+            print!("{}", tree);
+
+            let trailing = bytes.len() - span.length;
+            if trailing > 0 {
+                println!("{} trailing bytes", trailing);
+            }
+            Ok(trailing == 0)
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        "" => Err(USAGE.into()),
+        command => Err(format!("unknown command \"{}\"\n\n{}", command, USAGE).into()),
+    }
+}
+
+fn load(file: &str) -> Result<Protocol> {
+    let reader = BufReader::new(File::open(file).map_err(|err| format!("{}: {}", file, err))?);
+
+    read_protocol(reader).map_err(|err| format!("{}: {}", file, err).into())
+}
+
+fn unknown(name: &str) -> Box<dyn Error> {
+    format!("unknown type \"{}\"", name).into()
+}
+
+/// Looks up a type as it is visible from a namespace, the root types by default.
+fn find(protocol: &Protocol, namespace: Option<&str>, name: &str) -> Result<TypeId> {
+    let namespace = namespace.unwrap_or("");
+    let scope = Scope::new(protocol, namespace)
+        .ok_or_else(|| format!("unknown namespace \"{}\"", namespace))?;
+
+    scope
+        .find(name)
+        .map(|(id, _)| id)
+        .ok_or_else(|| unknown(name))
+}
+
+/// Prints parse errors and problems of a protocol.
+fn check(file: &str) -> Result<bool> {
+    let protocol = match load(file) {
+        Ok(protocol) => protocol,
+        Err(err) => {
+            println!("{}", err);
+            return Ok(false);
+        }
+    };
+
+    let diagnostics = validate(&protocol);
+
+    for diagnostic in &diagnostics {
+        println!("{}: {}", file, diagnostic);
+    }
+
+    Ok(diagnostics.is_empty())
+}
+
+fn list_types(protocol: &Protocol) {
+    println!("types");
+    print_types(protocol.types.iter());
+
+    for (path, namespace) in protocol.leaf_namespaces() {
+        println!("{}", path);
+        print_types(namespace.types());
+    }
+}
+
+fn print_types<'a, I>(types: I)
+where
+    I: IntoIterator<Item = (&'a String, &'a TypeDefinition)>,
+{
+    for (name, definition) in types {
+        match definition {
+            TypeDefinition::Native => println!("    {} (native)", name),
+            TypeDefinition::DataType(_) => println!("    {}", name),
+        }
+    }
+}
+
+/// Parses hex digits, ignoring whitespace.
+fn parse_hex(text: &str) -> Result<Vec<u8>> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if let Some(digit) = digits.iter().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex digit '{}'", digit).into());
+    }
+
+    if digits.len() % 2 != 0 {
+        return Err("odd number of hex digits".into());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16)
+                .map_err(|_| format!("invalid hex byte \"{}\"", pair).into())
+        })
+        .collect()
+}
+
+/// Writes a span and its children as an indented tree, one line per span with
+/// its offset, length and bytes, and the value of spans without children.
+fn render(span: &Span, name: &str, depth: usize, bytes: &[u8], out: &mut String) {
+    let name = match name {
+        "" => "(anonymous)",
+        name => name,
+    };
+
+    let data = &bytes[span.offset..span.offset + span.length];
+    let mut hex: Vec<String> = data
+        .iter()
+        .take(MAX_SHOWN_BYTES)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if data.len() > MAX_SHOWN_BYTES {
+        hex.push("..".to_owned());
+    }
+
+    let _ = write!(
+        out,
+        "{:indent$}{} @{} +{}",
+        "",
+        name,
+        span.offset,
+        span.length,
+        indent = depth * 2
+    );
+    if !hex.is_empty() {
+        let _ = write!(out, ": {}", hex.join(" "));
+    }
+
+    if span.children.is_empty() {
+        let value = serde_json::Value::from(span.value.clone());
+        let _ = write!(out, " = {}", value);
+    }
+    out.push('\n');
+
+    for child in &span.children {
+        render(child, &child.name, depth + 1, bytes, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = Args::parse(args(&["file.json", "--ns", "play", "packet"]), &["ns"]).unwrap();
+
+        assert_eq!(parsed.expect().unwrap(), ["file.json", "packet"]);
+        assert_eq!(parsed.option("ns"), Some("play"));
+        assert!(parsed.expect::<1>().is_err());
+
+        assert!(Args::parse(args(&["--ns"]), &["ns"]).is_err());
+        assert!(Args::parse(args(&["--type", "packet"]), &["ns"]).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        assert!(run(args(&["validate", "test/minecraft_v1662.json"])).unwrap());
+        assert!(!run(args(&["validate", "test/minecraft_v170.json"])).unwrap());
+        assert!(run(args(&[
+            "show",
+            "--ns",
+            "play.toClient",
+            "test/minecraft_v1662.json",
+            "slot"
+        ]))
+        .unwrap());
+        assert!(run(args(&["show", "test/minecraft_v1662.json", "packet_chat"])).is_err());
+        assert!(run(args(&["frobnicate"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("00 ff\n2A").unwrap(), vec![0x00, 0xff, 0x2a]);
+        assert!(parse_hex("0").is_err());
+        assert!(parse_hex("zz").is_err());
+        assert!(parse_hex("+f").is_err());
+        assert!(parse_hex("0 +f").is_err());
+    }
+
+    #[test]
+    fn test_render_span() {
+        let protocol = load("test/minecraft_v1662.json").unwrap();
+        let decoder = Decoder::with_namespace(&protocol, "handshaking.toServer").unwrap();
+        let bytes = parse_hex("00 f2 03 09 6c 6f 63 61 6c 68 6f 73 74 63 dd 01").unwrap();
+
+        let span = decoder.trace_type("packet", &bytes[..]).unwrap();
+        let mut tree = String::new();
+        render(&span, "packet", 0, &bytes, &mut tree);
+
+        assert_eq!(
+            tree,
+            "\
+packet @0 +16: 00 f2 03 09 6c 6f 63 61 6c 68 6f 73 74 63 dd 01
+  name @0 +1: 00 = \"set_protocol\"
+  params @1 +15: f2 03 09 6c 6f 63 61 6c 68 6f 73 74 63 dd 01
+    protocolVersion @1 +2: f2 03 = 498
+    serverHost @3 +10: 09 6c 6f 63 61 6c 68 6f 73 74 = \"localhost\"
+    serverPort @13 +2: 63 dd = 25565
+    nextState @15 +1: 01 = 1
+"
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! Decoding from byte slices into `ValueRef`s, which borrow strings and buffers
+//! from the input instead of copying them, and lazy views of containers.
+
+use crate::codec::decode::{decode_bitfield, decode_numeric, decode_primitive, decode_util_within};
+use crate::codec::{
+    lookup_field, mappings, matches_key, value_to_count, Builtin, Decoder, Error, NativeRegistry,
+    Result,
+};
+use crate::resolve::Scope;
+use crate::value::{Value, ValueRef};
+use crate::{
+    Array, ArrayCount, Buffer, Conditional, DataType, Field, Loop, Mapper, Primitive, Structure,
+    Switch, TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::borrow::Cow;
+use std::io;
+use std::slice;
+use std::str;
+
+impl<'a> Decoder<'a> {
+    /// Decodes a value of the given type from the start of `bytes`, advancing it past
+    /// the bytes read. Strings and buffers borrow from `bytes`.
+    pub fn decode_borrowed<'b>(
+        &self,
+        data_type: &DataType,
+        bytes: &mut &'b [u8],
+    ) -> Result<ValueRef<'b>> {
+        let mut state = Borrowing {
+            scope: &self.scope,
+            natives: self.natives.as_deref(),
+            frames: Vec::new(),
+            input: bytes,
+        };
+
+        let value = state.decode(data_type)?;
+        *bytes = state.input;
+
+        Ok(value)
+    }
+
+    /// Decodes a value of the type with the given name, borrowing from `bytes`.
+    pub fn decode_type_borrowed<'b>(
+        &self,
+        name: &str,
+        bytes: &mut &'b [u8],
+    ) -> Result<ValueRef<'b>> {
+        self.decode_borrowed(&*self.named_type(name)?, bytes)
+    }
+
+    /// Creates a view of a container, possibly behind type names, that decodes
+    /// its fields from `bytes` as they are accessed.
+    pub fn view<'d, 'b>(
+        &'d self,
+        data_type: &'d DataType,
+        bytes: &'b [u8],
+    ) -> Result<ContainerView<'d, 'b>> {
+        let fields = container_fields(&self.scope, data_type)?;
+
+        Ok(ContainerView {
+            state: Borrowing {
+                scope: &self.scope,
+                natives: self.natives.as_deref(),
+                frames: vec![LinkedHashMap::new()],
+                input: bytes,
+            },
+            fields: fields.iter(),
+            length: bytes.len(),
+        })
+    }
+
+    /// Creates a view of the container type with the given name.
+    pub fn view_type<'d, 'b>(
+        &'d self,
+        name: &str,
+        bytes: &'b [u8],
+    ) -> Result<ContainerView<'d, 'b>> {
+        match self.named_type(name)? {
+            Cow::Borrowed(data_type) => self.view(data_type, bytes),
+            Cow::Owned(_) => Err(Error::NotAContainer),
+        }
+    }
+}
+
+/// A container whose fields are decoded in order up to the one accessed, leaving
+/// the rest of the input unread until needed.
+pub struct ContainerView<'d, 'b> {
+    state: Borrowing<'d, 'd, 'b>,
+    /// Fields not decoded yet.
+    fields: slice::Iter<'d, Field>,
+    /// Length of the input.
+    length: usize,
+}
+
+impl<'d, 'b> ContainerView<'d, 'b> {
+    /// Returns a field, decoding the fields before it first, or `None` if the
+    /// container has no such field. The view must not be used after an error.
+    pub fn get(&mut self, name: &str) -> Result<Option<&ValueRef<'b>>> {
+        while !self.decoded().contains_key(name) {
+            match self.fields.next() {
+                Some(field) => self.state.decode_field(field)?,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(self.decoded().get(name))
+    }
+
+    /// The fields decoded so far.
+    pub fn decoded(&self) -> &LinkedHashMap<String, ValueRef<'b>> {
+        &self.state.frames[0]
+    }
+
+    /// Number of bytes read so far.
+    pub fn offset(&self) -> usize {
+        self.length - self.state.input.len()
+    }
+
+    /// The bytes following the fields decoded so far.
+    pub fn remaining(&self) -> &'b [u8] {
+        self.state.input
+    }
+
+    /// Decodes the remaining fields, returning the whole container.
+    pub fn finish(mut self) -> Result<ValueRef<'b>> {
+        for field in self.fields {
+            self.state.decode_field(field)?;
+        }
+
+        Ok(ValueRef::Map(
+            self.state.frames.pop().expect("container frame"),
+        ))
+    }
+}
+
+/// Fields of a container, following type names to its definition.
+fn container_fields<'d>(scope: &Scope<'d>, data_type: &'d DataType) -> Result<&'d [Field]> {
+    let mut data_type = data_type;
+    let mut seen = Vec::new();
+
+    loop {
+        match data_type {
+            DataType::Structure(structure) => match structure.as_ref() {
+                Structure::Container(fields) => return Ok(fields),
+                _ => return Err(Error::NotAContainer),
+            },
+            DataType::Custom(name) if !seen.contains(&name) => {
+                seen.push(name);
+                data_type = match scope.lookup(name) {
+                    Some(TypeDefinition::DataType(data_type)) => data_type,
+                    Some(TypeDefinition::Native) => return Err(Error::NotAContainer),
+                    None => return Err(Error::UnknownType(name.clone())),
+                };
+            }
+            _ => return Err(Error::NotAContainer),
+        }
+    }
+}
+
+struct Borrowing<'s, 'a, 'b> {
+    scope: &'s Scope<'a>,
+    natives: Option<&'s dyn NativeRegistry>,
+    /// Values of the containers being decoded, innermost last.
+    frames: Vec<LinkedHashMap<String, ValueRef<'b>>>,
+    /// The bytes not read yet.
+    input: &'b [u8],
+}
+
+impl<'s, 'a, 'b> Borrowing<'s, 'a, 'b> {
+    fn decode(&mut self, data_type: &DataType) -> Result<ValueRef<'b>> {
+        match data_type {
+            DataType::Conditional(conditional) => self.decode_conditional(conditional),
+            DataType::Numeric(numeric) => {
+                Ok(ValueRef::Owned(decode_numeric(numeric, &mut self.input)?))
+            }
+            DataType::Primitive(Primitive::String) => {
+                let length = self
+                    .input
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .ok_or_else(unexpected_eof)?;
+                let bytes = self.take(length + 1)?;
+
+                Ok(ValueRef::Str(str::from_utf8(&bytes[..length])?))
+            }
+            DataType::Primitive(primitive) => Ok(ValueRef::Owned(decode_primitive(
+                primitive,
+                &mut self.input,
+            )?)),
+            DataType::Structure(structure) => self.decode_structure(structure),
+            DataType::Util(util) => self.decode_util(util),
+            DataType::Parameterized { name, args } => {
+                let data_type = self
+                    .scope
+                    .instantiate(name, args)
+                    .ok_or_else(|| Error::UnknownType(name.clone()))?;
+
+                self.decode(&data_type)
+            }
+            DataType::Custom(name) => match self.scope.lookup(name) {
+                Some(TypeDefinition::Native) => self.decode_native(name),
+                Some(TypeDefinition::DataType(data_type)) => self.decode(data_type),
+                None => Err(Error::UnknownType(name.clone())),
+            },
+        }
+    }
+
+    fn decode_conditional(&mut self, conditional: &Conditional) -> Result<ValueRef<'b>> {
+        match conditional {
+            Conditional::Switch(switch) => self.decode_switch(switch),
+            Conditional::Option(data_type) => {
+                if self.take(1)?[0] == 0 {
+                    Ok(ValueRef::Option(None))
+                } else {
+                    let value = self.decode(data_type)?;
+                    Ok(ValueRef::Option(Some(Box::new(value))))
+                }
+            }
+        }
+    }
+
+    fn decode_switch(&mut self, switch: &Switch) -> Result<ValueRef<'b>> {
+        let compare_value = self.field(&switch.compare_to)?;
+
+        let branch = switch
+            .fields
+            .iter()
+            .find(|(key, _)| matches_key(key, compare_value))
+            .map(|(_, data_type)| data_type)
+            .or(switch.default.as_ref());
+
+        match branch {
+            Some(data_type) => self.decode(data_type),
+            None => Ok(ValueRef::Owned(Value::Void)),
+        }
+    }
+
+    fn decode_structure(&mut self, structure: &Structure) -> Result<ValueRef<'b>> {
+        match structure {
+            Structure::Array(array) => self.decode_array(array),
+            Structure::Container(fields) => {
+                self.frames.push(LinkedHashMap::new());
+
+                for field in fields {
+                    if let Err(err) = self.decode_field(field) {
+                        self.frames.pop();
+                        return Err(err);
+                    }
+                }
+
+                let values = self.frames.pop().expect("container frame");
+                Ok(ValueRef::Map(values))
+            }
+            Structure::Count(count) => self.decode(&count.count_type),
+        }
+    }
+
+    /// Decodes a field into the innermost container.
+    fn decode_field(&mut self, field: &Field) -> Result<()> {
+        let value = self.decode(&field.field_type)?;
+
+        let frame = self.frames.last_mut().expect("container frame");
+        match (&field.name, value) {
+            (Some(name), value) if !field.is_anonymous() => {
+                frame.insert(name.clone(), value);
+            }
+            // Anonymous fields are merged into the parent container.
+            (_, ValueRef::Map(values)) => frame.extend(values),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn decode_array(&mut self, array: &Array) -> Result<ValueRef<'b>> {
+        let count = self.count(array.count_type.as_ref(), array.count.as_ref())?;
+        let mut values = Vec::with_capacity(count.min(1024));
+
+        for _ in 0..count {
+            values.push(self.decode(&array.elements_type)?);
+        }
+
+        Ok(ValueRef::List(values))
+    }
+
+    fn decode_util(&mut self, util: &Util) -> Result<ValueRef<'b>> {
+        match util {
+            Util::Buffer(buffer) => self.decode_buffer(buffer),
+            Util::Mapper(mapper) => self.decode_mapper(mapper),
+            Util::Bitfield(fields) => Ok(decode_bitfield(fields, &mut self.input)?.into()),
+            Util::PrefixedString { count_type } => {
+                let count = self.count(Some(count_type), None)?;
+
+                Ok(ValueRef::Str(str::from_utf8(self.take(count)?)?))
+            }
+            Util::Loop(loop_util) => self.decode_loop(loop_util),
+            // Elements are read with their first byte altered, so they cannot borrow
+            // from the input and are decoded into owned values.
+            Util::TopBitSetTerminatedArray(_) => {
+                let frames = self
+                    .frames
+                    .iter()
+                    .map(|frame| {
+                        frame
+                            .iter()
+                            .map(|(name, value)| (name.clone(), value.to_value()))
+                            .collect()
+                    })
+                    .collect();
+
+                Ok(
+                    decode_util_within(self.scope, self.natives, frames, util, &mut self.input)?
+                        .into(),
+                )
+            }
+        }
+    }
+
+    fn decode_buffer(&mut self, buffer: &Buffer) -> Result<ValueRef<'b>> {
+        if buffer.rest == Some(true) {
+            return Ok(ValueRef::Bytes(self.take(self.input.len())?));
+        }
+
+        let count = self.count(buffer.count_type.as_ref(), buffer.count.as_ref())?;
+
+        Ok(ValueRef::Bytes(self.take(count)?))
+    }
+
+    fn decode_mapper(&mut self, mapper: &Mapper) -> Result<ValueRef<'b>> {
+        let (numeric, mappings) = mappings(mapper, self.scope)?;
+        let value = decode_numeric(&numeric, &mut self.input)?;
+
+        // Unknown keys are passed through as-is.
+        match value.as_i128().and_then(|key| mappings.name(key)) {
+            Some(name) => Ok(ValueRef::Owned(Value::String(name.to_owned()))),
+            None => Ok(ValueRef::Owned(value)),
+        }
+    }
+
+    fn decode_loop(&mut self, loop_util: &Loop) -> Result<ValueRef<'b>> {
+        let mut values = Vec::new();
+
+        loop {
+            let first = *self.input.first().ok_or_else(unexpected_eof)?;
+
+            if u32::from(first) == loop_util.end_val {
+                self.take(1)?;
+                break;
+            }
+
+            values.push(self.decode(&loop_util.data_type)?);
+        }
+
+        Ok(ValueRef::List(values))
+    }
+
+    fn decode_native(&mut self, name: &str) -> Result<ValueRef<'b>> {
+        if let Some(natives) = self.natives.filter(|natives| natives.contains(name)) {
+            return Ok(ValueRef::Owned(natives.decode(name, &mut self.input)?));
+        }
+
+        match Builtin::of(name) {
+            Some(Builtin::RestBuffer) => Ok(ValueRef::Bytes(self.take(self.input.len())?)),
+            Some(Builtin::Numeric(numeric)) => {
+                Ok(ValueRef::Owned(decode_numeric(&numeric, &mut self.input)?))
+            }
+            None => Err(Error::UnsupportedNative(name.to_owned())),
+        }
+    }
+
+    /// Reads the length prefix, or resolves the count of an array or a buffer.
+    fn count(
+        &mut self,
+        count_type: Option<&DataType>,
+        count: Option<&ArrayCount>,
+    ) -> Result<usize> {
+        match (count_type, count) {
+            (Some(count_type), _) => {
+                let value = self.decode(count_type)?;
+                value_to_count(&value)
+            }
+            (None, Some(ArrayCount::FixedLength(length))) => Ok(*length as usize),
+            (None, Some(ArrayCount::FieldReference(path))) => value_to_count(self.field(path)?),
+            (None, None) => Err(Error::MissingCount),
+        }
+    }
+
+    fn field(&self, path: &str) -> Result<&ValueRef<'b>> {
+        lookup_field(&self.frames, path)
+    }
+
+    /// Splits off the next `count` bytes of the input.
+    fn take(&mut self, count: usize) -> Result<&'b [u8]> {
+        if count > self.input.len() {
+            return Err(unexpected_eof());
+        }
+
+        let (bytes, rest) = self.input.split_at(count);
+        self.input = rest;
+
+        Ok(bytes)
+    }
+}
+
+fn unexpected_eof() -> Error {
+    Error::Io(io::ErrorKind::UnexpectedEof.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{self, Decoder};
+    use crate::value::{Value, ValueRef};
+    use crate::*;
+
+    #[test]
+    fn test_decode_borrowed() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "handshaking.toServer").unwrap();
+
+        let bytes = [
+            0x00, 0xf2, 0x03, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63,
+            0xdd, 0x01, 0xaa,
+        ];
+        let mut input = &bytes[..];
+        let value = decoder.decode_type_borrowed("packet", &mut input).unwrap();
+
+        assert_eq!(input, [0xaa]);
+        assert_eq!(
+            value.to_value(),
+            decoder.decode_type("packet", &bytes[..]).unwrap()
+        );
+
+        let host = value.get("params").unwrap().get("serverHost").unwrap();
+        assert_eq!(host, &ValueRef::Str("localhost"));
+        assert_eq!(host.as_str().unwrap().as_ptr(), bytes[4..].as_ptr());
+        assert_eq!(
+            value.get("name"),
+            Some(&ValueRef::Owned(Value::String("set_protocol".to_owned())))
+        );
+
+        let mut truncated = &bytes[..8];
+        assert!(matches!(
+            decoder.decode_type_borrowed("packet", &mut truncated),
+            Err(codec::Error::Io(_))
+        ));
+        assert_eq!(truncated.len(), 8);
+    }
+
+    #[test]
+    fn test_decode_borrowed_matches_decode() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let cases: &[(&str, &[u8])] = &[
+            (
+                "entityMetadata",
+                &[0x00, 0x00, 0x05, 0x07, 0x01, 0x01, 0xff],
+            ),
+            ("particle", &[0x03, 0x2a]),
+            (
+                "position",
+                &[0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x20, 0x40],
+            ),
+            ("packet", &[0x47, 0x01, 0x80, 0x00, 0x05, 0x00]),
+            ("packet", &[0x19, 0x03, b'{', b'}', b'!']),
+        ];
+
+        for (name, bytes) in cases {
+            let mut input = *bytes;
+            let borrowed = decoder.decode_type_borrowed(name, &mut input).unwrap();
+
+            assert!(input.is_empty(), "{}", name);
+            assert_eq!(
+                borrowed.to_value(),
+                decoder.decode_type(name, *bytes).unwrap(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_container_view() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let bytes = [0x19, 0x03, b'{', b'}', b'!', 0xff];
+        let mut view = decoder.view_type("packet", &bytes[..]).unwrap();
+
+        assert_eq!(
+            view.get("name").unwrap().and_then(ValueRef::as_str),
+            Some("kick_disconnect")
+        );
+        assert_eq!(view.offset(), 1);
+        assert_eq!(view.remaining(), &bytes[1..]);
+        assert_eq!(view.decoded().len(), 1);
+
+        let value = view.finish().unwrap();
+        assert_eq!(
+            value.get("params").unwrap().get("reason"),
+            Some(&ValueRef::Str("{}!"))
+        );
+
+        assert!(matches!(
+            decoder.view_type("varint", &bytes[..]),
+            Err(codec::Error::NotAContainer)
+        ));
+    }
+
+    #[test]
+    fn test_container_view_decodes_on_access() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        // The `sender` is cut off, which goes unnoticed until it is decoded.
+        let bytes = [0x03, b'{', b'}', b'!', 0x01, 0x00];
+        let mut view = decoder.view_type("packet_chat", &bytes[..]).unwrap();
+
+        assert_eq!(view.get("message").unwrap(), Some(&ValueRef::Str("{}!")));
+        assert_eq!(
+            view.get("position").unwrap(),
+            Some(&ValueRef::Owned(Value::I8(1)))
+        );
+        assert_eq!(
+            view.get("message").unwrap().map(ValueRef::kind),
+            Some("string")
+        );
+        assert!(matches!(view.get("sender"), Err(codec::Error::Io(_))));
+    }
+}
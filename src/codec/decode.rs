@@ -0,0 +1,956 @@
+use crate::codec::{
+    lookup_field, mappings, matches_key, value_to_count, Builtin, Error, NativeRegistry, Result,
+};
+use crate::resolve::Scope;
+use crate::value::Value;
+use crate::{
+    Array, ArrayCount, BitField, Buffer, ByteOrder, Conditional, DataType, Loop, Mapper, Numeric,
+    Primitive, Protocol, Structure, Switch, TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::io::Read;
+use std::sync::Arc;
+
+/// Decodes binary data into `Value`s according to the types of a protocol.
+pub struct Decoder<'a> {
+    pub(super) scope: Scope<'a>,
+    pub(super) natives: Option<Arc<dyn NativeRegistry>>,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a decoder resolving custom types against the root `types` of the protocol.
+    pub fn new(protocol: &'a Protocol) -> Self {
+        Decoder {
+            scope: Scope::new(protocol, "").expect("root scope always exists"),
+            natives: None,
+        }
+    }
+
+    /// Creates a decoder resolving custom types as seen from a namespace, e.g. `play.toClient`.
+    pub fn with_namespace(protocol: &'a Protocol, path: &str) -> Result<Self> {
+        Ok(Decoder {
+            scope: Scope::new(protocol, path)
+                .ok_or_else(|| Error::UnknownNamespace(path.to_owned()))?,
+            natives: None,
+        })
+    }
+
+    /// Decodes the native types implemented by a registry with it, before the built-in ones.
+    pub fn natives(mut self, natives: Arc<dyn NativeRegistry>) -> Self {
+        self.natives = Some(natives);
+        self
+    }
+
+    /// Decodes a value of the given type. A `&[u8]` can be passed as the reader.
+    pub fn decode<R: Read>(&self, data_type: &DataType, reader: R) -> Result<Value> {
+        let position = Cell::new(0);
+        let mut state = State {
+            scope: &self.scope,
+            natives: self.natives.as_deref(),
+            frames: Vec::new(),
+            position: &position,
+            spans: None,
+        };
+
+        state.decode(data_type, &mut Counting::new(reader, &position))
+    }
+
+    /// Decodes a value of the given type, recording which bytes each container
+    /// field and array element was read from.
+    pub fn trace<R: Read>(&self, data_type: &DataType, reader: R) -> Result<Span> {
+        let position = Cell::new(0);
+        let mut state = State {
+            scope: &self.scope,
+            natives: self.natives.as_deref(),
+            frames: Vec::new(),
+            position: &position,
+            spans: Some(vec![Vec::new()]),
+        };
+
+        state.decode_traced(
+            String::new(),
+            data_type,
+            &mut Counting::new(reader, &position),
+        )?;
+
+        let mut spans = state.spans.expect("tracing");
+        Ok(spans
+            .pop()
+            .and_then(|mut root| root.pop())
+            .expect("root span"))
+    }
+
+    /// Decodes a value of the type with the given name.
+    pub fn decode_type<R: Read>(&self, name: &str, reader: R) -> Result<Value> {
+        self.decode(&*self.named_type(name)?, reader)
+    }
+
+    /// Decodes a value of the type with the given name, recording the bytes of its parts.
+    pub fn trace_type<R: Read>(&self, name: &str, reader: R) -> Result<Span> {
+        self.trace(&*self.named_type(name)?, reader)
+    }
+
+    pub(super) fn named_type(&self, name: &str) -> Result<Cow<'a, DataType>> {
+        let data_type = self
+            .scope
+            .lookup(name)
+            .ok_or_else(|| Error::UnknownType(name.to_owned()))?;
+
+        Ok(match data_type {
+            TypeDefinition::Native => Cow::Owned(DataType::Custom(name.to_owned())),
+            TypeDefinition::DataType(data_type) => Cow::Borrowed(data_type),
+        })
+    }
+}
+
+/// Decodes a util inside containers whose values are already decoded, innermost last.
+pub(super) fn decode_util_within(
+    scope: &Scope,
+    natives: Option<&dyn NativeRegistry>,
+    frames: Vec<LinkedHashMap<String, Value>>,
+    util: &Util,
+    reader: &mut dyn Read,
+) -> Result<Value> {
+    let position = Cell::new(0);
+    let mut state = State {
+        scope,
+        natives,
+        frames,
+        position: &position,
+        spans: None,
+    };
+
+    state.decode_util(util, &mut Counting::new(reader, &position))
+}
+
+/// A decoded value along with the bytes it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// The field name, `[i]` for array elements, or empty for the decoded value
+    /// itself and anonymous fields.
+    pub name: String,
+    /// Offset of the first byte from the start of the input.
+    pub offset: usize,
+    pub length: usize,
+    pub value: Value,
+    /// Spans of the fields or elements of a container or array, possibly nested
+    /// in switches and options.
+    pub children: Vec<Span>,
+}
+
+/// Counts the bytes read through it into a position shared with the decoder.
+struct Counting<'p, R> {
+    inner: R,
+    position: &'p Cell<usize>,
+}
+
+impl<'p, R: Read> Counting<'p, R> {
+    fn new(inner: R, position: &'p Cell<usize>) -> Self {
+        Counting { inner, position }
+    }
+}
+
+impl<R: Read> Read for Counting<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position.set(self.position.get() + read);
+
+        Ok(read)
+    }
+}
+
+struct State<'s, 'a> {
+    scope: &'s Scope<'a>,
+    natives: Option<&'s dyn NativeRegistry>,
+    /// Values of the containers being decoded, innermost last.
+    frames: Vec<LinkedHashMap<String, Value>>,
+    /// Number of bytes read so far.
+    position: &'s Cell<usize>,
+    /// Spans of the fields being traced, innermost last, if tracing.
+    spans: Option<Vec<Vec<Span>>>,
+}
+
+impl<'s, 'a> State<'s, 'a> {
+    fn decode(&mut self, data_type: &DataType, reader: &mut dyn Read) -> Result<Value> {
+        match data_type {
+            DataType::Conditional(conditional) => self.decode_conditional(conditional, reader),
+            DataType::Numeric(numeric) => decode_numeric(numeric, reader),
+            DataType::Primitive(primitive) => decode_primitive(primitive, reader),
+            DataType::Structure(structure) => self.decode_structure(structure, reader),
+            DataType::Util(util) => self.decode_util(util, reader),
+            DataType::Parameterized { name, args } => {
+                let data_type = self
+                    .scope
+                    .instantiate(name, args)
+                    .ok_or_else(|| Error::UnknownType(name.clone()))?;
+
+                self.decode(&data_type, reader)
+            }
+            DataType::Custom(name) => {
+                let scope = self.scope;
+                match scope.lookup(name) {
+                    Some(TypeDefinition::Native) => self.decode_native(name, reader),
+                    Some(TypeDefinition::DataType(data_type)) => self.decode(data_type, reader),
+                    None => Err(Error::UnknownType(name.clone())),
+                }
+            }
+        }
+    }
+
+    fn decode_conditional(
+        &mut self,
+        conditional: &Conditional,
+        reader: &mut dyn Read,
+    ) -> Result<Value> {
+        match conditional {
+            Conditional::Switch(switch) => self.decode_switch(switch, reader),
+            Conditional::Option(data_type) => {
+                if read_array::<1>(reader)?[0] == 0 {
+                    Ok(Value::Option(None))
+                } else {
+                    let value = self.decode(data_type, reader)?;
+                    Ok(Value::Option(Some(Box::new(value))))
+                }
+            }
+        }
+    }
+
+    fn decode_switch(&mut self, switch: &Switch, reader: &mut dyn Read) -> Result<Value> {
+        let compare_value = self.field(&switch.compare_to)?.clone();
+
+        let branch = switch
+            .fields
+            .iter()
+            .find(|(key, _)| matches_key(key, &compare_value))
+            .map(|(_, data_type)| data_type)
+            .or(switch.default.as_ref());
+
+        match branch {
+            Some(data_type) => self.decode(data_type, reader),
+            None => Ok(Value::Void),
+        }
+    }
+
+    fn decode_structure(&mut self, structure: &Structure, reader: &mut dyn Read) -> Result<Value> {
+        match structure {
+            Structure::Array(array) => self.decode_array(array, reader),
+            Structure::Container(fields) => {
+                self.frames.push(LinkedHashMap::new());
+
+                for field in fields {
+                    let name = field.name.clone().unwrap_or_default();
+
+                    let value = match self.decode_traced(name, &field.field_type, reader) {
+                        Ok(value) => value,
+                        Err(err) => {
+                            self.frames.pop();
+                            return Err(err);
+                        }
+                    };
+
+                    let frame = self.frames.last_mut().expect("container frame");
+                    match (&field.name, value) {
+                        (Some(name), value) if !field.is_anonymous() => {
+                            frame.insert(name.clone(), value);
+                        }
+                        // Anonymous fields are merged into the parent container.
+                        (_, Value::Map(values)) => frame.extend(values),
+                        _ => {}
+                    }
+                }
+
+                let values = self.frames.pop().expect("container frame");
+                Ok(Value::Map(values))
+            }
+            Structure::Count(count) => self.decode(&count.count_type, reader),
+        }
+    }
+
+    fn decode_array(&mut self, array: &Array, reader: &mut dyn Read) -> Result<Value> {
+        let count = self.count(array.count_type.as_ref(), array.count.as_ref(), reader)?;
+        let mut values = Vec::with_capacity(count.min(1024));
+
+        for i in 0..count {
+            values.push(self.decode_traced(format!("[{}]", i), &array.elements_type, reader)?);
+        }
+
+        Ok(Value::List(values))
+    }
+
+    fn decode_util(&mut self, util: &Util, reader: &mut dyn Read) -> Result<Value> {
+        match util {
+            Util::Buffer(buffer) => self.decode_buffer(buffer, reader),
+            Util::Mapper(mapper) => self.decode_mapper(mapper, reader),
+            Util::Bitfield(fields) => decode_bitfield(fields, reader),
+            Util::PrefixedString { count_type } => {
+                let count = self.count(Some(count_type), None, reader)?;
+                let bytes = read_bytes(count, reader)?;
+
+                Ok(Value::String(String::from_utf8(bytes)?))
+            }
+            Util::Loop(loop_util) => self.decode_loop(loop_util, reader),
+            Util::TopBitSetTerminatedArray(structure) => {
+                let mut values = Vec::new();
+
+                loop {
+                    let name = format!("[{}]", values.len());
+                    let mut last = true;
+
+                    values.push(self.traced(name, |state| {
+                        let byte = read_array::<1>(reader)?[0];
+                        last = byte & 0x80 == 0;
+
+                        let first = [byte & 0x7f];
+                        let mut chained = state.unread(&first, reader);
+                        state.decode_structure(structure, &mut chained)
+                    })?);
+
+                    if last {
+                        break;
+                    }
+                }
+
+                Ok(Value::List(values))
+            }
+        }
+    }
+
+    fn decode_buffer(&mut self, buffer: &Buffer, reader: &mut dyn Read) -> Result<Value> {
+        if buffer.rest == Some(true) {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+
+            return Ok(Value::Bytes(bytes));
+        }
+
+        let count = self.count(buffer.count_type.as_ref(), buffer.count.as_ref(), reader)?;
+
+        Ok(Value::Bytes(read_bytes(count, reader)?))
+    }
+
+    fn decode_mapper(&mut self, mapper: &Mapper, reader: &mut dyn Read) -> Result<Value> {
+        let (numeric, mappings) = mappings(mapper, self.scope)?;
+        let value = decode_numeric(&numeric, reader)?;
+
+        // Unknown keys are passed through as-is.
+        match value.as_i128().and_then(|key| mappings.name(key)) {
+            Some(name) => Ok(Value::String(name.to_owned())),
+            None => Ok(value),
+        }
+    }
+
+    fn decode_loop(&mut self, loop_util: &Loop, reader: &mut dyn Read) -> Result<Value> {
+        let mut values = Vec::new();
+
+        loop {
+            let first = read_array::<1>(reader)?;
+
+            if u32::from(first[0]) == loop_util.end_val {
+                break;
+            }
+
+            let mut chained = self.unread(&first, reader);
+            values.push(self.decode(&loop_util.data_type, &mut chained)?);
+        }
+
+        Ok(Value::List(values))
+    }
+
+    fn decode_native(&mut self, name: &str, reader: &mut dyn Read) -> Result<Value> {
+        if let Some(natives) = self.natives.filter(|natives| natives.contains(name)) {
+            return natives.decode(name, reader);
+        }
+
+        match Builtin::of(name) {
+            Some(Builtin::RestBuffer) => {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+
+                Ok(Value::Bytes(bytes))
+            }
+            Some(Builtin::Numeric(numeric)) => decode_numeric(&numeric, reader),
+            None => Err(Error::UnsupportedNative(name.to_owned())),
+        }
+    }
+
+    /// Reads the length prefix, or resolves the count of an array or a buffer.
+    fn count(
+        &mut self,
+        count_type: Option<&DataType>,
+        count: Option<&ArrayCount>,
+        reader: &mut dyn Read,
+    ) -> Result<usize> {
+        match (count_type, count) {
+            (Some(count_type), _) => {
+                let value = self.decode(count_type, reader)?;
+                value_to_count(&value)
+            }
+            (None, Some(ArrayCount::FixedLength(length))) => Ok(*length as usize),
+            (None, Some(ArrayCount::FieldReference(path))) => value_to_count(self.field(path)?),
+            (None, None) => Err(Error::MissingCount),
+        }
+    }
+
+    fn field(&self, path: &str) -> Result<&Value> {
+        lookup_field(&self.frames, path)
+    }
+
+    /// Decodes a value, recording its span if tracing.
+    fn decode_traced(
+        &mut self,
+        name: String,
+        data_type: &DataType,
+        reader: &mut dyn Read,
+    ) -> Result<Value> {
+        self.traced(name, |state| state.decode(data_type, reader))
+    }
+
+    fn traced(
+        &mut self,
+        name: String,
+        decode: impl FnOnce(&mut Self) -> Result<Value>,
+    ) -> Result<Value> {
+        match &mut self.spans {
+            Some(spans) => spans.push(Vec::new()),
+            None => return decode(self),
+        }
+
+        let offset = self.position.get();
+        let result = decode(self);
+
+        let spans = self.spans.as_mut().expect("tracing");
+        let children = spans.pop().expect("span of the value");
+        let value = result?;
+
+        spans.last_mut().expect("parent span").push(Span {
+            name,
+            offset,
+            length: self.position.get() - offset,
+            value: value.clone(),
+            children,
+        });
+
+        Ok(value)
+    }
+
+    /// Puts bytes read ahead back in front of the reader, keeping the position right.
+    fn unread<'r>(&self, bytes: &'r [u8], reader: &'r mut dyn Read) -> impl Read + 'r
+    where
+        's: 'r,
+    {
+        self.position.set(self.position.get() - bytes.len());
+
+        Counting::new(bytes, self.position).chain(reader)
+    }
+}
+
+pub(super) fn decode_numeric(numeric: &Numeric, reader: &mut dyn Read) -> Result<Value> {
+    macro_rules! read {
+        ($ty:ty, $byte_order:expr) => {{
+            let bytes = read_array(reader)?;
+            match $byte_order {
+                ByteOrder::BigEndian => <$ty>::from_be_bytes(bytes),
+                ByteOrder::LittleEndian => <$ty>::from_le_bytes(bytes),
+            }
+        }};
+    }
+
+    let value = match numeric {
+        Numeric::Byte { signed: true } => Value::I8(read!(i8, ByteOrder::BigEndian)),
+        Numeric::Byte { signed: false } => Value::U8(read!(u8, ByteOrder::BigEndian)),
+        Numeric::Short {
+            signed: true,
+            byte_order,
+        } => Value::I16(read!(i16, byte_order)),
+        Numeric::Short {
+            signed: false,
+            byte_order,
+        } => Value::U16(read!(u16, byte_order)),
+        Numeric::Int {
+            signed: true,
+            byte_order,
+        } => Value::I32(read!(i32, byte_order)),
+        Numeric::Int {
+            signed: false,
+            byte_order,
+        } => Value::U32(read!(u32, byte_order)),
+        Numeric::Long {
+            signed: true,
+            byte_order,
+        } => Value::I64(read!(i64, byte_order)),
+        Numeric::Long {
+            signed: false,
+            byte_order,
+        } => Value::U64(read!(u64, byte_order)),
+        Numeric::Int128 {
+            signed: true,
+            byte_order,
+        } => Value::I128(read!(i128, byte_order)),
+        Numeric::Int128 {
+            signed: false,
+            byte_order,
+        } => Value::U128(read!(u128, byte_order)),
+        Numeric::Float { byte_order } => Value::F32(read!(f32, byte_order)),
+        Numeric::Double { byte_order } => Value::F64(read!(f64, byte_order)),
+        Numeric::VarInt => Value::I32(read_varint(reader)?),
+        Numeric::VarLong => Value::I64(read_varlong(reader)?),
+        Numeric::ZigZag32 => {
+            let value = read_varint(reader)? as u32;
+            Value::I32((value >> 1) as i32 ^ -((value & 1) as i32))
+        }
+        Numeric::ZigZag64 => {
+            let value = read_varlong(reader)? as u64;
+            Value::I64((value >> 1) as i64 ^ -((value & 1) as i64))
+        }
+    };
+
+    Ok(value)
+}
+
+pub(super) fn decode_primitive(primitive: &Primitive, reader: &mut dyn Read) -> Result<Value> {
+    match primitive {
+        Primitive::Boolean => Ok(Value::Bool(read_array::<1>(reader)?[0] != 0)),
+        Primitive::String => {
+            let mut bytes = Vec::new();
+
+            loop {
+                match read_array::<1>(reader)?[0] {
+                    0 => break,
+                    byte => bytes.push(byte),
+                }
+            }
+
+            Ok(Value::String(String::from_utf8(bytes)?))
+        }
+        Primitive::Void => Ok(Value::Void),
+    }
+}
+
+pub(super) fn decode_bitfield(fields: &[BitField], reader: &mut dyn Read) -> Result<Value> {
+    let total_size: usize = fields.iter().map(|field| field.size).sum();
+
+    if total_size > 128 || fields.iter().any(|field| field.size > 64) {
+        return Err(Error::BitfieldTooLarge(total_size));
+    }
+
+    let total_bytes = total_size.div_ceil(8);
+    let bits = read_bytes(total_bytes, reader)?
+        .into_iter()
+        .fold(0u128, |bits, byte| (bits << 8) | u128::from(byte));
+
+    let mut offset = total_bytes * 8;
+    let mut values = LinkedHashMap::new();
+
+    for field in fields {
+        offset -= field.size;
+        let mask = (1u128 << field.size) - 1;
+        let raw = (bits >> offset) & mask;
+
+        let value = if field.signed {
+            let signed = if field.size > 0 && raw & (1u128 << (field.size - 1)) != 0 {
+                raw as i128 - (1i128 << field.size)
+            } else {
+                raw as i128
+            };
+
+            Value::I64(signed as i64)
+        } else {
+            Value::U64(raw as u64)
+        };
+
+        values.insert(field.name.clone(), value);
+    }
+
+    Ok(Value::Map(values))
+}
+
+fn read_varint(reader: &mut dyn Read) -> Result<i32> {
+    let mut result = 0u32;
+
+    for i in 0..5 {
+        let byte = read_array::<1>(reader)?[0];
+        result |= u32::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+
+    Err(Error::VarIntTooLong)
+}
+
+fn read_varlong(reader: &mut dyn Read) -> Result<i64> {
+    let mut result = 0u64;
+
+    for i in 0..10 {
+        let byte = read_array::<1>(reader)?[0];
+        result |= u64::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok(result as i64);
+        }
+    }
+
+    Err(Error::VarIntTooLong)
+}
+
+fn read_array<const N: usize>(reader: &mut dyn Read) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+fn read_bytes(count: usize, reader: &mut dyn Read) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(count.min(4096));
+    reader.take(count as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != count {
+        return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{parse_integer_key, Builtin, Decoder, Encoder};
+    use crate::value::Value;
+    use crate::*;
+
+    #[test]
+    fn test_decode_numeric() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+
+        let data_type = DataType::Numeric(Numeric::Int {
+            signed: true,
+            byte_order: ByteOrder::LittleEndian,
+        });
+
+        assert_eq!(
+            decoder
+                .decode(&data_type, &[0xfe, 0xff, 0xff, 0xff][..])
+                .unwrap(),
+            Value::I32(-2)
+        );
+    }
+
+    #[test]
+    fn test_decode_varint() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+        let data_type = DataType::Numeric(Numeric::VarInt);
+
+        assert_eq!(
+            decoder.decode(&data_type, &[0xdd, 0xc7, 0x01][..]).unwrap(),
+            Value::I32(25565)
+        );
+        assert_eq!(
+            decoder
+                .decode(&data_type, &[0xff, 0xff, 0xff, 0xff, 0x0f][..])
+                .unwrap(),
+            Value::I32(-1)
+        );
+    }
+
+    #[test]
+    fn test_decode_varlong_and_zigzag() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+        let decode = |json: &str, bytes: &[u8]| {
+            let data_type: DataType = serde_json::from_str(json).unwrap();
+            decoder.decode(&data_type, bytes)
+        };
+
+        assert_eq!(
+            decode(
+                r#""varlong""#,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+            )
+            .unwrap(),
+            Value::I64(-1)
+        );
+        assert_eq!(
+            decode(r#""varlong""#, &[0xdd, 0xc7, 0x01]).unwrap(),
+            Value::I64(25565)
+        );
+        assert_eq!(decode(r#""zigzag32""#, &[0x03]).unwrap(), Value::I32(-2));
+        assert_eq!(decode(r#""zigzag32""#, &[0x04]).unwrap(), Value::I32(2));
+        assert_eq!(
+            decode(r#""zigzag64""#, &[0xff, 0xff, 0xff, 0xff, 0x1f]).unwrap(),
+            Value::I64(-(1 << 32))
+        );
+        assert!(matches!(
+            decode(r#""varlong""#, &[0xff; 11]),
+            Err(codec::Error::VarIntTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_decode_int128_and_uuid() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+        let mut bytes = [0xff; 16];
+        bytes[0] = 0x7f;
+
+        let data_type: DataType = serde_json::from_str(r#""li128""#).unwrap();
+        assert_eq!(
+            decoder.decode(&data_type, &bytes[..]).unwrap(),
+            Value::I128(-129)
+        );
+        assert_eq!(
+            decoder.decode_type("UUID", &bytes[..]).unwrap(),
+            Value::U128(u128::MAX >> 1)
+        );
+    }
+
+    #[test]
+    fn test_decode_handshake_packet() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "handshaking.toServer").unwrap();
+
+        let bytes = [
+            0x00, 0xf2, 0x03, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63,
+            0xdd, 0x01,
+        ];
+
+        let expected = map(vec![
+            ("name", Value::String("set_protocol".to_owned())),
+            (
+                "params",
+                map(vec![
+                    ("protocolVersion", Value::I32(498)),
+                    ("serverHost", Value::String("localhost".to_owned())),
+                    ("serverPort", Value::U16(25565)),
+                    ("nextState", Value::I32(1)),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(decoder.decode_type("packet", &bytes[..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_decode_anonymous_switch() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+
+        let empty = decoder.decode_type("slot", &[0x00][..]).unwrap();
+        assert_eq!(empty, map(vec![("present", Value::Bool(false))]));
+
+        let item = decoder
+            .decode_type("slot", &[0x01, 0x05, 0x40, 0x00][..])
+            .unwrap_err();
+        assert!(matches!(item, codec::Error::UnsupportedNative(name) if name == "optionalNbt"));
+    }
+
+    #[test]
+    fn test_decode_parameterized_switch() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+
+        let bytes = [0x03, 0x2a];
+        let expected = map(vec![
+            ("particleId", Value::I32(3)),
+            ("data", map(vec![("blockState", Value::I32(42))])),
+        ]);
+
+        assert_eq!(
+            decoder.decode_type("particle", &bytes[..]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_integer_key() {
+        assert_eq!(parse_integer_key("5"), Some(5));
+        assert_eq!(parse_integer_key("-5"), Some(-5));
+        assert_eq!(parse_integer_key("0x1f"), Some(0x1f));
+        assert_eq!(parse_integer_key("-0X1F"), Some(-0x1f));
+        assert_eq!(
+            parse_integer_key(&format!("-{}", i128::MAX)),
+            Some(-i128::MAX)
+        );
+
+        for key in [
+            "", "-", "0x", "+5", "--5", "0x+5", "0x-5", "-+5", " 5", "5a", "true",
+        ] {
+            assert_eq!(parse_integer_key(key), None, "{:?}", key);
+        }
+        assert_eq!(
+            parse_integer_key(&i128::MIN.unsigned_abs().to_string()),
+            None
+        );
+        assert_eq!(
+            parse_integer_key(&format!("--{}", i128::MIN.unsigned_abs())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_natives_are_builtin() {
+        for name in codec::NATIVES {
+            assert!(Builtin::of(name).is_some(), "{}", name);
+        }
+
+        assert_eq!(Builtin::of("nbt"), None);
+    }
+
+    #[test]
+    fn test_decode_bitfield() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+
+        // x = -1, z = 2, y = 64
+        let bytes = [0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x20, 0x40];
+        let expected = map(vec![
+            ("x", Value::I64(-1)),
+            ("z", Value::I64(2)),
+            ("y", Value::I64(64)),
+        ]);
+
+        assert_eq!(
+            decoder.decode_type("position", &bytes[..]).unwrap(),
+            expected
+        );
+
+        let data_type: DataType = serde_json::from_str(
+            r#"["bitfield", [
+                {"name": "empty", "size": 0, "signed": true},
+                {"name": "value", "size": 8, "signed": true}
+            ]]"#,
+        )
+        .unwrap();
+        let value = map(vec![("empty", Value::I64(0)), ("value", Value::I64(-2))]);
+        assert_eq!(decoder.decode(&data_type, &[0xfe][..]).unwrap(), value);
+
+        let mut bytes = Vec::new();
+        Encoder::new(&protocol)
+            .encode(&data_type, &value, &mut bytes)
+            .unwrap();
+        assert_eq!(bytes, [0xfe]);
+    }
+
+    #[test]
+    fn test_decode_entity_metadata_loop() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::new(&protocol);
+
+        let bytes = [0x00, 0x00, 0x05, 0x07, 0x01, 0x01, 0xff];
+        let expected = Value::List(vec![
+            map(vec![
+                ("key", Value::U8(0)),
+                ("type", Value::I32(0)),
+                ("value", Value::I8(5)),
+            ]),
+            map(vec![
+                ("key", Value::U8(7)),
+                ("type", Value::I32(1)),
+                ("value", Value::I32(1)),
+            ]),
+        ]);
+
+        assert_eq!(
+            decoder.decode_type("entityMetadata", &bytes[..]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_decode_top_bit_set_terminated_array() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let bytes = [0x01, 0x80, 0x00, 0x05, 0x00];
+        let expected = map(vec![
+            ("entityId", Value::I32(1)),
+            (
+                "equipments",
+                Value::List(vec![
+                    map(vec![
+                        ("slot", Value::I8(0)),
+                        ("item", map(vec![("present", Value::Bool(false))])),
+                    ]),
+                    map(vec![
+                        ("slot", Value::I8(5)),
+                        ("item", map(vec![("present", Value::Bool(false))])),
+                    ]),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(
+            decoder
+                .decode_type("packet_entity_equipment", &bytes[..])
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_trace_packet() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let bytes = [0x47, 0x01, 0x80, 0x00, 0x05, 0x00];
+        let span = decoder.trace_type("packet", &bytes[..]).unwrap();
+
+        let layout = |span: &codec::Span| -> Vec<(String, usize, usize)> {
+            span.children
+                .iter()
+                .map(|child| (child.name.clone(), child.offset, child.length))
+                .collect()
+        };
+
+        assert_eq!((span.offset, span.length), (0, 6));
+        assert_eq!(
+            span.value,
+            decoder.decode_type("packet", &bytes[..]).unwrap()
+        );
+        assert_eq!(
+            layout(&span),
+            vec![("name".to_owned(), 0, 1), ("params".to_owned(), 1, 5)]
+        );
+
+        let params = &span.children[1];
+        assert_eq!(
+            layout(params),
+            vec![
+                ("entityId".to_owned(), 1, 1),
+                ("equipments".to_owned(), 2, 4)
+            ]
+        );
+        assert_eq!(
+            layout(&params.children[1]),
+            vec![("[0]".to_owned(), 2, 2), ("[1]".to_owned(), 4, 2)]
+        );
+        assert_eq!(
+            layout(&params.children[1].children[0]),
+            vec![("slot".to_owned(), 2, 1), ("item".to_owned(), 3, 1)]
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown_namespace() {
+        let protocol = protocol("minecraft_v1662.json");
+
+        assert!(matches!(
+            Decoder::with_namespace(&protocol, "play.nowhere"),
+            Err(codec::Error::UnknownNamespace(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_natives() {
+        let protocol = protocol("minecraft_v1662.json");
+        let natives: Vec<String> = codec::unsupported_natives(&protocol)
+            .into_iter()
+            .map(|id| id.to_string())
+            .collect();
+
+        assert_eq!(natives, vec!["optionalNbt", "nbt"]);
+    }
+}
@@ -0,0 +1,890 @@
+use crate::codec::{lookup_field, mappings, matches_key, Builtin, Error, NativeRegistry, Result};
+use crate::resolve::Scope;
+use crate::value::Value;
+use crate::{
+    Array, ArrayCount, BitField, Buffer, ByteOrder, Conditional, DataType, Loop, Mapper, Numeric,
+    Primitive, Protocol, Structure, Switch, TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Value of fields missing from an encoded container.
+static VOID: Value = Value::Void;
+
+/// Encodes `Value`s into binary data according to the types of a protocol.
+///
+/// Lengths are computed from the encoded values: `count` fields, `countType`
+/// prefixes of arrays and buffers, and `pstring` prefixes do not need to be
+/// present or correct in the value.
+pub struct Encoder<'a> {
+    scope: Scope<'a>,
+    natives: Option<Arc<dyn NativeRegistry>>,
+}
+
+impl<'a> Encoder<'a> {
+    /// Creates an encoder resolving custom types against the root `types` of the protocol.
+    pub fn new(protocol: &'a Protocol) -> Self {
+        Encoder {
+            scope: Scope::new(protocol, "").expect("root scope always exists"),
+            natives: None,
+        }
+    }
+
+    /// Creates an encoder resolving custom types as seen from a namespace, e.g. `play.toClient`.
+    pub fn with_namespace(protocol: &'a Protocol, path: &str) -> Result<Self> {
+        Ok(Encoder {
+            scope: Scope::new(protocol, path)
+                .ok_or_else(|| Error::UnknownNamespace(path.to_owned()))?,
+            natives: None,
+        })
+    }
+
+    /// Encodes the native types implemented by a registry with it, before the built-in ones.
+    pub fn natives(mut self, natives: Arc<dyn NativeRegistry>) -> Self {
+        self.natives = Some(natives);
+        self
+    }
+
+    /// Encodes a value of the given type. A `&mut Vec<u8>` can be passed as the writer.
+    pub fn encode<W: Write>(
+        &self,
+        data_type: &DataType,
+        value: &Value,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut state = State {
+            scope: &self.scope,
+            natives: self.natives.as_deref(),
+            frames: Vec::new(),
+        };
+
+        state.encode(data_type, value, &mut writer)
+    }
+
+    /// Encodes a value of the type with the given name.
+    pub fn encode_type<W: Write>(&self, name: &str, value: &Value, writer: W) -> Result<()> {
+        let data_type = self
+            .scope
+            .lookup(name)
+            .ok_or_else(|| Error::UnknownType(name.to_owned()))?;
+
+        match data_type {
+            TypeDefinition::Native => {
+                self.encode(&DataType::Custom(name.to_owned()), value, writer)
+            }
+            TypeDefinition::DataType(data_type) => self.encode(data_type, value, writer),
+        }
+    }
+}
+
+struct State<'s, 'a, 'v> {
+    scope: &'s Scope<'a>,
+    natives: Option<&'s dyn NativeRegistry>,
+    /// Values of the containers being encoded, innermost last.
+    frames: Vec<&'v LinkedHashMap<String, Value>>,
+}
+
+impl<'s, 'a, 'v> State<'s, 'a, 'v> {
+    fn encode(
+        &mut self,
+        data_type: &DataType,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        match data_type {
+            DataType::Conditional(conditional) => {
+                self.encode_conditional(conditional, value, writer)
+            }
+            DataType::Numeric(numeric) => encode_numeric(numeric, value, writer),
+            DataType::Primitive(primitive) => encode_primitive(primitive, value, writer),
+            DataType::Structure(structure) => self.encode_structure(structure, value, writer),
+            DataType::Util(util) => self.encode_util(util, value, writer),
+            DataType::Parameterized { name, args } => {
+                let data_type = self
+                    .scope
+                    .instantiate(name, args)
+                    .ok_or_else(|| Error::UnknownType(name.clone()))?;
+
+                self.encode(&data_type, value, writer)
+            }
+            DataType::Custom(name) => {
+                let scope = self.scope;
+                match scope.lookup(name) {
+                    Some(TypeDefinition::Native) => {
+                        encode_native(self.natives, name, value, writer)
+                    }
+                    Some(TypeDefinition::DataType(data_type)) => {
+                        self.encode(data_type, value, writer)
+                    }
+                    None => Err(Error::UnknownType(name.clone())),
+                }
+            }
+        }
+    }
+
+    /// Encodes an integer computed by the encoder, like a length prefix.
+    fn encode_integer(
+        &mut self,
+        data_type: &DataType,
+        value: i128,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        match data_type {
+            DataType::Numeric(numeric) => encode_numeric(numeric, &integer(value)?, writer),
+            DataType::Custom(name) => {
+                let scope = self.scope;
+                match scope.lookup(name) {
+                    Some(TypeDefinition::Native) => {
+                        encode_native(self.natives, name, &integer(value)?, writer)
+                    }
+                    Some(TypeDefinition::DataType(data_type)) => {
+                        self.encode_integer(data_type, value, writer)
+                    }
+                    None => Err(Error::UnknownType(name.clone())),
+                }
+            }
+            _ => Err(Error::InvalidCount),
+        }
+    }
+
+    fn encode_conditional(
+        &mut self,
+        conditional: &Conditional,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        match conditional {
+            Conditional::Switch(switch) => self.encode_switch(switch, value, writer),
+            Conditional::Option(data_type) => match value {
+                Value::Option(None) | Value::Void => Ok(writer.write_all(&[0])?),
+                Value::Option(Some(value)) => {
+                    writer.write_all(&[1])?;
+                    self.encode(data_type, value, writer)
+                }
+                value => {
+                    writer.write_all(&[1])?;
+                    self.encode(data_type, value, writer)
+                }
+            },
+        }
+    }
+
+    fn encode_switch(
+        &mut self,
+        switch: &Switch,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let branch = {
+            let compare_value = lookup_field(&self.frames, &switch.compare_to)?;
+
+            switch
+                .fields
+                .iter()
+                .find(|(key, _)| matches_key(key, compare_value))
+                .map(|(_, data_type)| data_type)
+                .or(switch.default.as_ref())
+        };
+
+        match branch {
+            Some(data_type) => self.encode(data_type, value, writer),
+            None => Ok(()),
+        }
+    }
+
+    fn encode_structure(
+        &mut self,
+        structure: &Structure,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        match structure {
+            Structure::Array(array) => self.encode_array(array, value, writer),
+            Structure::Container(fields) => {
+                let values = match value {
+                    Value::Map(values) => values,
+                    value => return Err(mismatch("map", value)),
+                };
+
+                self.frames.push(values);
+
+                let result = fields.iter().try_for_each(|field| {
+                    let name = match &field.name {
+                        Some(name) if !field.is_anonymous() => name,
+                        // Anonymous fields are encoded from the parent container.
+                        _ => return self.encode(&field.field_type, value, writer),
+                    };
+
+                    match values.get(name) {
+                        Some(value) => self.encode(&field.field_type, value, writer),
+                        None => match self.encode(&field.field_type, &VOID, writer) {
+                            Err(Error::TypeMismatch { .. }) => {
+                                Err(Error::MissingField(name.clone()))
+                            }
+                            result => result,
+                        },
+                    }
+                });
+
+                self.frames.pop();
+                result
+            }
+            Structure::Count(count) => {
+                let length = match lookup_field(&self.frames, &count.count_for) {
+                    Ok(counted) => value_len(counted)?,
+                    Err(_) => return self.encode(&count.count_type, value, writer),
+                };
+
+                self.encode_integer(&count.count_type, length as i128, writer)
+            }
+        }
+    }
+
+    fn encode_array(
+        &mut self,
+        array: &Array,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let values = match value {
+            Value::List(values) => values,
+            value => return Err(mismatch("list", value)),
+        };
+
+        self.encode_count(
+            array.count_type.as_ref(),
+            array.count.as_ref(),
+            values.len(),
+            writer,
+        )?;
+
+        for value in values {
+            self.encode(&array.elements_type, value, writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_util(&mut self, util: &Util, value: &'v Value, writer: &mut dyn Write) -> Result<()> {
+        match util {
+            Util::Buffer(buffer) => self.encode_buffer(buffer, value, writer),
+            Util::Mapper(mapper) => self.encode_mapper(mapper, value, writer),
+            Util::Bitfield(fields) => encode_bitfield(fields, value, writer),
+            Util::PrefixedString { count_type } => {
+                let string = match value {
+                    Value::String(string) => string,
+                    value => return Err(mismatch("string", value)),
+                };
+
+                self.encode_integer(count_type, string.len() as i128, writer)?;
+                Ok(writer.write_all(string.as_bytes())?)
+            }
+            Util::Loop(loop_util) => self.encode_loop(loop_util, value, writer),
+            Util::TopBitSetTerminatedArray(structure) => {
+                let values = match value {
+                    Value::List(values) if !values.is_empty() => values,
+                    Value::List(_) => return Err(Error::InvalidTopBitSetTerminatedArray),
+                    value => return Err(mismatch("list", value)),
+                };
+
+                for (i, value) in values.iter().enumerate() {
+                    let mut bytes = Vec::new();
+                    self.encode_structure(structure, value, &mut bytes)?;
+
+                    match bytes.first_mut() {
+                        Some(first) if *first & 0x80 == 0 => {
+                            if i + 1 < values.len() {
+                                *first |= 0x80;
+                            }
+                        }
+                        _ => return Err(Error::InvalidTopBitSetTerminatedArray),
+                    }
+
+                    writer.write_all(&bytes)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn encode_buffer(
+        &mut self,
+        buffer: &Buffer,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let bytes = buffer_bytes(value)?;
+
+        if buffer.rest != Some(true) {
+            self.encode_count(
+                buffer.count_type.as_ref(),
+                buffer.count.as_ref(),
+                bytes.len(),
+                writer,
+            )?;
+        }
+
+        Ok(writer.write_all(&bytes)?)
+    }
+
+    fn encode_mapper(
+        &mut self,
+        mapper: &Mapper,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let (numeric, mappings) = mappings(mapper, self.scope)?;
+
+        let name = match value {
+            Value::String(name) => name,
+            // Unknown keys are decoded as-is.
+            value => return encode_numeric(&numeric, value, writer),
+        };
+
+        let key = mappings
+            .key(name)
+            .ok_or_else(|| Error::UnknownMapping(name.clone()))?;
+
+        encode_numeric(&numeric, &integer(key)?, writer)
+    }
+
+    fn encode_loop(
+        &mut self,
+        loop_util: &Loop,
+        value: &'v Value,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let values = match value {
+            Value::List(values) => values,
+            value => return Err(mismatch("list", value)),
+        };
+
+        for value in values {
+            self.encode(&loop_util.data_type, value, writer)?;
+        }
+
+        let end_val = u8::try_from(loop_util.end_val)
+            .map_err(|_| Error::OutOfRange(loop_util.end_val.into()))?;
+
+        Ok(writer.write_all(&[end_val])?)
+    }
+
+    /// Writes the length prefix, or checks the fixed length of an array or a buffer.
+    ///
+    /// A length referenced from another field is not checked, as that field
+    /// is either a computed `count` or is provided by the caller.
+    fn encode_count(
+        &mut self,
+        count_type: Option<&DataType>,
+        count: Option<&ArrayCount>,
+        length: usize,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        match (count_type, count) {
+            (Some(count_type), _) => self.encode_integer(count_type, length as i128, writer),
+            (None, Some(ArrayCount::FixedLength(expected))) if *expected as usize != length => {
+                Err(Error::CountMismatch {
+                    expected: *expected as usize,
+                    found: length,
+                })
+            }
+            (None, Some(_)) => Ok(()),
+            (None, None) => Err(Error::MissingCount),
+        }
+    }
+}
+
+fn encode_numeric(numeric: &Numeric, value: &Value, writer: &mut dyn Write) -> Result<()> {
+    macro_rules! write {
+        ($ty:ty, $byte_order:expr) => {{
+            let value = value.as_i128().ok_or_else(|| mismatch(stringify!($ty), value))?;
+            let value = <$ty>::try_from(value).map_err(|_| Error::OutOfRange(value))?;
+            write!(@bytes value, $byte_order)
+        }};
+        (@float $ty:ty, $byte_order:expr) => {{
+            let value = value.as_f64().ok_or_else(|| mismatch(stringify!($ty), value))?;
+            write!(@bytes value as $ty, $byte_order)
+        }};
+        (@bytes $value:expr, $byte_order:expr) => {
+            match $byte_order {
+                ByteOrder::BigEndian => writer.write_all(&$value.to_be_bytes())?,
+                ByteOrder::LittleEndian => writer.write_all(&$value.to_le_bytes())?,
+            }
+        };
+    }
+
+    match numeric {
+        Numeric::Byte { signed: true } => write!(i8, ByteOrder::BigEndian),
+        Numeric::Byte { signed: false } => write!(u8, ByteOrder::BigEndian),
+        Numeric::Short {
+            signed: true,
+            byte_order,
+        } => write!(i16, byte_order),
+        Numeric::Short {
+            signed: false,
+            byte_order,
+        } => write!(u16, byte_order),
+        Numeric::Int {
+            signed: true,
+            byte_order,
+        } => write!(i32, byte_order),
+        Numeric::Int {
+            signed: false,
+            byte_order,
+        } => write!(u32, byte_order),
+        Numeric::Long {
+            signed: true,
+            byte_order,
+        } => write!(i64, byte_order),
+        Numeric::Long {
+            signed: false,
+            byte_order,
+        } => write!(u64, byte_order),
+        // Also accepts the decimal strings 128-bit integers become in JSON.
+        Numeric::Int128 { byte_order, .. } => match value.coerce(numeric) {
+            Some(Value::I128(value)) => write!(@bytes value, byte_order),
+            Some(Value::U128(value)) => write!(@bytes value, byte_order),
+            _ => {
+                return Err(match value.as_i128() {
+                    Some(value) => Error::OutOfRange(value),
+                    None => mismatch(numeric.name(), value),
+                })
+            }
+        },
+        Numeric::Float { byte_order } => write!(@float f32, byte_order),
+        Numeric::Double { byte_order } => write!(@float f64, byte_order),
+        Numeric::VarInt => {
+            let value = value.as_i128().ok_or_else(|| mismatch("varint", value))?;
+            let value = i32::try_from(value).map_err(|_| Error::OutOfRange(value))?;
+            write_varint(value, writer)?;
+        }
+        Numeric::VarLong => {
+            let value = value.as_i128().ok_or_else(|| mismatch("varlong", value))?;
+            let value = i64::try_from(value).map_err(|_| Error::OutOfRange(value))?;
+            write_varlong(value, writer)?;
+        }
+        Numeric::ZigZag32 => {
+            let value = value.as_i128().ok_or_else(|| mismatch("zigzag32", value))?;
+            let value = i32::try_from(value).map_err(|_| Error::OutOfRange(value))?;
+            write_varint((value << 1) ^ (value >> 31), writer)?;
+        }
+        Numeric::ZigZag64 => {
+            let value = value.as_i128().ok_or_else(|| mismatch("zigzag64", value))?;
+            let value = i64::try_from(value).map_err(|_| Error::OutOfRange(value))?;
+            write_varlong((value << 1) ^ (value >> 63), writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn encode_primitive(primitive: &Primitive, value: &Value, writer: &mut dyn Write) -> Result<()> {
+    match (primitive, value) {
+        (Primitive::Boolean, Value::Bool(boolean)) => Ok(writer.write_all(&[*boolean as u8])?),
+        (Primitive::Boolean, value) => Err(mismatch("bool", value)),
+        (Primitive::String, Value::String(string)) => {
+            if string.contains('\0') {
+                return Err(Error::NullInString);
+            }
+
+            writer.write_all(string.as_bytes())?;
+            Ok(writer.write_all(&[0])?)
+        }
+        (Primitive::String, value) => Err(mismatch("string", value)),
+        (Primitive::Void, _) => Ok(()),
+    }
+}
+
+fn encode_bitfield(fields: &[BitField], value: &Value, writer: &mut dyn Write) -> Result<()> {
+    let values = match value {
+        Value::Map(values) => values,
+        value => return Err(mismatch("map", value)),
+    };
+
+    let total_size: usize = fields.iter().map(|field| field.size).sum();
+
+    if total_size > 128 || fields.iter().any(|field| field.size > 64) {
+        return Err(Error::BitfieldTooLarge(total_size));
+    }
+
+    let mut bits = 0u128;
+
+    for field in fields {
+        let value = values
+            .get(&field.name)
+            .ok_or_else(|| Error::MissingField(field.name.clone()))?;
+        let value = value.as_i128().ok_or_else(|| mismatch("integer", value))?;
+
+        let (min, max) = if field.signed && field.size > 0 {
+            (-(1i128 << field.size) / 2, (1i128 << field.size) / 2 - 1)
+        } else {
+            (0, (1i128 << field.size) - 1)
+        };
+
+        if value < min || value > max {
+            return Err(Error::OutOfRange(value));
+        }
+
+        let mask = (1u128 << field.size) - 1;
+        bits = (bits << field.size) | (value as u128 & mask);
+    }
+
+    let total_bytes = total_size.div_ceil(8);
+    bits <<= total_bytes * 8 - total_size;
+
+    Ok(writer.write_all(&bits.to_be_bytes()[16 - total_bytes..])?)
+}
+
+fn encode_native(
+    natives: Option<&dyn NativeRegistry>,
+    name: &str,
+    value: &Value,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    if let Some(natives) = natives.filter(|natives| natives.contains(name)) {
+        return natives.encode(name, value, writer);
+    }
+
+    match Builtin::of(name) {
+        Some(Builtin::RestBuffer) => Ok(writer.write_all(&buffer_bytes(value)?)?),
+        Some(Builtin::Numeric(numeric)) => encode_numeric(&numeric, value, writer),
+        None => Err(Error::UnsupportedNative(name.to_owned())),
+    }
+}
+
+/// The bytes of a buffer value.
+fn buffer_bytes(value: &Value) -> Result<Cow<'_, [u8]>> {
+    match value {
+        Value::Bytes(bytes) => Ok(Cow::Borrowed(bytes)),
+        // Buffers converted from JSON are lists of numbers.
+        Value::List(values) => values
+            .iter()
+            .map(|value| value.as_i128().and_then(|byte| u8::try_from(byte).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .map(Cow::Owned)
+            .ok_or_else(|| mismatch("bytes", value)),
+        value => Err(mismatch("bytes", value)),
+    }
+}
+
+fn write_varint(value: i32, writer: &mut dyn Write) -> Result<()> {
+    let mut value = value as u32;
+
+    loop {
+        if value & !0x7f == 0 {
+            return Ok(writer.write_all(&[value as u8])?);
+        }
+
+        writer.write_all(&[(value & 0x7f) as u8 | 0x80])?;
+        value >>= 7;
+    }
+}
+
+fn write_varlong(value: i64, writer: &mut dyn Write) -> Result<()> {
+    let mut value = value as u64;
+
+    loop {
+        if value & !0x7f == 0 {
+            return Ok(writer.write_all(&[value as u8])?);
+        }
+
+        writer.write_all(&[(value & 0x7f) as u8 | 0x80])?;
+        value >>= 7;
+    }
+}
+
+/// The length of a value counted by a `count` field.
+fn value_len(value: &Value) -> Result<usize> {
+    match value {
+        Value::List(values) => Ok(values.len()),
+        Value::Bytes(bytes) => Ok(bytes.len()),
+        Value::String(string) => Ok(string.len()),
+        value => Err(mismatch("list", value)),
+    }
+}
+
+fn integer(value: i128) -> Result<Value> {
+    i64::try_from(value)
+        .map(Value::I64)
+        .or_else(|_| u64::try_from(value).map(Value::U64))
+        .map_err(|_| Error::OutOfRange(value))
+}
+
+fn mismatch(expected: &'static str, found: &Value) -> Error {
+    Error::TypeMismatch {
+        expected,
+        found: found.kind(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{Decoder, Encoder};
+    use crate::value::Value;
+    use crate::*;
+
+    fn encode_type(encoder: &Encoder, name: &str, value: &Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        encoder
+            .encode_type(name, value, &mut bytes)
+            .expect("Failed to encode value");
+
+        bytes
+    }
+
+    #[test]
+    fn test_encode_varint() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::new(&protocol);
+        let data_type = DataType::Numeric(Numeric::VarInt);
+
+        let mut bytes = Vec::new();
+        encoder
+            .encode(&data_type, &Value::I32(-1), &mut bytes)
+            .unwrap();
+
+        assert_eq!(bytes, [0xff, 0xff, 0xff, 0xff, 0x0f]);
+    }
+
+    #[test]
+    fn test_encode_null_in_string() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::new(&protocol);
+        let data_type = DataType::Primitive(Primitive::String);
+
+        assert!(matches!(
+            encoder.encode(
+                &data_type,
+                &Value::String("a\0b".to_owned()),
+                &mut Vec::new()
+            ),
+            Err(codec::Error::NullInString)
+        ));
+    }
+
+    #[test]
+    fn test_encode_varlong_and_zigzag() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::new(&protocol);
+        let encode = |numeric: Numeric, value: Value| {
+            let mut bytes = Vec::new();
+            encoder
+                .encode(&DataType::Numeric(numeric), &value, &mut bytes)
+                .map(|_| bytes)
+        };
+
+        assert_eq!(
+            encode(Numeric::VarLong, Value::I64(-1)).unwrap(),
+            [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+        assert_eq!(encode(Numeric::ZigZag32, Value::I32(-2)).unwrap(), [0x03]);
+        assert_eq!(
+            encode(Numeric::ZigZag64, Value::I64(i64::MIN)).unwrap(),
+            [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+        assert!(matches!(
+            encode(Numeric::ZigZag32, Value::I64(1 << 31)),
+            Err(codec::Error::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_encode_uuid_from_json() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::with_namespace(&protocol, "play.toClient").unwrap();
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+        let bytes = [
+            0x01, b'a', 0x00, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x01, 0x23, 0x45,
+            0x67, 0x89, 0xab, 0xcd, 0xef,
+        ];
+
+        let json = serde_json::Value::from(decoder.decode_type("packet_chat", &bytes[..]).unwrap());
+        assert_eq!(
+            json["sender"],
+            serde_json::Value::String(0x1234_5678_9abc_def0_0123_4567_89ab_cdef_u128.to_string())
+        );
+
+        assert_eq!(
+            encode_type(&encoder, "packet_chat", &Value::from(json)),
+            bytes
+        );
+    }
+
+    #[test]
+    fn test_encode_out_of_range() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::new(&protocol);
+        let data_type = DataType::Numeric(Numeric::Byte { signed: false });
+
+        assert!(matches!(
+            encoder.encode(&data_type, &Value::I32(256), &mut Vec::new()),
+            Err(codec::Error::OutOfRange(256))
+        ));
+    }
+
+    #[test]
+    fn test_encode_handshake_packet() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::with_namespace(&protocol, "handshaking.toServer").unwrap();
+
+        let value = map(vec![
+            ("name", Value::String("set_protocol".to_owned())),
+            (
+                "params",
+                map(vec![
+                    ("protocolVersion", Value::I32(498)),
+                    ("serverHost", Value::String("localhost".to_owned())),
+                    ("serverPort", Value::U16(25565)),
+                    ("nextState", Value::I32(1)),
+                ]),
+            ),
+        ]);
+
+        let expected = [
+            0x00, 0xf2, 0x03, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63,
+            0xdd, 0x01,
+        ];
+
+        assert_eq!(encode_type(&encoder, "packet", &value), expected);
+    }
+
+    #[test]
+    fn test_encode_count_fields() {
+        let protocol = protocol("minecraft_v170.json");
+        let encoder = Encoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let column = map(vec![
+            ("x", Value::I32(1)),
+            ("z", Value::I32(2)),
+            ("bitMap", Value::U16(3)),
+            ("addBitMap", Value::U16(4)),
+        ]);
+
+        let value = map(vec![
+            ("skyLightSent", Value::Bool(true)),
+            ("compressedChunkData", Value::Bytes(vec![0xaa, 0xbb, 0xcc])),
+            ("meta", Value::List(vec![column])),
+        ]);
+
+        let expected = [
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0xaa, 0xbb, 0xcc, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04,
+        ];
+
+        assert_eq!(
+            encode_type(&encoder, "packet_map_chunk_bulk", &value),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_encode_fixed_length_mismatch() {
+        let protocol = protocol("diablo2.json");
+        let encoder = Encoder::with_namespace(&protocol, "toServer").unwrap();
+
+        let value = map(vec![
+            ("MCPCookie", Value::U32(1)),
+            ("MCPStatus", Value::U32(2)),
+            ("MCPChunk1", Value::List(vec![Value::U32(3)])),
+        ]);
+
+        assert!(matches!(
+            encoder.encode_type("MCP_STARTUP", &value, &mut Vec::new()),
+            Err(codec::Error::CountMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_encode_missing_field() {
+        let protocol = protocol("diablo2.json");
+        let encoder = Encoder::with_namespace(&protocol, "toServer").unwrap();
+
+        let value = map(vec![("requestId", Value::U16(1))]);
+
+        assert!(matches!(
+            encoder.encode_type("MCP_JOINGAME", &value, &mut Vec::new()),
+            Err(codec::Error::MissingField(name)) if name == "gameName"
+        ));
+    }
+
+    #[test]
+    fn test_encode_mapper_keys() {
+        let protocol: Protocol = serde_json::from_str(
+            r#"{"types": {
+                "id": "i8",
+                "kind": ["mapper", {"type": "id", "mappings": {"0x0a": "ten", "-1": "minus"}}],
+                "broken": ["mapper", {"type": "u8", "mappings": {"0": "a", "0x00": "b"}}]
+            }}"#,
+        )
+        .unwrap();
+        let encoder = Encoder::new(&protocol);
+        let decoder = Decoder::new(&protocol);
+
+        assert_eq!(
+            decoder.decode_type("kind", &[0x0a][..]).unwrap(),
+            Value::from("ten")
+        );
+        assert_eq!(
+            decoder.decode_type("kind", &[0xff][..]).unwrap(),
+            Value::from("minus")
+        );
+        assert_eq!(
+            decoder.decode_type("kind", &[0x05][..]).unwrap(),
+            Value::I8(5)
+        );
+
+        assert_eq!(encode_type(&encoder, "kind", &Value::from("minus")), [0xff]);
+        assert_eq!(encode_type(&encoder, "kind", &Value::I8(5)), [0x05]);
+        assert!(matches!(
+            encoder.encode_type("kind", &Value::from("one"), &mut Vec::new()),
+            Err(codec::Error::UnknownMapping(name)) if name == "one"
+        ));
+
+        assert!(matches!(
+            decoder.decode_type("broken", &[0x00][..]),
+            Err(codec::Error::InvalidMapper(
+                mapping::MappingError::DuplicateKey { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let protocol = protocol("minecraft_v1662.json");
+        let encoder = Encoder::with_namespace(&protocol, "play.toClient").unwrap();
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let samples: &[(&str, &[u8])] = &[
+            (
+                "position",
+                &[0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x20, 0x40],
+            ),
+            ("particle", &[0x03, 0x2a]),
+            (
+                "entityMetadata",
+                &[0x00, 0x00, 0x05, 0x07, 0x01, 0x01, 0xff],
+            ),
+            ("packet_entity_equipment", &[0x01, 0x80, 0x00, 0x05, 0x00]),
+            ("slot", &[0x00]),
+            (
+                "packet_chat",
+                &[
+                    0x01, b'a', 0x00, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x01, 0x23,
+                    0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+                ],
+            ),
+        ];
+
+        for (name, bytes) in samples {
+            let value = decoder.decode_type(name, *bytes).unwrap();
+            assert_eq!(encode_type(&encoder, name, &value), *bytes, "{}", name);
+        }
+    }
+}
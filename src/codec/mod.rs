@@ -0,0 +1,353 @@
+//! Runtime interpretation of a parsed `Protocol` against binary data.
+
+mod borrowed;
+mod decode;
+mod encode;
+mod native;
+mod plan;
+
+pub use borrowed::ContainerView;
+pub use decode::{Decoder, Span};
+pub use encode::Encoder;
+pub use native::{DecodeFn, EncodeFn, NativeRegistry, Natives};
+pub use plan::Plan;
+
+use crate::mapping::{numeric_type, MappingError, Mappings};
+use crate::resolve::{Scope, TypeId};
+use crate::value::{Value, ValueRef};
+use crate::{ByteOrder, DataType, Mapper, Numeric, Protocol};
+use linked_hash_map::LinkedHashMap;
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Native types implemented by the decoder and the encoder.
+pub const NATIVES: &[&str] = &["restBuffer", "UUID"];
+
+/// The layout of the `UUID` native type: 16 bytes, most significant first.
+pub(crate) const UUID: Numeric = Numeric::Int128 {
+    signed: false,
+    byte_order: ByteOrder::BigEndian,
+};
+
+/// How the decoders and the encoder implement a native type of `NATIVES`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Builtin {
+    /// The rest of the input, as a buffer.
+    RestBuffer,
+    /// A number of the given layout, e.g. `UUID`.
+    Numeric(Numeric),
+}
+
+impl Builtin {
+    /// The implementation of the native type with the given name, if supported.
+    pub(crate) fn of(name: &str) -> Option<Self> {
+        match name {
+            "restBuffer" => Some(Builtin::RestBuffer),
+            "UUID" => Some(Builtin::Numeric(UUID)),
+            _ => None,
+        }
+    }
+}
+
+/// Native types required by a protocol that the decoder and the encoder do not implement.
+pub fn unsupported_natives(protocol: &Protocol) -> Vec<TypeId> {
+    protocol
+        .required_natives()
+        .into_iter()
+        .filter(|id| !NATIVES.contains(&id.name.as_str()))
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The namespace path does not exist in the protocol.
+    UnknownNamespace(String),
+    /// A `DataType::Custom` name is not defined in the scope.
+    UnknownType(String),
+    /// A type is declared as native but has no built-in implementation.
+    UnsupportedNative(String),
+    /// A `compareTo` or `count` reference does not point to a decoded field.
+    UnresolvedField(String),
+    /// A value used as a length is not a non-negative integer.
+    InvalidCount,
+    /// An array or buffer has neither a count type nor a count.
+    MissingCount,
+    InvalidUtf8(Utf8Error),
+    /// A `cstring` value contains a null character, which would end it early.
+    NullInString,
+    VarIntTooLong,
+    /// A bitfield spans more bits than supported.
+    BitfieldTooLarge(usize),
+    /// A value does not have the shape expected by its type.
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A container field is missing from the encoded value.
+    MissingField(String),
+    /// An integer does not fit into its type.
+    OutOfRange(i128),
+    /// A value does not have the length required by its fixed-length type.
+    CountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// A mapper has no key for the encoded name.
+    UnknownMapping(String),
+    /// The keys of a mapper are not integers of its type, or are ambiguous.
+    InvalidMapper(MappingError),
+    /// A top bit set terminated array has no elements, or an element starts with the top bit set.
+    InvalidTopBitSetTerminatedArray,
+    /// A container view was requested of a type that is not a container.
+    NotAContainer,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::UnknownNamespace(path) => write!(f, "unknown namespace \"{}\"", path),
+            Error::UnknownType(name) => write!(f, "unknown type \"{}\"", name),
+            Error::UnsupportedNative(name) => write!(f, "unsupported native type \"{}\"", name),
+            Error::UnresolvedField(path) => write!(f, "unresolved field reference \"{}\"", path),
+            Error::InvalidCount => f.write_str("count is not a non-negative integer"),
+            Error::MissingCount => f.write_str("neither count type nor count is specified"),
+            Error::InvalidUtf8(err) => write!(f, "invalid utf-8 string: {}", err),
+            Error::NullInString => f.write_str("string contains a null character"),
+            Error::VarIntTooLong => f.write_str("varint is too long"),
+            Error::BitfieldTooLarge(size) => write!(f, "bitfield of {} bits is too large", size),
+            Error::TypeMismatch { expected, found } => {
+                write!(f, "expected {} value, found {}", expected, found)
+            }
+            Error::MissingField(name) => write!(f, "missing field \"{}\"", name),
+            Error::OutOfRange(value) => write!(f, "integer {} is out of range", value),
+            Error::CountMismatch { expected, found } => {
+                write!(f, "expected {} elements, found {}", expected, found)
+            }
+            Error::UnknownMapping(name) => write!(f, "no mapping for \"{}\"", name),
+            Error::InvalidMapper(err) => write!(f, "invalid mapper: {}", err),
+            Error::InvalidTopBitSetTerminatedArray => {
+                f.write_str("invalid top bit set terminated array")
+            }
+            Error::NotAContainer => f.write_str("type is not a container"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::InvalidUtf8(err) => Some(err),
+            Error::InvalidMapper(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<MappingError> for Error {
+    fn from(err: MappingError) -> Self {
+        Error::InvalidMapper(err)
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Self {
+        Error::InvalidUtf8(err.utf8_error())
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(err: Utf8Error) -> Self {
+        Error::InvalidUtf8(err)
+    }
+}
+
+/// Parses a switch or mapper key, which may be written in decimal or hex (`0x1f`).
+pub(crate) fn parse_integer_key(key: &str) -> Option<i128> {
+    let (negative, digits) = match key.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, key),
+    };
+
+    let (radix, digits) = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => (16, hex),
+        None => (10, digits),
+    };
+
+    // `from_str_radix` would accept another sign in front of the digits.
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+
+    let value = i128::from_str_radix(digits, radix).ok()?;
+
+    if negative {
+        value.checked_neg()
+    } else {
+        Some(value)
+    }
+}
+
+/// The underlying type of a mapper, e.g. `varint`.
+pub(crate) fn mapper_type(mapper: &Mapper) -> DataType {
+    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+        mapper.mappings_type.as_str().into_deserializer();
+
+    DataType::deserialize(deserializer)
+        .unwrap_or_else(|_| DataType::Custom(mapper.mappings_type.clone()))
+}
+
+/// The underlying numeric type of a mapper, as seen from the scope, and its mappings.
+pub(crate) fn mappings(
+    mapper: &Mapper,
+    scope: &Scope,
+) -> std::result::Result<(Numeric, Mappings), MappingError> {
+    let numeric = numeric_type(mapper, scope)
+        .ok_or_else(|| MappingError::UnsupportedType(mapper.mappings_type.clone()))?;
+    let mappings = Mappings::new(mapper, &numeric)?;
+
+    Ok((numeric, mappings))
+}
+
+/// What a value is compared with switch keys as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Selector<'v> {
+    /// A string, or a boolean as `true` or `false`.
+    Name(&'v str),
+    Integer(i128),
+}
+
+impl<'v> Selector<'v> {
+    pub(crate) fn of<V: DecodedValue>(value: &'v V) -> Option<Self> {
+        if let Some(string) = value.as_str() {
+            return Some(Selector::Name(string));
+        }
+
+        match value.as_bool() {
+            Some(boolean) => Some(Selector::Name(if boolean { "true" } else { "false" })),
+            None => value.as_i128().map(Selector::Integer),
+        }
+    }
+}
+
+/// Whether a switch or mapper key selects the given value.
+pub(crate) fn matches_key<V: DecodedValue>(key: &str, value: &V) -> bool {
+    match Selector::of(value) {
+        Some(Selector::Name(name)) => key == name,
+        Some(Selector::Integer(integer)) => parse_integer_key(key) == Some(integer),
+        None => false,
+    }
+}
+
+/// The count of an array or a buffer given by a length prefix or a field.
+pub(crate) fn value_to_count<V: DecodedValue>(value: &V) -> Result<usize> {
+    value
+        .as_i128()
+        .and_then(|count| usize::try_from(count).ok())
+        .ok_or(Error::InvalidCount)
+}
+
+/// Values produced by the decoders, which field references point into and
+/// switch keys and counts are read from.
+pub(crate) trait DecodedValue {
+    /// A field of a map value.
+    fn field(&self, name: &str) -> Option<&Self>;
+
+    fn as_str(&self) -> Option<&str>;
+
+    fn as_bool(&self) -> Option<bool>;
+
+    fn as_i128(&self) -> Option<i128>;
+}
+
+impl DecodedValue for Value {
+    fn field(&self, name: &str) -> Option<&Self> {
+        self.get(name)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        Value::as_str(self)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        Value::as_bool(self)
+    }
+
+    fn as_i128(&self) -> Option<i128> {
+        Value::as_i128(self)
+    }
+}
+
+impl DecodedValue for ValueRef<'_> {
+    fn field(&self, name: &str) -> Option<&Self> {
+        self.get(name)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        ValueRef::as_str(self)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            ValueRef::Owned(value) => value.as_bool(),
+            _ => None,
+        }
+    }
+
+    fn as_i128(&self) -> Option<i128> {
+        ValueRef::as_i128(self)
+    }
+}
+
+/// Resolves a reference like `name`, `../action` or `flags/present`
+/// against the values of the enclosing containers, innermost last.
+pub(crate) fn lookup_field<'v, V, M>(frames: &'v [M], path: &str) -> Result<&'v V>
+where
+    V: DecodedValue,
+    M: Borrow<LinkedHashMap<String, V>>,
+{
+    let unresolved = || Error::UnresolvedField(path.to_owned());
+
+    let mut depth = frames.len();
+    let mut current: Option<&V> = None;
+
+    for segment in path.split('/') {
+        match (segment, current) {
+            ("" | ".", _) => {}
+            ("..", None) if depth > 1 => depth -= 1,
+            (name, None) if depth > 0 => {
+                current = Some(
+                    frames[depth - 1]
+                        .borrow()
+                        .get(name)
+                        .ok_or_else(unresolved)?,
+                );
+            }
+            (name, Some(value)) => {
+                current = Some(value.field(name).ok_or_else(unresolved)?);
+            }
+            _ => return Err(unresolved()),
+        }
+    }
+
+    current.ok_or_else(unresolved)
+}
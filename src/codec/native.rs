@@ -0,0 +1,192 @@
+//! Native types implemented outside of the crate, registered by name.
+
+use crate::codec::{Error, Result};
+use crate::size::SizeInfo;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Reads a value of a native type.
+pub type DecodeFn = dyn Fn(&mut dyn Read) -> Result<Value> + Send + Sync;
+
+/// Writes a value of a native type.
+pub type EncodeFn = dyn Fn(&Value, &mut dyn Write) -> Result<()> + Send + Sync;
+
+/// Implementations of native types with custom wire formats, like `nbt`.
+///
+/// The decoder, the encoder, compiled plans and size analysis consult the
+/// registry whenever a `DataType::Custom` resolves to a native type, before
+/// falling back to the built-in `NATIVES`.
+pub trait NativeRegistry: Send + Sync {
+    /// Whether the registry implements the native type.
+    fn contains(&self, name: &str) -> bool;
+
+    fn decode(&self, name: &str, reader: &mut dyn Read) -> Result<Value>;
+
+    fn encode(&self, name: &str, value: &Value, writer: &mut dyn Write) -> Result<()>;
+
+    /// The number of bytes values of the native type take.
+    fn size(&self, name: &str) -> SizeInfo;
+}
+
+struct Native {
+    size: SizeInfo,
+    decode: Box<DecodeFn>,
+    encode: Box<EncodeFn>,
+}
+
+/// A `NativeRegistry` of closures registered by name.
+#[derive(Default)]
+pub struct Natives {
+    natives: HashMap<String, Native>,
+}
+
+impl Natives {
+    pub fn new() -> Self {
+        Natives::default()
+    }
+
+    /// Registers the closures implementing a native type, replacing any registered before.
+    pub fn register<D, E>(mut self, name: &str, size: SizeInfo, decode: D, encode: E) -> Self
+    where
+        D: Fn(&mut dyn Read) -> Result<Value> + Send + Sync + 'static,
+        E: Fn(&Value, &mut dyn Write) -> Result<()> + Send + Sync + 'static,
+    {
+        self.natives.insert(
+            name.to_owned(),
+            Native {
+                size,
+                decode: Box::new(decode),
+                encode: Box::new(encode),
+            },
+        );
+        self
+    }
+
+    fn get(&self, name: &str) -> Result<&Native> {
+        self.natives
+            .get(name)
+            .ok_or_else(|| Error::UnsupportedNative(name.to_owned()))
+    }
+}
+
+impl NativeRegistry for Natives {
+    fn contains(&self, name: &str) -> bool {
+        self.natives.contains_key(name)
+    }
+
+    fn decode(&self, name: &str, reader: &mut dyn Read) -> Result<Value> {
+        (self.get(name)?.decode)(reader)
+    }
+
+    fn encode(&self, name: &str, value: &Value, writer: &mut dyn Write) -> Result<()> {
+        (self.get(name)?.encode)(value, writer)
+    }
+
+    fn size(&self, name: &str) -> SizeInfo {
+        self.natives
+            .get(name)
+            .map_or(SizeInfo::Variable, |native| native.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{Decoder, Encoder, NativeRegistry, Natives};
+    use crate::resolve::Scope;
+    use crate::size::*;
+    use crate::value::Value;
+    use crate::*;
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+
+    /// A buffer prefixed with its length in one byte.
+    fn blob() -> Natives {
+        Natives::new().register(
+            "blob",
+            SizeInfo::Bounded(1, 256),
+            |reader: &mut dyn Read| {
+                let mut length = [0];
+                reader.read_exact(&mut length)?;
+                let mut bytes = vec![0; length[0].into()];
+                reader.read_exact(&mut bytes)?;
+
+                Ok(Value::Bytes(bytes))
+            },
+            |value: &Value, writer: &mut dyn Write| {
+                let bytes = value.as_bytes().ok_or(codec::Error::TypeMismatch {
+                    expected: "bytes",
+                    found: value.kind(),
+                })?;
+                writer.write_all(&[bytes.len() as u8])?;
+
+                Ok(writer.write_all(bytes)?)
+            },
+        )
+    }
+
+    fn blob_protocol() -> Protocol {
+        serde_json::from_str(
+            r#"{"types": {
+                "blob": "native",
+                "entry": ["container", [
+                    {"name": "id", "type": "u8"},
+                    {"name": "data", "type": "blob"},
+                    {"name": "flag", "type": "bool"}
+                ]]
+            }}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_registered_natives() {
+        let protocol = blob_protocol();
+        let natives: Arc<dyn NativeRegistry> = Arc::new(blob());
+        let decoder = Decoder::new(&protocol).natives(natives.clone());
+        let encoder = Encoder::new(&protocol).natives(natives.clone());
+        let bytes = [0x07, 0x02, 0xaa, 0xbb, 0x01];
+
+        let value = decoder.decode_type("entry", &bytes[..]).unwrap();
+        assert_eq!(value.get("data"), Some(&Value::Bytes(vec![0xaa, 0xbb])));
+        assert_eq!(value.get("flag"), Some(&Value::Bool(true)));
+
+        let plan = decoder.compile("entry").unwrap();
+        assert_eq!(plan.decode(&mut &bytes[..]).unwrap(), value);
+        assert_eq!(
+            decoder
+                .decode_type_borrowed("entry", &mut &bytes[..])
+                .unwrap()
+                .to_value(),
+            value
+        );
+
+        let mut encoded = Vec::new();
+        encoder.encode_type("entry", &value, &mut encoded).unwrap();
+        assert_eq!(encoded, bytes);
+
+        let scope = Scope::new(&protocol, "").unwrap();
+        let entry = DataType::Custom("entry".to_owned());
+        assert_eq!(
+            size_of_with_natives(&entry, &scope, &*natives),
+            SizeInfo::Bounded(3, 258)
+        );
+        assert_eq!(size_of(&entry, &scope), SizeInfo::Variable);
+    }
+
+    #[test]
+    fn test_unregistered_natives() {
+        let protocol = blob_protocol();
+        let decoder = Decoder::new(&protocol).natives(Arc::new(Natives::new()));
+
+        assert!(matches!(
+            decoder.decode_type("entry", &[0x07, 0x00, 0x01][..]),
+            Err(codec::Error::UnsupportedNative(name)) if name == "blob"
+        ));
+        assert!(matches!(
+            decoder.compile("entry").unwrap().decode(&mut &[0x07, 0x00, 0x01][..]),
+            Err(codec::Error::UnsupportedNative(name)) if name == "blob"
+        ));
+        assert!(!blob().contains("nbt"));
+    }
+}
@@ -0,0 +1,1081 @@
+//! Decoding plans: types lowered into a flat instruction program with type names
+//! resolved, field references bound to container slots where possible, and switch
+//! and mapper keys parsed into lookup tables, run without walking the type tree.
+
+use crate::codec::decode::{decode_bitfield, decode_numeric, decode_primitive};
+use crate::codec::{
+    mappings, parse_integer_key, value_to_count, Builtin, Decoder, Error, NativeRegistry, Result,
+    Selector,
+};
+use crate::mapping::{MappingError, Mappings};
+use crate::resolve::Scope;
+use crate::size::size_with;
+use crate::value::Value;
+use crate::{
+    ArrayCount, BitField, Conditional, DataType, Field, Numeric, Primitive, Structure,
+    TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+/// A compiled program decoding values of one type.
+pub struct Plan {
+    ops: Vec<Op>,
+    layouts: Vec<Layout>,
+    fixed_size: Option<usize>,
+    /// The registry of the decoder, decoding the natives it implements.
+    natives: Option<Arc<dyn NativeRegistry>>,
+}
+
+impl fmt::Debug for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Plan")
+            .field("ops", &self.ops)
+            .field("layouts", &self.layouts)
+            .field("fixed_size", &self.fixed_size)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+enum Op {
+    /// Pushes a number.
+    Numeric(Numeric),
+    /// Pushes a `bool` or `void`.
+    Primitive(Primitive),
+    /// Pushes a null-terminated string.
+    CString,
+    /// Pushes a string of the popped count of bytes.
+    String,
+    /// Pushes a buffer of the popped count of bytes.
+    Bytes,
+    /// Pushes the rest of the input.
+    RestBytes,
+    /// Pushes a value of a native type decoded by the registry.
+    Native(String),
+    Bitfield(Vec<BitField>),
+    /// Replaces the value on top with its mapped name, if any.
+    Mapper(Mappings),
+    /// Pops a value and pushes it as a count.
+    Count,
+    /// Pushes the value of a field as a count.
+    LoadCount(FieldRef),
+    FixedCount(usize),
+    /// Fails unless this many bytes are left.
+    Ensure(usize),
+    /// Starts the container with the given layout.
+    BeginContainer(usize),
+    /// Pops a value into a slot of the current container.
+    Store(usize),
+    /// Pushes the current container.
+    EndContainer,
+    /// Starts a list, popping its length if counted.
+    BeginList {
+        counted: bool,
+    },
+    /// Jumps to `end` once the count of elements is read.
+    NextElement {
+        end: usize,
+    },
+    /// Jumps to `end` past a terminating byte.
+    NextLooped {
+        end_val: u32,
+        end: usize,
+    },
+    /// Jumps to `end` after an element with the top bit clear, otherwise reads
+    /// the next element with the top bit of its first byte cleared.
+    NextTopBitSet {
+        end: usize,
+    },
+    /// Pops a value into the current list.
+    Append,
+    /// Pushes the current list.
+    EndList,
+    /// Reads the presence byte of an option, pushing `None` and jumping to `end` if absent.
+    OptionFlag {
+        end: usize,
+    },
+    /// Wraps the value on top into `Some`.
+    WrapSome,
+    /// Jumps to the case selected by a field, or pushes `void` and jumps to `end`.
+    Switch {
+        field: FieldRef,
+        cases: Cases<usize>,
+        default: Option<usize>,
+        end: usize,
+    },
+    Jump(usize),
+    Call(usize),
+    Return,
+    Fail(Failure),
+}
+
+#[derive(Debug)]
+enum Failure {
+    UnknownType(String),
+    UnsupportedNative(String),
+    InvalidMapper(MappingError),
+    MissingCount,
+}
+
+/// Names of the fields of a container by slot.
+#[derive(Debug)]
+struct Layout {
+    slots: Vec<Slot>,
+}
+
+#[derive(Debug)]
+enum Slot {
+    Named(String),
+    /// An anonymous field, or an unnamed one, merged into the container if it is a map.
+    Merged,
+}
+
+/// A reference to a field like `../action`.
+#[derive(Debug)]
+enum FieldRef {
+    /// A field of an enclosing container of the same plan procedure, `up` levels out.
+    Slot {
+        up: usize,
+        slot: usize,
+        rest: Vec<String>,
+        path: String,
+    },
+    /// A field looked up by name, e.g. in the container a type is used in.
+    Path {
+        segments: Vec<Segment>,
+        path: String,
+    },
+}
+
+#[derive(Debug)]
+enum Segment {
+    Parent,
+    Name(String),
+}
+
+/// Keys of switch cases, matched the way `matches_key` does.
+#[derive(Debug)]
+struct Cases<T> {
+    strings: HashMap<String, T>,
+    integers: HashMap<i128, T>,
+}
+
+impl<T> Cases<T> {
+    fn new() -> Self {
+        Cases {
+            strings: HashMap::new(),
+            integers: HashMap::new(),
+        }
+    }
+
+    /// Adds a key, unless an earlier key matches the same values.
+    fn insert(&mut self, key: &str, target: T)
+    where
+        T: Clone,
+    {
+        if let Some(integer) = parse_integer_key(key) {
+            self.integers
+                .entry(integer)
+                .or_insert_with(|| target.clone());
+        }
+
+        self.strings.entry(key.to_owned()).or_insert(target);
+    }
+
+    fn get(&self, value: &Value) -> Option<&T> {
+        match Selector::of(value)? {
+            Selector::Name(name) => self.strings.get(name),
+            Selector::Integer(integer) => self.integers.get(&integer),
+        }
+    }
+}
+
+impl<'a> Decoder<'a> {
+    /// Compiles a plan decoding the type with the given name the way this decoder does.
+    pub fn compile(&self, name: &str) -> Result<Plan> {
+        let data_type = self.named_type(name)?;
+        let mut compiler = Compiler {
+            scope: &self.scope,
+            natives: self.natives.as_deref(),
+            ops: Vec::new(),
+            layouts: Vec::new(),
+            containers: Vec::new(),
+            procedures: HashMap::new(),
+            pending: Vec::new(),
+            starts: Vec::new(),
+        };
+
+        compiler.compile(&data_type);
+        compiler.ops.push(Op::Return);
+
+        while let Some((id, data_type)) = compiler.pending.pop() {
+            compiler.starts[id] = compiler.ops.len();
+            compiler.containers.clear();
+            compiler.compile(&data_type);
+            compiler.ops.push(Op::Return);
+        }
+
+        let starts = compiler.starts;
+        let mut ops = compiler.ops;
+        for op in &mut ops {
+            if let Op::Call(id) = op {
+                *op = Op::Call(starts[*id]);
+            }
+        }
+
+        Ok(Plan {
+            ops,
+            layouts: compiler.layouts,
+            fixed_size: size_with(&data_type, &self.scope, self.natives.as_deref()).fixed(),
+            natives: self.natives.clone(),
+        })
+    }
+}
+
+impl Plan {
+    /// Decodes a value from the start of `bytes`, advancing it past the bytes read.
+    pub fn decode(&self, bytes: &mut &[u8]) -> Result<Value> {
+        let mut machine = Machine {
+            input: Input { bytes, patch: None },
+            stack: Vec::new(),
+            counts: Vec::new(),
+            frames: Vec::new(),
+            lists: Vec::new(),
+            calls: Vec::new(),
+        };
+
+        machine.run(self)?;
+        *bytes = machine.input.bytes;
+
+        Ok(machine.stack.pop().expect("decoded value"))
+    }
+
+    /// The number of bytes every value of the type takes, if it is the same for all.
+    pub fn fixed_size(&self) -> Option<usize> {
+        self.fixed_size
+    }
+
+    /// The number of instructions.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+struct Compiler<'s, 'a> {
+    scope: &'s Scope<'a>,
+    natives: Option<&'s dyn NativeRegistry>,
+    ops: Vec<Op>,
+    layouts: Vec<Layout>,
+    /// Layouts of the containers being compiled in the current procedure, innermost last.
+    containers: Vec<usize>,
+    /// Procedure IDs of the types compiled as procedures.
+    procedures: HashMap<String, usize>,
+    /// Procedures to compile.
+    pending: Vec<(usize, DataType)>,
+    /// Start of each procedure by ID.
+    starts: Vec<usize>,
+}
+
+impl<'s, 'a> Compiler<'s, 'a> {
+    fn compile(&mut self, data_type: &DataType) {
+        match data_type {
+            DataType::Conditional(conditional) => match conditional.as_ref() {
+                Conditional::Switch(switch) => {
+                    let field = self.field_ref(&switch.compare_to);
+                    let at = self.emit(Op::Jump(0));
+                    let mut ends = Vec::new();
+                    let mut cases = Cases::new();
+
+                    for (key, data_type) in &switch.fields {
+                        cases.insert(key, self.ops.len());
+                        self.compile(data_type);
+                        ends.push(self.emit(Op::Jump(0)));
+                    }
+
+                    let default = switch.default.as_ref().map(|data_type| {
+                        let start = self.ops.len();
+                        self.compile(data_type);
+                        start
+                    });
+
+                    let end = self.ops.len();
+                    for at in ends {
+                        self.ops[at] = Op::Jump(end);
+                    }
+                    self.ops[at] = Op::Switch {
+                        field,
+                        cases,
+                        default,
+                        end,
+                    };
+                }
+                Conditional::Option(data_type) => {
+                    let at = self.emit(Op::OptionFlag { end: 0 });
+                    self.compile(data_type);
+                    self.emit(Op::WrapSome);
+                    self.ops[at] = Op::OptionFlag {
+                        end: self.ops.len(),
+                    };
+                }
+            },
+            DataType::Numeric(numeric) => {
+                self.emit(Op::Numeric(numeric.clone()));
+            }
+            DataType::Primitive(Primitive::String) => {
+                self.emit(Op::CString);
+            }
+            DataType::Primitive(primitive) => {
+                self.emit(Op::Primitive(primitive.clone()));
+            }
+            DataType::Structure(structure) => self.compile_structure(structure),
+            DataType::Util(util) => self.compile_util(util),
+            DataType::Parameterized { name, args } => {
+                let key = format!("{}{:?}", name, args);
+                match self.scope.instantiate(name, args) {
+                    Some(data_type) => self.call(key, data_type),
+                    None => {
+                        self.emit(Op::Fail(Failure::UnknownType(name.clone())));
+                    }
+                }
+            }
+            DataType::Custom(name) => match self.scope.lookup(name) {
+                Some(TypeDefinition::Native)
+                    if self.natives.is_some_and(|natives| natives.contains(name)) =>
+                {
+                    self.emit(Op::Native(name.clone()));
+                }
+                Some(TypeDefinition::Native) => match Builtin::of(name) {
+                    Some(Builtin::RestBuffer) => {
+                        self.emit(Op::RestBytes);
+                    }
+                    Some(Builtin::Numeric(numeric)) => {
+                        self.emit(Op::Numeric(numeric));
+                    }
+                    None => {
+                        self.emit(Op::Fail(Failure::UnsupportedNative(name.clone())));
+                    }
+                },
+                Some(TypeDefinition::DataType(data_type)) => {
+                    self.call(name.clone(), data_type.clone())
+                }
+                None => {
+                    self.emit(Op::Fail(Failure::UnknownType(name.clone())));
+                }
+            },
+        }
+    }
+
+    fn compile_structure(&mut self, structure: &Structure) {
+        match structure {
+            Structure::Array(array) => {
+                self.compile_count(array.count_type.as_ref(), array.count.as_ref());
+                self.emit(Op::BeginList { counted: true });
+                let start = self.emit(Op::NextElement { end: 0 });
+                self.compile(&array.elements_type);
+                self.end_list(start, Op::NextElement { end: 0 });
+            }
+            Structure::Container(fields) => self.compile_container(fields),
+            Structure::Count(count) => self.compile(&count.count_type),
+        }
+    }
+
+    fn compile_container(&mut self, fields: &[Field]) {
+        let slots = fields
+            .iter()
+            .map(|field| match &field.name {
+                Some(name) if !field.is_anonymous() => Slot::Named(name.clone()),
+                _ => Slot::Merged,
+            })
+            .collect();
+
+        let layout = self.layouts.len();
+        self.layouts.push(Layout { slots });
+        self.emit(Op::BeginContainer(layout));
+
+        let size = fields.iter().fold(0, |size, field| {
+            size_with(&field.field_type, self.scope, self.natives)
+                .min()
+                .saturating_add(size)
+        });
+        if size > 0 {
+            self.emit(Op::Ensure(size));
+        }
+
+        self.containers.push(layout);
+        for (slot, field) in fields.iter().enumerate() {
+            self.compile(&field.field_type);
+            self.emit(Op::Store(slot));
+        }
+        self.containers.pop();
+
+        self.emit(Op::EndContainer);
+    }
+
+    fn compile_util(&mut self, util: &Util) {
+        match util {
+            Util::Buffer(buffer) => {
+                if buffer.rest == Some(true) {
+                    self.emit(Op::RestBytes);
+                } else {
+                    self.compile_count(buffer.count_type.as_ref(), buffer.count.as_ref());
+                    self.emit(Op::Bytes);
+                }
+            }
+            Util::Mapper(mapper) => match mappings(mapper, self.scope) {
+                Ok((numeric, mappings)) => {
+                    self.emit(Op::Numeric(numeric));
+                    self.emit(Op::Mapper(mappings));
+                }
+                Err(err) => {
+                    self.emit(Op::Fail(Failure::InvalidMapper(err)));
+                }
+            },
+            Util::Bitfield(fields) => {
+                self.emit(Op::Bitfield(fields.clone()));
+            }
+            Util::PrefixedString { count_type } => {
+                self.compile(count_type);
+                self.emit(Op::Count);
+                self.emit(Op::String);
+            }
+            Util::Loop(loop_util) => {
+                let next = || Op::NextLooped {
+                    end_val: loop_util.end_val,
+                    end: 0,
+                };
+
+                self.emit(Op::BeginList { counted: false });
+                let start = self.emit(next());
+                self.compile(&loop_util.data_type);
+                self.end_list(start, next());
+            }
+            Util::TopBitSetTerminatedArray(structure) => {
+                self.emit(Op::BeginList { counted: false });
+                let start = self.emit(Op::NextTopBitSet { end: 0 });
+                self.compile_structure(structure);
+                self.end_list(start, Op::NextTopBitSet { end: 0 });
+            }
+        }
+    }
+
+    /// Emits the end of the loop over list elements starting at `start`,
+    /// patching the jump out of it.
+    fn end_list(&mut self, start: usize, mut next: Op) {
+        self.emit(Op::Append);
+        self.emit(Op::Jump(start));
+
+        let end = self.emit(Op::EndList);
+        match &mut next {
+            Op::NextElement { end: target }
+            | Op::NextLooped { end: target, .. }
+            | Op::NextTopBitSet { end: target } => *target = end,
+            _ => unreachable!("list iteration op"),
+        }
+        self.ops[start] = next;
+    }
+
+    fn compile_count(&mut self, count_type: Option<&DataType>, count: Option<&ArrayCount>) {
+        match (count_type, count) {
+            (Some(count_type), _) => {
+                self.compile(count_type);
+                self.emit(Op::Count);
+            }
+            (None, Some(ArrayCount::FixedLength(length))) => {
+                self.emit(Op::FixedCount(*length as usize));
+            }
+            (None, Some(ArrayCount::FieldReference(path))) => {
+                let field = self.field_ref(path);
+                self.emit(Op::LoadCount(field));
+            }
+            (None, None) => {
+                self.emit(Op::Fail(Failure::MissingCount));
+            }
+        }
+    }
+
+    /// Emits a call of the procedure decoding a type, queueing it if new.
+    fn call(&mut self, key: String, data_type: DataType) {
+        let id = match self.procedures.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = self.starts.len();
+                self.starts.push(0);
+                self.procedures.insert(key, id);
+                self.pending.push((id, data_type));
+                id
+            }
+        };
+
+        self.emit(Op::Call(id));
+    }
+
+    /// Binds a reference to a slot of a container of the current procedure if it
+    /// names a field of one without anonymous fields, which could shadow it.
+    fn field_ref(&mut self, path: &str) -> FieldRef {
+        let segments: Vec<Segment> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .map(|segment| match segment {
+                ".." => Segment::Parent,
+                name => Segment::Name(name.to_owned()),
+            })
+            .collect();
+
+        let up = segments
+            .iter()
+            .take_while(|segment| matches!(segment, Segment::Parent))
+            .count();
+        let mut names = segments.iter().skip(up).map(|segment| match segment {
+            Segment::Name(name) => Some(name.clone()),
+            Segment::Parent => None,
+        });
+
+        let bound = match (names.next(), up < self.containers.len()) {
+            (Some(Some(name)), true) => {
+                let layout = &self.layouts[self.containers[self.containers.len() - 1 - up]];
+                let merged = layout.slots.iter().any(|slot| matches!(slot, Slot::Merged));
+                let slot = layout
+                    .slots
+                    .iter()
+                    .position(|slot| matches!(slot, Slot::Named(slot) if *slot == name));
+
+                match (merged, slot, names.collect::<Option<Vec<String>>>()) {
+                    (false, Some(slot), Some(rest)) => Some((slot, rest)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        match bound {
+            Some((slot, rest)) => FieldRef::Slot {
+                up,
+                slot,
+                rest,
+                path: path.to_owned(),
+            },
+            None => FieldRef::Path {
+                segments,
+                path: path.to_owned(),
+            },
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.ops.push(op);
+        self.ops.len() - 1
+    }
+}
+
+/// The input, with the first byte replaced while reading an element of a top
+/// bit set terminated array.
+struct Input<'b> {
+    bytes: &'b [u8],
+    patch: Option<u8>,
+}
+
+impl<'b> Input<'b> {
+    fn peek(&self) -> Result<u8> {
+        match (self.patch, self.bytes.first()) {
+            (_, None) => Err(unexpected_eof()),
+            (Some(byte), Some(_)) | (None, Some(&byte)) => Ok(byte),
+        }
+    }
+
+    fn split(&mut self, count: usize) -> Result<Vec<u8>> {
+        if count > self.bytes.len() {
+            return Err(unexpected_eof());
+        }
+
+        let (bytes, rest) = self.bytes.split_at(count);
+        let mut bytes = bytes.to_vec();
+        self.bytes = rest;
+
+        if let (Some(patch), Some(first)) = (self.patch.take(), bytes.first_mut()) {
+            *first = patch;
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl Read for Input<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.bytes.read(buf)?;
+
+        if read > 0 {
+            if let Some(patch) = self.patch.take() {
+                buf[0] = patch;
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+struct Frame {
+    layout: usize,
+    slots: Vec<Option<Value>>,
+}
+
+struct List {
+    values: Vec<Value>,
+    remaining: usize,
+    /// Whether the last element of a top bit set terminated array was read.
+    last: bool,
+}
+
+struct Machine<'b> {
+    input: Input<'b>,
+    stack: Vec<Value>,
+    counts: Vec<usize>,
+    frames: Vec<Frame>,
+    lists: Vec<List>,
+    calls: Vec<usize>,
+}
+
+impl<'b> Machine<'b> {
+    fn run(&mut self, plan: &Plan) -> Result<()> {
+        let mut pc = 0;
+
+        loop {
+            match &plan.ops[pc] {
+                Op::Numeric(numeric) => {
+                    let value = decode_numeric(numeric, &mut self.input)?;
+                    self.stack.push(value);
+                }
+                Op::Primitive(primitive) => {
+                    let value = decode_primitive(primitive, &mut self.input)?;
+                    self.stack.push(value);
+                }
+                Op::CString => {
+                    let length = match self.input.patch {
+                        Some(0) => Some(0),
+                        Some(_) => self
+                            .input
+                            .bytes
+                            .iter()
+                            .skip(1)
+                            .position(|&byte| byte == 0)
+                            .map(|index| index + 1),
+                        None => self.input.bytes.iter().position(|&byte| byte == 0),
+                    };
+                    let length = length.ok_or_else(unexpected_eof)?;
+
+                    let mut bytes = self.input.split(length + 1)?;
+                    bytes.pop();
+                    self.stack.push(Value::String(String::from_utf8(bytes)?));
+                }
+                Op::String => {
+                    let count = self.counts.pop().expect("count");
+                    let bytes = self.input.split(count)?;
+                    self.stack.push(Value::String(String::from_utf8(bytes)?));
+                }
+                Op::Bytes => {
+                    let count = self.counts.pop().expect("count");
+                    let bytes = self.input.split(count)?;
+                    self.stack.push(Value::Bytes(bytes));
+                }
+                Op::RestBytes => {
+                    let bytes = self.input.split(self.input.bytes.len())?;
+                    self.stack.push(Value::Bytes(bytes));
+                }
+                Op::Native(name) => {
+                    let natives = plan.natives.as_deref().expect("registry of native op");
+                    let value = natives.decode(name, &mut self.input)?;
+                    self.stack.push(value);
+                }
+                Op::Bitfield(fields) => {
+                    let value = decode_bitfield(fields, &mut self.input)?;
+                    self.stack.push(value);
+                }
+                Op::Mapper(mappings) => {
+                    let value = self.stack.last_mut().expect("mapped value");
+                    if let Some(name) = value.as_i128().and_then(|key| mappings.name(key)) {
+                        *value = Value::String(name.to_owned());
+                    }
+                }
+                Op::Count => {
+                    let value = self.stack.pop().expect("count value");
+                    self.counts.push(value_to_count(&value)?);
+                }
+                Op::LoadCount(field) => {
+                    let count = value_to_count(self.field(plan, field)?)?;
+                    self.counts.push(count);
+                }
+                Op::FixedCount(count) => self.counts.push(*count),
+                Op::Ensure(size) => {
+                    if self.input.bytes.len() < *size {
+                        return Err(unexpected_eof());
+                    }
+                }
+                Op::BeginContainer(layout) => self.frames.push(Frame {
+                    layout: *layout,
+                    slots: (0..plan.layouts[*layout].slots.len())
+                        .map(|_| None)
+                        .collect(),
+                }),
+                Op::Store(slot) => {
+                    let value = self.stack.pop().expect("field value");
+                    self.frames.last_mut().expect("container frame").slots[*slot] = Some(value);
+                }
+                Op::EndContainer => {
+                    let frame = self.frames.pop().expect("container frame");
+                    let layout = &plan.layouts[frame.layout];
+                    let mut values = LinkedHashMap::new();
+
+                    for (slot, value) in layout.slots.iter().zip(frame.slots) {
+                        match (slot, value) {
+                            (Slot::Named(name), Some(value)) => {
+                                values.insert(name.clone(), value);
+                            }
+                            // Anonymous fields are merged into the parent container.
+                            (Slot::Merged, Some(Value::Map(merged))) => values.extend(merged),
+                            _ => {}
+                        }
+                    }
+
+                    self.stack.push(Value::Map(values));
+                }
+                Op::BeginList { counted } => {
+                    let remaining = if *counted {
+                        self.counts.pop().expect("count")
+                    } else {
+                        0
+                    };
+
+                    self.lists.push(List {
+                        values: Vec::with_capacity(remaining.min(1024)),
+                        remaining,
+                        last: false,
+                    });
+                }
+                Op::NextElement { end } => {
+                    let list = self.lists.last_mut().expect("list");
+                    if list.remaining == 0 {
+                        pc = *end;
+                        continue;
+                    }
+                    list.remaining -= 1;
+                }
+                Op::NextLooped { end_val, end } => {
+                    if u32::from(self.input.peek()?) == *end_val {
+                        self.input.split(1)?;
+                        pc = *end;
+                        continue;
+                    }
+                }
+                Op::NextTopBitSet { end } => {
+                    let list = self.lists.last_mut().expect("list");
+                    if list.last {
+                        pc = *end;
+                        continue;
+                    }
+
+                    let byte = self.input.peek()?;
+                    list.last = byte & 0x80 == 0;
+                    self.input.patch = Some(byte & 0x7f);
+                }
+                Op::Append => {
+                    let value = self.stack.pop().expect("element value");
+                    self.lists.last_mut().expect("list").values.push(value);
+                }
+                Op::EndList => {
+                    let list = self.lists.pop().expect("list");
+                    self.stack.push(Value::List(list.values));
+                }
+                Op::OptionFlag { end } => {
+                    if self.input.split(1)?[0] == 0 {
+                        self.stack.push(Value::Option(None));
+                        pc = *end;
+                        continue;
+                    }
+                }
+                Op::WrapSome => {
+                    let value = self.stack.pop().expect("option value");
+                    self.stack.push(Value::Option(Some(Box::new(value))));
+                }
+                Op::Switch {
+                    field,
+                    cases,
+                    default,
+                    end,
+                } => {
+                    let value = self.field(plan, field)?;
+
+                    match cases.get(value).or(default.as_ref()) {
+                        Some(case) => pc = *case,
+                        None => {
+                            self.stack.push(Value::Void);
+                            pc = *end;
+                        }
+                    }
+                    continue;
+                }
+                Op::Jump(target) => {
+                    pc = *target;
+                    continue;
+                }
+                Op::Call(start) => {
+                    self.calls.push(pc + 1);
+                    pc = *start;
+                    continue;
+                }
+                Op::Return => match self.calls.pop() {
+                    Some(next) => {
+                        pc = next;
+                        continue;
+                    }
+                    None => return Ok(()),
+                },
+                Op::Fail(Failure::UnknownType(name)) => {
+                    return Err(Error::UnknownType(name.clone()))
+                }
+                Op::Fail(Failure::UnsupportedNative(name)) => {
+                    return Err(Error::UnsupportedNative(name.clone()))
+                }
+                Op::Fail(Failure::InvalidMapper(err)) => {
+                    return Err(Error::InvalidMapper(err.clone()))
+                }
+                Op::Fail(Failure::MissingCount) => return Err(Error::MissingCount),
+            }
+
+            pc += 1;
+        }
+    }
+
+    fn field(&self, plan: &Plan, field: &FieldRef) -> Result<&Value> {
+        match field {
+            FieldRef::Slot {
+                up,
+                slot,
+                rest,
+                path,
+            } => {
+                let unresolved = || Error::UnresolvedField(path.clone());
+                let frame = &self.frames[self.frames.len() - 1 - up];
+                let mut value = frame.slots[*slot].as_ref().ok_or_else(unresolved)?;
+
+                for name in rest {
+                    value = value.get(name).ok_or_else(unresolved)?;
+                }
+
+                Ok(value)
+            }
+            FieldRef::Path { segments, path } => {
+                let unresolved = || Error::UnresolvedField(path.clone());
+                let mut depth = self.frames.len();
+                let mut current: Option<&Value> = None;
+
+                for segment in segments {
+                    match (segment, current) {
+                        (Segment::Parent, None) if depth > 1 => depth -= 1,
+                        (Segment::Name(name), None) if depth > 0 => {
+                            current = Some(
+                                frame_field(plan, &self.frames[depth - 1], name)
+                                    .ok_or_else(unresolved)?,
+                            );
+                        }
+                        (Segment::Name(name), Some(value)) => {
+                            current = Some(value.get(name).ok_or_else(unresolved)?);
+                        }
+                        _ => return Err(unresolved()),
+                    }
+                }
+
+                current.ok_or_else(unresolved)
+            }
+        }
+    }
+}
+
+/// The value of a field of a container, the last one decoded if several have the name.
+fn frame_field<'f>(plan: &Plan, frame: &'f Frame, name: &str) -> Option<&'f Value> {
+    let slots = &plan.layouts[frame.layout].slots;
+
+    slots
+        .iter()
+        .zip(&frame.slots)
+        .rev()
+        .find_map(|(slot, value)| match (slot, value) {
+            (Slot::Named(slot), Some(value)) if slot == name => Some(value),
+            (Slot::Merged, Some(value)) => value.get(name),
+            _ => None,
+        })
+}
+
+fn unexpected_eof() -> Error {
+    Error::Io(io::ErrorKind::UnexpectedEof.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{self, Decoder};
+    use crate::value::Value;
+    use crate::*;
+
+    /// Asserts that a plan decodes the same value and reads as many bytes as the decoder.
+    fn assert_same(decoder: &Decoder, name: &str, bytes: &[u8]) {
+        let plan = decoder.compile(name).unwrap();
+
+        let mut expected_input = bytes;
+        let expected = decoder.decode_type(name, &mut expected_input);
+        let mut input = bytes;
+        let decoded = plan.decode(&mut input);
+
+        match (expected, decoded) {
+            (Ok(expected), Ok(decoded)) => {
+                assert_eq!(decoded, expected, "{} {:02x?}", name, bytes);
+                assert_eq!(input.len(), expected_input.len(), "{} {:02x?}", name, bytes);
+            }
+            (Err(_), Err(_)) => {}
+            (expected, decoded) => panic!(
+                "{} {:02x?}: expected {:?}, decoded {:?}",
+                name, bytes, expected, decoded
+            ),
+        }
+    }
+
+    #[test]
+    fn test_plan_decode() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let cases: &[(&str, &[u8])] = &[
+            (
+                "entityMetadata",
+                &[0x00, 0x00, 0x05, 0x07, 0x01, 0x01, 0xff],
+            ),
+            ("particle", &[0x03, 0x2a]),
+            (
+                "position",
+                &[0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x20, 0x40],
+            ),
+            ("slot", &[0x00]),
+            ("slot", &[0x01, 0x05, 0x40, 0x00]),
+            ("packet", &[0x47, 0x01, 0x80, 0x00, 0x05, 0x00]),
+            ("packet", &[0x19, 0x03, b'{', b'}', b'!', 0xaa]),
+            ("packet", &[0x19, 0x03, b'{', b'}']),
+        ];
+
+        for (name, bytes) in cases {
+            assert_same(&decoder, name, bytes);
+        }
+
+        let plan = decoder.compile("packet").unwrap();
+        let mut input = &[0x19, 0x01, b'x', 0xaa][..];
+        assert_eq!(
+            plan.decode(&mut input).unwrap().get("name"),
+            Some(&Value::String("kick_disconnect".to_owned()))
+        );
+        assert_eq!(input, [0xaa]);
+
+        // The first string of an element starts with the patched byte.
+        let protocol: Protocol = serde_json::from_str(
+            r#"{"types": {"names": ["topBitSetTerminatedArray", {"type": ["container", [
+                {"name": "name", "type": "cstring"}
+            ]]}]}}"#,
+        )
+        .unwrap();
+        assert_same(
+            &Decoder::new(&protocol),
+            "names",
+            &[0xe1, b'b', 0x00, b'c', 0x00],
+        );
+
+        // Keys are parsed as integers of the mapper type when compiled.
+        let protocol: Protocol = serde_json::from_str(
+            r#"{"types": {
+                "id": "i8",
+                "kind": ["mapper", {"type": "id", "mappings": {"0x0a": "ten", "-1": "minus"}}],
+                "broken": ["mapper", {"type": "u8", "mappings": {"0": "a", "0x00": "b"}}]
+            }}"#,
+        )
+        .unwrap();
+        let decoder = Decoder::new(&protocol);
+        for bytes in &[[0x0a], [0xff], [0x05]] {
+            assert_same(&decoder, "kind", bytes);
+        }
+        assert_eq!(
+            decoder
+                .compile("kind")
+                .unwrap()
+                .decode(&mut &[0xff][..])
+                .unwrap(),
+            Value::String("minus".to_owned())
+        );
+        assert!(matches!(
+            decoder.compile("broken").unwrap().decode(&mut &[0x00][..]),
+            Err(codec::Error::InvalidMapper(_))
+        ));
+    }
+
+    #[test]
+    fn test_plan_matches_decoder() {
+        let protocol = protocol("minecraft_v1662.json");
+
+        // Mostly small bytes, so that counts and lengths stay within the input.
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next_byte = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            match seed % 4 {
+                0 => (seed >> 8) as u8,
+                _ => (seed >> 8) as u8 % 4,
+            }
+        };
+
+        for namespace in &["play.toClient", "play.toServer"] {
+            let decoder = Decoder::with_namespace(&protocol, namespace).unwrap();
+            let names: Vec<String> = protocol
+                .get(namespace)
+                .unwrap()
+                .types()
+                .map(|(name, _)| name.clone())
+                .collect();
+
+            for name in &names {
+                for length in &[0, 4, 16, 64] {
+                    let bytes: Vec<u8> = (0..*length).map(|_| next_byte()).collect();
+                    assert_same(&decoder, name, &bytes);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_fixed_size() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        assert_eq!(decoder.compile("position").unwrap().fixed_size(), Some(8));
+        assert_eq!(
+            decoder
+                .compile("packet_entity_velocity")
+                .unwrap()
+                .fixed_size(),
+            None
+        );
+        assert_eq!(
+            decoder.compile("packet_update_time").unwrap().fixed_size(),
+            Some(16)
+        );
+        assert_eq!(decoder.compile("packet").unwrap().fixed_size(), None);
+
+        let plan = decoder.compile("packet_update_time").unwrap();
+        assert!(matches!(
+            plan.decode(&mut &[0u8; 15][..]),
+            Err(codec::Error::Io(_))
+        ));
+        assert!(!plan.is_empty());
+    }
+}
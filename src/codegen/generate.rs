@@ -0,0 +1,1612 @@
+use crate::codec::{mapper_type, parse_integer_key, Builtin};
+use crate::codegen::names::{field_ident, indent, type_name, unique, variant_name};
+use crate::codegen::{Error, Generator, Result};
+use crate::resolve::{namespaces, parameters, Scope};
+use crate::walk::escape;
+use crate::{
+    Array, ArrayCount, BitField, Buffer, Conditional, DataType, Field, Loop, Mapper, Numeric,
+    Primitive, Structure, Switch, TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::mem;
+use std::rc::Rc;
+
+const RUNTIME: &str = include_str!("runtime.rs");
+
+/// Generates the code of a whole protocol.
+pub(super) fn generate(generator: &Generator) -> Result<String> {
+    let mut state = State::new(generator);
+    let mut definitions = Vec::new();
+
+    for (_, namespace, types) in namespaces(generator.protocol) {
+        let module = module_path(&namespace);
+        state.modules.entry(module.clone()).or_default();
+
+        for (index, (name, definition)) in types.into_iter().enumerate() {
+            let id_pointer = crate::resolve::TypeId::new(&namespace, name).pointer();
+            state
+                .definitions
+                .insert(id_pointer.clone(), (namespace.clone(), index));
+
+            match definition {
+                TypeDefinition::DataType(data_type) if parameters(data_type).is_empty() => {
+                    state.reserve(&id_pointer, &module, index, &type_name(name));
+                    definitions.push((namespace.clone(), index, id_pointer, name, data_type));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for (namespace, index, pointer, name, data_type) in definitions {
+        let module = module_path(&namespace);
+        state.context = Context {
+            namespace,
+            module: module.clone(),
+            index,
+        };
+        state.function = Function::new(module.clone(), true);
+
+        let code = match state.generate(data_type, &pointer, &type_name(name)) {
+            Ok(code) => code,
+            Err(Fail::Error(err)) => return Err(err),
+            Err(Fail::Escape) => unreachable!("definitions are generated leniently"),
+        };
+
+        // Definitions which are not a struct or an enum become type aliases.
+        let (ty, slot) = state.names[&pointer].clone();
+        if state.modules[&module].items[slot].text.is_none() {
+            let alias = format!(
+                "pub type {} = {};",
+                ty.render(&module),
+                code.ty.render(&module)
+            );
+            state.fill(&pointer, alias);
+        }
+    }
+
+    Ok(state.finish())
+}
+
+/// A Rust type of generated values.
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Unit,
+    Primitive(&'static str),
+    String,
+    Bytes,
+    Vec(Box<Ty>),
+    Option(Box<Ty>),
+    /// A generated struct, enum or type alias.
+    Named {
+        module: Vec<String>,
+        name: String,
+    },
+    /// A Rust type standing for a native type.
+    Extern(String),
+}
+
+impl Ty {
+    /// Renders the type as seen from a module.
+    fn render(&self, from: &[String]) -> String {
+        match self {
+            Ty::Unit => "()".to_owned(),
+            Ty::Primitive(name) => (*name).to_owned(),
+            Ty::String => "std::string::String".to_owned(),
+            Ty::Bytes => "std::vec::Vec<u8>".to_owned(),
+            Ty::Vec(ty) => format!("std::vec::Vec<{}>", ty.render(from)),
+            Ty::Option(ty) => format!("std::option::Option<{}>", ty.render(from)),
+            Ty::Named { module, name } => relative_path(from, module, name),
+            Ty::Extern(path) => path.clone(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Ty::Named { name, .. } => name,
+            _ => "",
+        }
+    }
+}
+
+/// Path of an item in a module as seen from another module.
+fn relative_path(from: &[String], module: &[String], name: &str) -> String {
+    let common = from
+        .iter()
+        .zip(module)
+        .take_while(|(from, module)| from == module)
+        .count();
+
+    let mut path = String::new();
+
+    for _ in common..from.len() {
+        path.push_str("super::");
+    }
+
+    for segment in &module[common..] {
+        path.push_str(segment);
+        path.push_str("::");
+    }
+
+    path.push_str(name);
+    path
+}
+
+/// Module path of a namespace, e.g. `play::to_client` for `play.toClient`.
+fn module_path(namespace: &str) -> Vec<String> {
+    namespace
+        .split('.')
+        .filter(|name| !name.is_empty())
+        .map(field_ident)
+        .collect()
+}
+
+/// How values of a type compare against switch keys.
+#[derive(Debug, Clone)]
+enum Key {
+    Integer(&'static str),
+    Bool,
+    String,
+    Mapper(Rc<MapperKey>),
+    /// Values cannot be compared.
+    Other,
+}
+
+#[derive(Debug)]
+struct MapperKey {
+    ty: Ty,
+    /// Keys, mapped names and variants.
+    cases: Vec<(i128, String, String)>,
+}
+
+/// A field of a generated struct, which references can descend into.
+#[derive(Debug, Clone)]
+struct Member {
+    name: String,
+    ident: String,
+    key: Key,
+    members: Vec<Member>,
+}
+
+/// Generated code of a node of a type tree.
+struct Code {
+    ty: Ty,
+    key: Key,
+    /// Statements preceding the expression reading a value.
+    stmts: Vec<String>,
+    /// Expression reading a value from `reader`.
+    read: String,
+    /// Statements writing `value`, a reference to a value, into `writer`.
+    write: String,
+    members: Vec<Member>,
+}
+
+impl Code {
+    fn new(ty: Ty, read: String, write: String) -> Self {
+        Code {
+            ty,
+            key: Key::Other,
+            stmts: Vec::new(),
+            read,
+            write,
+            members: Vec::new(),
+        }
+    }
+
+    fn with_key(mut self, key: Key) -> Self {
+        self.key = key;
+        self
+    }
+
+    fn read_expr(&self) -> String {
+        if self.stmts.is_empty() {
+            self.read.clone()
+        } else {
+            format!("{{\n{}\n{}\n}}", self.stmts.join("\n"), self.read)
+        }
+    }
+}
+
+/// A field read so far, which references can point to.
+#[derive(Debug, Clone)]
+struct FrameField {
+    name: String,
+    expr: String,
+    key: Key,
+    members: Vec<Member>,
+}
+
+/// Why code of a node could not be generated.
+enum Fail {
+    /// The node refers to fields outside of it, so it can only be read in place.
+    Escape,
+    Error(Error),
+}
+
+impl From<Error> for Fail {
+    fn from(err: Error) -> Self {
+        Fail::Error(err)
+    }
+}
+
+type Generated<T> = std::result::Result<T, Fail>;
+
+enum Node {
+    /// Code of the node is being generated.
+    InProgress,
+    /// The node has its own `read` and `write` functions.
+    Standalone { key: Key, members: Vec<Member> },
+    /// The node refers to fields outside of it and is generated in place.
+    Contextual,
+}
+
+#[derive(Default)]
+struct Module {
+    names: HashSet<String>,
+    items: Vec<Item>,
+}
+
+struct Item {
+    /// Index of the definition and order of declaration, to keep items of a definition together.
+    order: (usize, usize),
+    text: Option<String>,
+}
+
+/// The definition being generated.
+#[derive(Clone, Default)]
+struct Context {
+    namespace: String,
+    module: Vec<String>,
+    index: usize,
+}
+
+/// The function being generated.
+struct Function {
+    module: Vec<String>,
+    /// Fields of the containers being read, innermost last.
+    read_frames: Vec<Vec<FrameField>>,
+    /// Names and expressions of the fields of the containers being written, innermost last.
+    write_frames: Vec<Vec<(String, String)>>,
+    locals: usize,
+    /// Whether unresolved references are ignored, when only declarations are needed.
+    lenient: bool,
+    allow: BTreeSet<&'static str>,
+}
+
+impl Function {
+    fn new(module: Vec<String>, lenient: bool) -> Self {
+        Function {
+            module,
+            read_frames: Vec::new(),
+            write_frames: Vec::new(),
+            locals: 0,
+            lenient,
+            allow: BTreeSet::new(),
+        }
+    }
+}
+
+struct State<'g, 'a> {
+    generator: &'g Generator<'a>,
+    modules: LinkedHashMap<Vec<String>, Module>,
+    /// Namespaces and indices of the definitions, by JSON pointer.
+    definitions: HashMap<String, (String, usize)>,
+    /// Declared structs, enums and type aliases with their item slots, by JSON pointer.
+    names: HashMap<String, (Ty, usize)>,
+    nodes: HashMap<String, Node>,
+    context: Context,
+    function: Function,
+    /// Type aliases being generated, to detect recursion.
+    aliases: Vec<String>,
+    /// Nodes being generated in place, to detect recursion.
+    inlining: Vec<String>,
+}
+
+impl<'g, 'a> State<'g, 'a> {
+    fn new(generator: &'g Generator<'a>) -> Self {
+        State {
+            generator,
+            modules: LinkedHashMap::new(),
+            definitions: HashMap::new(),
+            names: HashMap::new(),
+            nodes: HashMap::new(),
+            context: Context::default(),
+            function: Function::new(Vec::new(), true),
+            aliases: Vec::new(),
+            inlining: Vec::new(),
+        }
+    }
+
+    /// Reserves a name and an item slot for a node.
+    fn reserve(&mut self, path: &str, module: &[String], index: usize, hint: &str) -> Ty {
+        if let Some((ty, _)) = self.names.get(path) {
+            return ty.clone();
+        }
+
+        let module_entry = self.modules.entry(module.to_vec()).or_default();
+
+        let name = unique(&mut module_entry.names, hint.to_owned());
+        module_entry.items.push(Item {
+            order: (index, self.names.len()),
+            text: None,
+        });
+
+        let ty = Ty::Named {
+            module: module.to_vec(),
+            name,
+        };
+        self.names
+            .insert(path.to_owned(), (ty.clone(), module_entry.items.len() - 1));
+
+        ty
+    }
+
+    /// Reserves a name for a node of the current definition.
+    fn declare(&mut self, path: &str, hint: &str) -> Ty {
+        let module = self.context.module.clone();
+        let index = self.context.index;
+        self.reserve(path, &module, index, hint)
+    }
+
+    /// Sets the item of a node, unless it is already set.
+    fn fill(&mut self, path: &str, text: String) {
+        let (ty, slot) = &self.names[path];
+
+        if let Ty::Named { module, .. } = ty {
+            let item = &mut self.modules[module].items[*slot];
+
+            if item.text.is_none() {
+                item.text = Some(text);
+            }
+        }
+    }
+
+    fn runtime(&self) -> String {
+        relative_path(&self.function.module, &[], "runtime")
+    }
+
+    fn render(&self, ty: &Ty) -> String {
+        ty.render(&self.function.module)
+    }
+
+    fn local(&mut self) -> String {
+        let local = format!("v{}", self.function.locals);
+        self.function.locals += 1;
+        local
+    }
+
+    fn generate(&mut self, data_type: &DataType, path: &str, hint: &str) -> Generated<Code> {
+        match data_type {
+            DataType::Conditional(conditional) => match conditional.as_ref() {
+                Conditional::Switch(_) => self.nominal(data_type, path, hint),
+                Conditional::Option(data_type) => self.option(data_type, path, hint),
+            },
+            DataType::Numeric(numeric) => Ok(self.numeric(numeric)),
+            DataType::Primitive(primitive) => Ok(self.primitive(primitive)),
+            DataType::Structure(structure) => match structure.as_ref() {
+                Structure::Array(array) => self.array(array, path, hint),
+                Structure::Container(_) => self.nominal(data_type, path, hint),
+                Structure::Count(count) => {
+                    self.generate(&count.count_type, &format!("{}/1/type", path), hint)
+                }
+            },
+            DataType::Util(util) => match util.as_ref() {
+                Util::Buffer(buffer) => self.buffer(buffer, path),
+                Util::Mapper(_) | Util::Bitfield(_) => self.nominal(data_type, path, hint),
+                Util::PrefixedString { count_type } => self.prefixed_string(count_type, path),
+                Util::Loop(loop_util) => self.loop_util(loop_util, path, hint),
+                Util::TopBitSetTerminatedArray(structure) => {
+                    self.top_bit_set_terminated_array(structure, path, hint)
+                }
+            },
+            DataType::Parameterized { name, args } => {
+                let scope = self.scope();
+                let data_type =
+                    scope
+                        .instantiate(name, args)
+                        .ok_or_else(|| Error::UnknownType {
+                            path: path.to_owned(),
+                            name: name.clone(),
+                        })?;
+
+                self.generate(&data_type, path, hint)
+            }
+            DataType::Custom(name) => self.custom(name, path),
+        }
+    }
+
+    fn scope(&self) -> Scope<'a> {
+        Scope::new(self.generator.protocol, &self.context.namespace).expect("namespace exists")
+    }
+
+    fn custom(&mut self, name: &str, path: &str) -> Generated<Code> {
+        let (id, definition) = self.scope().find(name).ok_or_else(|| Error::UnknownType {
+            path: path.to_owned(),
+            name: name.to_owned(),
+        })?;
+
+        let data_type = match definition {
+            TypeDefinition::Native => return Ok(self.native(name)?),
+            TypeDefinition::DataType(data_type) => data_type,
+        };
+
+        let pointer = id.pointer();
+        let alias = !is_nominal(data_type);
+
+        if alias && self.aliases.contains(&pointer) {
+            return Err(Error::RecursiveType(pointer).into());
+        }
+
+        let index = self
+            .definitions
+            .get(&pointer)
+            .map_or(0, |(_, index)| *index);
+        let context = mem::replace(
+            &mut self.context,
+            Context {
+                namespace: id.namespace.clone(),
+                module: module_path(&id.namespace),
+                index,
+            },
+        );
+
+        if alias {
+            self.aliases.push(pointer.clone());
+        }
+
+        let result = self.generate(data_type, &pointer, &type_name(name));
+
+        if alias {
+            self.aliases.pop();
+        }
+        self.context = context;
+
+        let mut code = result?;
+
+        if alias {
+            if let Some((ty, _)) = self.names.get(&pointer) {
+                code.ty = ty.clone();
+            }
+        }
+
+        Ok(code)
+    }
+
+    fn native(&mut self, name: &str) -> Result<Code> {
+        if let Some(rust_type) = self.generator.natives.get(name) {
+            return Ok(Code::new(
+                Ty::Extern(rust_type.clone()),
+                format!("<{}>::read(reader)?", rust_type),
+                format!("<{}>::write(value, writer)?;", rust_type),
+            ));
+        }
+
+        match Builtin::of(name) {
+            Some(Builtin::RestBuffer) => Ok(Code::new(
+                Ty::Bytes,
+                format!("{}::read_to_end(reader)?", self.runtime()),
+                format!("{}::write_bytes(writer, value)?;", self.runtime()),
+            )),
+            Some(Builtin::Numeric(numeric)) => Ok(self.numeric(&numeric)),
+            None => Err(Error::UnsupportedNative(name.to_owned())),
+        }
+    }
+
+    fn numeric(&mut self, numeric: &Numeric) -> Code {
+        let runtime = self.runtime();
+
+        let (ty, key) = match numeric {
+            Numeric::VarInt | Numeric::VarLong | Numeric::ZigZag32 | Numeric::ZigZag64 => {
+                let name = numeric.name();
+                let ty = match numeric {
+                    Numeric::VarInt | Numeric::ZigZag32 => "i32",
+                    _ => "i64",
+                };
+
+                return Code::new(
+                    Ty::Primitive(ty),
+                    format!("{}::read_{}(reader)?", runtime, name),
+                    format!("{}::write_{}(writer, *value)?;", runtime, name),
+                )
+                .with_key(Key::Integer(ty));
+            }
+            Numeric::Float { .. } => ("f32", Key::Other),
+            Numeric::Double { .. } => ("f64", Key::Other),
+            _ => {
+                let name = numeric.name();
+                let ty = name.trim_start_matches('l');
+                (ty, Key::Integer(ty))
+            }
+        };
+
+        let endian = if numeric.name().starts_with('l') {
+            "le"
+        } else {
+            "be"
+        };
+
+        Code::new(
+            Ty::Primitive(ty),
+            format!(
+                "{}::from_{}_bytes({}::read_array(reader)?)",
+                ty, endian, runtime
+            ),
+            format!(
+                "{}::write_bytes(writer, &value.to_{}_bytes())?;",
+                runtime, endian
+            ),
+        )
+        .with_key(key)
+    }
+
+    fn primitive(&mut self, primitive: &Primitive) -> Code {
+        let runtime = self.runtime();
+
+        match primitive {
+            Primitive::Boolean => Code::new(
+                Ty::Primitive("bool"),
+                format!("{}::read_bool(reader)?", runtime),
+                format!("{}::write_bytes(writer, &[u8::from(*value)])?;", runtime),
+            )
+            .with_key(Key::Bool),
+            Primitive::String => Code::new(
+                Ty::String,
+                format!("{}::read_cstring(reader)?", runtime),
+                format!("{}::write_cstring(writer, value)?;", runtime),
+            )
+            .with_key(Key::String),
+            Primitive::Void => Code::new(Ty::Unit, "()".to_owned(), String::new()),
+        }
+    }
+
+    fn option(&mut self, data_type: &DataType, path: &str, hint: &str) -> Generated<Code> {
+        let inner = self.generate(data_type, &format!("{}/1", path), hint)?;
+        let runtime = self.runtime();
+
+        let read = format!(
+            "if {}::read_bool(reader)? {{\nSome({})\n}} else {{\nNone\n}}",
+            runtime,
+            inner.read_expr()
+        );
+        let write = format!(
+            "match value {{\nSome(value) => {{\n{}::write_bytes(writer, &[1])?;\n{}\n}}\nNone => {}::write_bytes(writer, &[0])?,\n}}",
+            runtime, inner.write, runtime
+        );
+
+        Ok(Code::new(Ty::Option(Box::new(inner.ty)), read, write))
+    }
+
+    /// Generates code reading the count of an array or a buffer into `count`,
+    /// and writing the length of `value` as its prefix.
+    fn count(
+        &mut self,
+        count_type: Option<&DataType>,
+        count: Option<&ArrayCount>,
+        path: &str,
+    ) -> Generated<(String, String)> {
+        let runtime = self.runtime();
+
+        match (count_type, count) {
+            (Some(count_type), _) => {
+                let code = self.generate(count_type, &format!("{}/1/countType", path), "")?;
+
+                Ok((
+                    format!(
+                        "let count = {}::count({})?;",
+                        runtime,
+                        code.read_expr()
+                    ),
+                    format!(
+                        "{{\nlet count: {} = {}::length(value.len())?;\nlet value = &count;\n{}\n}}",
+                        self.render(&code.ty),
+                        runtime,
+                        code.write
+                    ),
+                ))
+            }
+            (None, Some(ArrayCount::FixedLength(length))) => Ok((
+                format!("let count = {};", length),
+                format!("{}::check_length({}, value.len())?;", runtime, length),
+            )),
+            (None, Some(ArrayCount::FieldReference(reference))) => {
+                let field = self.lookup_read(reference, path)?;
+
+                match field.key {
+                    Key::Integer(_) => {}
+                    _ if self.function.lenient => {}
+                    _ => return Err(Error::InvalidCount(path.to_owned()).into()),
+                }
+
+                Ok((
+                    format!("let count = {}::count({})?;", runtime, field.expr),
+                    String::new(),
+                ))
+            }
+            (None, None) => Err(Error::MissingCount(path.to_owned()).into()),
+        }
+    }
+
+    fn array(&mut self, array: &Array, path: &str, hint: &str) -> Generated<Code> {
+        let (read_count, write_count) =
+            self.count(array.count_type.as_ref(), array.count.as_ref(), path)?;
+        let element = self.generate(
+            &array.elements_type,
+            &format!("{}/1/type", path),
+            &format!("{}Item", hint),
+        )?;
+
+        let mut code = Code::new(
+            Ty::Vec(Box::new(element.ty.clone())),
+            "items".to_owned(),
+            format!(
+                "{}\nfor value in value {{\n{}\n}}",
+                write_count, element.write
+            ),
+        );
+        code.stmts = vec![
+            read_count,
+            "let mut items = std::vec::Vec::with_capacity(count.min(1024));".to_owned(),
+            format!(
+                "for _ in 0..count {{\nitems.push({});\n}}",
+                element.read_expr()
+            ),
+        ];
+
+        Ok(code)
+    }
+
+    fn buffer(&mut self, buffer: &Buffer, path: &str) -> Generated<Code> {
+        let runtime = self.runtime();
+
+        if buffer.rest == Some(true) {
+            return Ok(Code::new(
+                Ty::Bytes,
+                format!("{}::read_to_end(reader)?", runtime),
+                format!("{}::write_bytes(writer, value)?;", runtime),
+            ));
+        }
+
+        let (read_count, write_count) =
+            self.count(buffer.count_type.as_ref(), buffer.count.as_ref(), path)?;
+
+        let mut code = Code::new(
+            Ty::Bytes,
+            format!("{}::read_bytes(reader, count)?", runtime),
+            format!("{}\n{}::write_bytes(writer, value)?;", write_count, runtime),
+        );
+        code.stmts = vec![read_count];
+
+        Ok(code)
+    }
+
+    fn prefixed_string(&mut self, count_type: &DataType, path: &str) -> Generated<Code> {
+        let runtime = self.runtime();
+        let (read_count, write_count) = self.count(Some(count_type), None, path)?;
+
+        let mut code = Code::new(
+            Ty::String,
+            format!("{}::read_string(reader, count)?", runtime),
+            format!(
+                "{}\n{}::write_bytes(writer, value.as_bytes())?;",
+                write_count, runtime
+            ),
+        )
+        .with_key(Key::String);
+        code.stmts = vec![read_count];
+
+        Ok(code)
+    }
+
+    fn loop_util(&mut self, loop_util: &Loop, path: &str, hint: &str) -> Generated<Code> {
+        if loop_util.end_val > 0xff {
+            return Err(Error::InvalidEndValue(path.to_owned()).into());
+        }
+
+        let runtime = self.runtime();
+        let element = self.generate(
+            &loop_util.data_type,
+            &format!("{}/1/type", path),
+            &format!("{}Item", hint),
+        )?;
+
+        let mut code = Code::new(
+            Ty::Vec(Box::new(element.ty.clone())),
+            "items".to_owned(),
+            format!(
+                "for value in value {{\n{}\n}}\n{}::write_bytes(writer, &[{}])?;",
+                element.write, runtime, loop_util.end_val
+            ),
+        );
+        code.stmts = vec![
+            "let mut items = std::vec::Vec::new();".to_owned(),
+            format!(
+                "loop {{\nlet first: [u8; 1] = {}::read_array(reader)?;\nif first[0] == {} {{\nbreak;\n}}\nlet reader = &mut std::io::Read::chain(&first[..], &mut *reader);\nitems.push({});\n}}",
+                runtime,
+                loop_util.end_val,
+                element.read_expr()
+            ),
+        ];
+
+        Ok(code)
+    }
+
+    fn top_bit_set_terminated_array(
+        &mut self,
+        structure: &Structure,
+        path: &str,
+        hint: &str,
+    ) -> Generated<Code> {
+        let runtime = self.runtime();
+        let element = self.generate(
+            &DataType::Structure(Box::new(structure.clone())),
+            &format!("{}/1/type", path),
+            &format!("{}Item", hint),
+        )?;
+
+        let mut code = Code::new(
+            Ty::Vec(Box::new(element.ty.clone())),
+            "items".to_owned(),
+            format!(
+                "if value.is_empty() {{\nreturn Err({runtime}::invalid_data(\"empty top bit set terminated array\"));\n}}\nlet count = value.len();\nfor (index, value) in value.iter().enumerate() {{\nlet mut bytes = std::vec::Vec::new();\n{{\nlet writer = &mut bytes;\n{write}\n}}\n{runtime}::write_top_bit_set(writer, bytes, index + 1 < count)?;\n}}",
+                runtime = runtime,
+                write = element.write
+            ),
+        );
+        code.stmts = vec![
+            "let mut items = std::vec::Vec::new();".to_owned(),
+            format!(
+                "loop {{\nlet [byte]: [u8; 1] = {}::read_array(reader)?;\nlet first = [byte & 0x7f];\nlet reader = &mut std::io::Read::chain(&first[..], &mut *reader);\nitems.push({});\nif byte & 0x80 == 0 {{\nbreak;\n}}\n}}",
+                runtime,
+                element.read_expr()
+            ),
+        ];
+
+        Ok(code)
+    }
+
+    /// Generates a node which becomes a struct or an enum. Nodes not referring to
+    /// fields outside of them get `read` and `write` functions, others are
+    /// generated in place.
+    fn nominal(&mut self, data_type: &DataType, path: &str, hint: &str) -> Generated<Code> {
+        let ty = self.declare(path, hint);
+
+        match self.nodes.get(path) {
+            Some(Node::Standalone { key, members }) => {
+                let (key, members) = (key.clone(), members.clone());
+                return Ok(self.call(&ty, key, members));
+            }
+            Some(Node::InProgress) => return Ok(self.call(&ty, Key::Other, Vec::new())),
+            Some(Node::Contextual) => {}
+            None => {
+                self.nodes.insert(path.to_owned(), Node::InProgress);
+
+                let module = match &ty {
+                    Ty::Named { module, .. } => module.clone(),
+                    _ => unreachable!("declared types are named"),
+                };
+                let function = mem::replace(&mut self.function, Function::new(module, false));
+                let result = self.build(data_type, path, &ty);
+                let function = mem::replace(&mut self.function, function);
+
+                match result {
+                    Ok((code, declaration)) => {
+                        let item = self.implementation(declaration, &ty, &code, function);
+                        self.fill(path, item);
+                        self.nodes.insert(
+                            path.to_owned(),
+                            Node::Standalone {
+                                key: code.key.clone(),
+                                members: code.members.clone(),
+                            },
+                        );
+
+                        return Ok(self.call(&ty, code.key, code.members));
+                    }
+                    Err(Fail::Escape) => {
+                        self.nodes.insert(path.to_owned(), Node::Contextual);
+                    }
+                    Err(err) => {
+                        self.nodes.remove(path);
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        if self.inlining.iter().any(|inlining| inlining == path) {
+            return Err(Error::RecursiveType(path.to_owned()).into());
+        }
+
+        self.inlining.push(path.to_owned());
+        let result = self.build(data_type, path, &ty);
+        self.inlining.pop();
+
+        let (code, declaration) = result?;
+        self.fill(path, declaration);
+
+        Ok(code)
+    }
+
+    /// Code calling the `read` and `write` functions of a type.
+    fn call(&self, ty: &Ty, key: Key, members: Vec<Member>) -> Code {
+        let rendered = self.render(ty);
+
+        Code {
+            key,
+            members,
+            ..Code::new(
+                ty.clone(),
+                format!("{}::read(reader)?", rendered),
+                format!("{}::write(value, writer)?;", rendered),
+            )
+        }
+    }
+
+    /// The declaration of a type along with its `read` and `write` functions.
+    fn implementation(
+        &self,
+        declaration: String,
+        ty: &Ty,
+        code: &Code,
+        function: Function,
+    ) -> String {
+        let mut read = code.stmts.join("\n");
+        if !read.is_empty() {
+            read.push('\n');
+        }
+
+        let mut write = code.write.clone();
+        if write.contains("value") {
+            write.insert_str(0, "let value = self;\n");
+        }
+
+        // Types without data, e.g. empty containers, neither read nor write anything.
+        let mut lints = function.allow;
+        if !format!("{}{}", read, code.read).contains("reader") || !write.contains("writer") {
+            lints.insert("unused_variables");
+        }
+
+        let allow = if lints.is_empty() {
+            String::new()
+        } else {
+            let lints: Vec<&str> = lints.into_iter().collect();
+            format!("#[allow({})]\n", lints.join(", "))
+        };
+
+        format!(
+            "{declaration}\n\n{allow}impl {name} {{\npub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {{\n{read}Ok({expr})\n}}\n\npub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {{\n{write}\nOk(())\n}}\n}}",
+            declaration = declaration,
+            allow = allow,
+            name = ty.name(),
+            read = read,
+            expr = code.read,
+            write = write,
+        )
+    }
+
+    /// Generates the code and the declaration of a struct or an enum.
+    fn build(&mut self, data_type: &DataType, path: &str, ty: &Ty) -> Generated<(Code, String)> {
+        match data_type {
+            DataType::Conditional(conditional) => match conditional.as_ref() {
+                Conditional::Switch(switch) => self.switch(switch, path, ty),
+                Conditional::Option(_) => unreachable!("options are not nominal"),
+            },
+            DataType::Structure(structure) => match structure.as_ref() {
+                Structure::Container(fields) => self.container(fields, path, ty),
+                _ => unreachable!("only containers are nominal"),
+            },
+            DataType::Util(util) => match util.as_ref() {
+                Util::Mapper(mapper) => self.mapper(mapper, path, ty),
+                Util::Bitfield(fields) => self.bitfield(fields, path, ty),
+                _ => unreachable!("only mappers and bitfields are nominal"),
+            },
+            _ => unreachable!("only conditionals, structures and utils are nominal"),
+        }
+    }
+
+    fn container(&mut self, fields: &[Field], path: &str, ty: &Ty) -> Generated<(Code, String)> {
+        let value = self.local();
+        let mut idents = HashSet::new();
+        let mut write_frame = Vec::new();
+        let mut field_idents = Vec::new();
+
+        for field in fields {
+            let stored = !matches!(
+                field.field_type,
+                DataType::Structure(ref structure) if matches!(**structure, Structure::Count(_))
+            ) && field.field_type != DataType::Primitive(Primitive::Void);
+
+            let ident = match &field.name {
+                Some(name) if !field.is_anonymous() => {
+                    let ident = unique(&mut idents, field_ident(name));
+                    if stored {
+                        write_frame.push((name.clone(), format!("{}.{}", value, ident)));
+                    }
+                    ident
+                }
+                _ => unique(&mut idents, "anon".to_owned()),
+            };
+
+            field_idents.push(ident);
+        }
+
+        self.function.read_frames.push(Vec::new());
+        self.function.write_frames.push(write_frame);
+
+        let result = self.container_fields(fields, field_idents, path, ty, &value);
+
+        self.function.read_frames.pop();
+        self.function.write_frames.pop();
+
+        let (stmts, write, declared) = result?;
+        let module = match ty {
+            Ty::Named { module, .. } => module.clone(),
+            _ => Vec::new(),
+        };
+
+        let rendered = self.render(ty);
+        let mut members = Vec::new();
+        let mut declaration = format!(
+            "#[derive(Debug, Clone, PartialEq)]\npub struct {} {{\n",
+            ty.name()
+        );
+        let mut read = format!("{} {{\n", rendered);
+
+        for (member, field_ty, local) in declared {
+            declaration.push_str(&format!(
+                "pub {}: {},\n",
+                member.ident,
+                field_ty.render(&module)
+            ));
+            read.push_str(&format!("{}: {},\n", member.ident, local));
+            members.push(member);
+        }
+
+        declaration.push('}');
+        read.push('}');
+
+        let write = if members.is_empty() {
+            write
+        } else {
+            format!("let {} = value;\n{}", value, write)
+        };
+
+        let mut code = Code::new(ty.clone(), read, write);
+        code.stmts = stmts;
+        code.members = members;
+
+        Ok((code, declaration))
+    }
+
+    /// Generates the reading and writing of each field of a container, and returns the
+    /// statements reading them, the statements writing them, and the stored fields.
+    #[allow(clippy::type_complexity)]
+    fn container_fields(
+        &mut self,
+        fields: &[Field],
+        idents: Vec<String>,
+        path: &str,
+        ty: &Ty,
+        value: &str,
+    ) -> Generated<(Vec<String>, String, Vec<(Member, Ty, String)>)> {
+        let mut stmts = Vec::new();
+        let mut write = Vec::new();
+        let mut declared = Vec::new();
+
+        for (i, (field, ident)) in fields.iter().zip(idents).enumerate() {
+            let field_path = format!("{}/1/{}/type", path, i);
+            let anonymous = field.is_anonymous() || field.name.is_none();
+            let hint = format!(
+                "{}{}",
+                ty.name(),
+                type_name(field.name.as_deref().unwrap_or("anon"))
+            );
+            let local = self.local();
+
+            if let DataType::Structure(structure) = &field.field_type {
+                if let Structure::Count(count) = structure.as_ref() {
+                    let code =
+                        self.generate(&count.count_type, &format!("{}/1/type", field_path), &hint)?;
+                    let target = self.lookup_write(&count.count_for, &field_path)?;
+
+                    stmts.push(format!("let {} = {};", local, code.read_expr()));
+                    write.push(format!(
+                        "{{\nlet count: {} = {}::length({}.len())?;\nlet value = &count;\n{}\n}}",
+                        self.render(&code.ty),
+                        self.runtime(),
+                        target,
+                        code.write
+                    ));
+                    self.function.allow.insert("unused_variables");
+
+                    if let Some(name) = &field.name {
+                        self.push_read_field(name, local, code.key, code.members);
+                    }
+
+                    continue;
+                }
+            }
+
+            let code = self.generate(&field.field_type, &field_path, &hint)?;
+
+            if code.ty == Ty::Unit {
+                continue;
+            }
+
+            stmts.push(format!("let {} = {};", local, code.read_expr()));
+            write.push(format!(
+                "{{\nlet value = &{}.{};\n{}\n}}",
+                value, ident, code.write
+            ));
+
+            match &field.name {
+                Some(name) if !anonymous => {
+                    self.push_read_field(
+                        name,
+                        local.clone(),
+                        code.key.clone(),
+                        code.members.clone(),
+                    );
+                }
+                // Fields of anonymous containers are merged into the parent container.
+                _ => {
+                    for member in &code.members {
+                        self.push_read_field(
+                            &member.name,
+                            format!("{}.{}", local, member.ident),
+                            member.key.clone(),
+                            member.members.clone(),
+                        );
+                    }
+                }
+            }
+
+            let member = Member {
+                name: field.name.clone().unwrap_or_default(),
+                ident,
+                key: code.key,
+                members: code.members,
+            };
+            declared.push((member, code.ty, local));
+        }
+
+        Ok((stmts, write.join("\n"), declared))
+    }
+
+    fn push_read_field(&mut self, name: &str, expr: String, key: Key, members: Vec<Member>) {
+        let frame = self
+            .function
+            .read_frames
+            .last_mut()
+            .expect("container frame");
+
+        frame.push(FrameField {
+            name: name.to_owned(),
+            expr,
+            key,
+            members,
+        });
+    }
+
+    fn switch(&mut self, switch: &Switch, path: &str, ty: &Ty) -> Generated<(Code, String)> {
+        let compared = self.lookup_read(&switch.compare_to, path)?;
+
+        if let Key::Other = compared.key {
+            if !self.function.lenient {
+                return Err(Error::UnsupportedSwitch(path.to_owned()).into());
+            }
+        }
+
+        let module = match ty {
+            Ty::Named { module, .. } => module.clone(),
+            _ => Vec::new(),
+        };
+        let rendered = self.render(ty);
+
+        let mut variants = HashSet::new();
+        variants.insert("Default".to_owned());
+
+        let mut declaration = format!(
+            "#[derive(Debug, Clone, PartialEq)]\npub enum {} {{\n",
+            ty.name()
+        );
+        let mut read = Vec::new();
+        let mut write = Vec::new();
+
+        let cases = switch
+            .fields
+            .iter()
+            .map(|(key, data_type)| {
+                (
+                    Some(key.as_str()),
+                    data_type,
+                    format!("{}/1/fields/{}", path, escape(key)),
+                )
+            })
+            .chain(
+                switch
+                    .default
+                    .iter()
+                    .map(|data_type| (None, data_type, format!("{}/1/default", path))),
+            );
+
+        for (key, data_type, case_path) in cases {
+            let variant = match key {
+                Some(key) => unique(&mut variants, variant_name(key)),
+                None => "Default".to_owned(),
+            };
+            let code =
+                self.generate(data_type, &case_path, &format!("{}{}", ty.name(), variant))?;
+
+            let pattern = match key {
+                Some(key) => match pattern(&compared.key, key) {
+                    Some(pattern) => pattern.render(&self.function.module),
+                    None => String::new(),
+                },
+                None => "_".to_owned(),
+            };
+
+            if code.ty == Ty::Unit {
+                declaration.push_str(&format!("{},\n", variant));
+                if !pattern.is_empty() {
+                    read.push(format!("{} => {}::{},", pattern, rendered, variant));
+                }
+                write.push(format!("{}::{} => {{}}", rendered, variant));
+            } else {
+                declaration.push_str(&format!("{}({}),\n", variant, code.ty.render(&module)));
+                if !pattern.is_empty() {
+                    read.push(format!(
+                        "{} => {}::{}({}),",
+                        pattern,
+                        rendered,
+                        variant,
+                        code.read_expr()
+                    ));
+                }
+                write.push(format!(
+                    "{}::{}(value) => {{\n{}\n}}",
+                    rendered, variant, code.write
+                ));
+            }
+        }
+
+        // A switch without a matching case nor a default holds no value.
+        if switch.default.is_none() {
+            declaration.push_str("Default,\n");
+            read.push(format!("_ => {}::Default,", rendered));
+            write.push(format!("{}::Default => {{}}", rendered));
+        }
+
+        declaration.push('}');
+        self.function.allow.insert("unreachable_patterns");
+
+        let scrutinee = match compared.key {
+            Key::String => format!("{}.as_str()", compared.expr),
+            _ => compared.expr,
+        };
+
+        // A switch without cases always holds the default.
+        let read = match read.as_slice() {
+            [default] => default["_ => ".len()..default.len() - 1].to_owned(),
+            _ => format!("match {} {{\n{}\n}}", scrutinee, read.join("\n")),
+        };
+
+        let code = Code::new(
+            ty.clone(),
+            read,
+            format!("match value {{\n{}\n}}", write.join("\n")),
+        );
+
+        Ok((code, declaration))
+    }
+
+    fn mapper(&mut self, mapper: &Mapper, path: &str, ty: &Ty) -> Generated<(Code, String)> {
+        let code = self.generate(&mapper_type(mapper), &format!("{}/1/type", path), "")?;
+
+        let integer = match code.key {
+            Key::Integer(integer) => integer,
+            _ => return Err(Error::UnsupportedMapper(path.to_owned()).into()),
+        };
+
+        let rendered = self.render(ty);
+        let mut variants = HashSet::new();
+        let mut cases = Vec::new();
+
+        for (key, name) in &mapper.mappings {
+            let value = parse_integer_key(key)
+                .filter(|value| fits(integer, *value))
+                .ok_or_else(|| Error::InvalidMappingKey {
+                    path: path.to_owned(),
+                    key: key.clone(),
+                })?;
+
+            cases.push((
+                value,
+                name.clone(),
+                unique(&mut variants, variant_name(name)),
+            ));
+        }
+
+        let mut declaration = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\npub enum {} {{\n",
+            ty.name()
+        );
+        let mut read = Vec::new();
+        let mut write = Vec::new();
+
+        for (value, _, variant) in &cases {
+            declaration.push_str(&format!("{},\n", variant));
+            read.push(format!("{} => {}::{},", value, rendered, variant));
+            write.push(format!("{}::{} => {},", rendered, variant, value));
+        }
+
+        declaration.push('}');
+        read.push(format!(
+            "_ => return Err({}::invalid_data({:?})),",
+            self.runtime(),
+            format!("unknown {}", ty.name())
+        ));
+        self.function.allow.insert("unreachable_patterns");
+
+        // Values of a mapper without mappings can neither be read nor written.
+        if cases.is_empty() {
+            self.function.allow.insert("unreachable_code");
+        }
+
+        let key = Key::Mapper(Rc::new(MapperKey {
+            ty: ty.clone(),
+            cases,
+        }));
+
+        let write = if write.is_empty() {
+            "match *value {}".to_owned()
+        } else {
+            format!(
+                "let raw: {} = match *value {{\n{}\n}};\nlet value = &raw;\n{}",
+                self.render(&code.ty),
+                write.join("\n"),
+                code.write
+            )
+        };
+
+        let mut result = Code::new(
+            ty.clone(),
+            format!("match {} {{\n{}\n}}", code.read_expr(), read.join("\n")),
+            write,
+        )
+        .with_key(key);
+
+        if let [unknown] = read.as_slice() {
+            result.stmts = vec![format!("{};", code.read_expr())];
+            result.read = unknown["_ => ".len()..unknown.len() - 1].to_owned();
+        }
+
+        Ok((result, declaration))
+    }
+
+    fn bitfield(&mut self, fields: &[BitField], path: &str, ty: &Ty) -> Generated<(Code, String)> {
+        let total_size: usize = fields.iter().map(|field| field.size).sum();
+
+        if total_size > 128 || fields.iter().any(|field| field.size > 64) {
+            return Err(Error::InvalidBitfield(path.to_owned()).into());
+        }
+
+        let runtime = self.runtime();
+        let rendered = self.render(ty);
+        let total_bytes = total_size.div_ceil(8);
+        let mut offset = total_bytes * 8;
+        let mut idents = HashSet::new();
+
+        let mut declaration = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq)]\npub struct {} {{\n",
+            ty.name()
+        );
+        let mut read = format!("{} {{\n", rendered);
+        let mut write = vec!["let mut bits = 0u128;".to_owned()];
+        let mut members = Vec::new();
+
+        for field in fields {
+            offset -= field.size;
+
+            let ident = unique(&mut idents, field_ident(&field.name));
+            let bits = match field.size {
+                0..=8 => 8,
+                9..=16 => 16,
+                17..=32 => 32,
+                _ => 64,
+            };
+            let integer = match (field.signed, bits) {
+                (true, 8) => "i8",
+                (true, 16) => "i16",
+                (true, 32) => "i32",
+                (true, _) => "i64",
+                (false, 8) => "u8",
+                (false, 16) => "u16",
+                (false, 32) => "u32",
+                (false, _) => "u64",
+            };
+            let mask = format!("{:#x}", (1u128 << field.size) - 1);
+            let shifted = if offset == 0 {
+                "bits".to_owned()
+            } else {
+                format!("(bits >> {})", offset)
+            };
+            let raw = format!("{} & {}", shifted, mask);
+
+            declaration.push_str(&format!("pub {}: {},\n", ident, integer));
+
+            if field.size == 0 {
+                // A zero-width field takes no bits and always holds zero.
+                read.push_str(&format!("{}: 0,\n", ident));
+            } else if field.signed {
+                read.push_str(&format!(
+                    "{}: {}::sign_extend({}, {}) as {},\n",
+                    ident, runtime, raw, field.size, integer
+                ));
+            } else {
+                read.push_str(&format!("{}: ({}) as {},\n", ident, raw, integer));
+            }
+
+            if field.size > 0 {
+                let masked = format!("(value.{} as u128) & {}", ident, mask);
+                write.push(if offset == 0 {
+                    format!("bits |= {};", masked)
+                } else {
+                    format!("bits |= ({}) << {};", masked, offset)
+                });
+            }
+
+            members.push(Member {
+                name: field.name.clone(),
+                ident,
+                key: Key::Integer(integer),
+                members: Vec::new(),
+            });
+        }
+
+        declaration.push('}');
+        read.push('}');
+        write.push(format!(
+            "{}::write_bits(writer, bits, {})?;",
+            runtime, total_bytes
+        ));
+
+        let mut code = Code::new(ty.clone(), read, write.join("\n"));
+        code.stmts = vec![format!(
+            "let bits = {}::read_bits(reader, {})?;",
+            runtime, total_bytes
+        )];
+        code.members = members;
+
+        Ok((code, declaration))
+    }
+
+    /// Resolves a `compareTo` or `count` reference like `name`, `../action` or
+    /// `flags/present` against the fields read so far.
+    fn lookup_read(&self, reference: &str, path: &str) -> Generated<FrameField> {
+        let frames = &self.function.read_frames;
+        let mut depth = frames.len();
+        let mut current: Option<FrameField> = None;
+
+        let unresolved = || -> Generated<FrameField> {
+            if self.function.lenient {
+                Ok(FrameField {
+                    name: reference.to_owned(),
+                    expr: "unreachable!()".to_owned(),
+                    key: Key::Other,
+                    members: Vec::new(),
+                })
+            } else {
+                Err(Error::UnresolvedField {
+                    path: path.to_owned(),
+                    field: reference.to_owned(),
+                }
+                .into())
+            }
+        };
+
+        for segment in reference.split('/') {
+            current = match (segment, current) {
+                ("" | ".", current) => current,
+                ("..", None) if depth > 1 => {
+                    depth -= 1;
+                    None
+                }
+                ("..", None) | (_, None) if depth == 0 || segment == ".." => {
+                    return if self.function.lenient {
+                        unresolved()
+                    } else {
+                        Err(Fail::Escape)
+                    };
+                }
+                (name, None) => match frames[depth - 1]
+                    .iter()
+                    .rev()
+                    .find(|field| field.name == name)
+                {
+                    Some(field) => Some(field.clone()),
+                    None => return unresolved(),
+                },
+                (name, Some(field)) => {
+                    match field.members.iter().find(|member| member.name == name) {
+                        Some(member) => Some(FrameField {
+                            name: member.name.clone(),
+                            expr: format!("{}.{}", field.expr, member.ident),
+                            key: member.key.clone(),
+                            members: member.members.clone(),
+                        }),
+                        None => return unresolved(),
+                    }
+                }
+            };
+        }
+
+        match current {
+            Some(field) => Ok(field),
+            None => unresolved(),
+        }
+    }
+
+    /// Resolves a `countFor` reference like `items` or `../items` against the
+    /// fields of the containers being written.
+    fn lookup_write(&self, reference: &str, path: &str) -> Generated<String> {
+        let frames = &self.function.write_frames;
+        let mut depth = frames.len();
+
+        let segments: Vec<&str> = reference
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect();
+
+        for (i, segment) in segments.iter().enumerate() {
+            if *segment == ".." {
+                if depth > 1 {
+                    depth -= 1;
+                    continue;
+                }
+
+                return if self.function.lenient {
+                    Ok("unreachable!()".to_owned())
+                } else {
+                    Err(Fail::Escape)
+                };
+            }
+
+            let found = frames[depth - 1]
+                .iter()
+                .find(|(name, _)| name == segment)
+                .map(|(_, expr)| expr.clone());
+
+            return match found {
+                Some(expr) if i + 1 == segments.len() => Ok(expr),
+                _ if self.function.lenient => Ok("unreachable!()".to_owned()),
+                _ => Err(Error::UnresolvedField {
+                    path: path.to_owned(),
+                    field: reference.to_owned(),
+                }
+                .into()),
+            };
+        }
+
+        Err(Error::UnresolvedField {
+            path: path.to_owned(),
+            field: reference.to_owned(),
+        }
+        .into())
+    }
+
+    /// Assembles the items of all modules.
+    fn finish(self) -> String {
+        let mut code = String::from(
+            "// @generated by protodef-parser from a protocol definition.\n\n#[allow(dead_code)]\nmod runtime {\n",
+        );
+
+        for line in RUNTIME.lines() {
+            if !line.is_empty() {
+                code.push_str("    ");
+            }
+            code.push_str(line);
+            code.push('\n');
+        }
+        code.push_str("}\n");
+
+        let mut items = String::new();
+        let mut open: Vec<String> = Vec::new();
+
+        for (module, mut contents) in self.modules {
+            while !module.starts_with(&open) {
+                open.pop();
+                items.push_str("}\n");
+            }
+
+            for name in &module[open.len()..] {
+                items.push_str(&format!("\npub mod {} {{\n", name));
+                open.push(name.clone());
+            }
+
+            contents.items.sort_by_key(|item| item.order);
+
+            for item in contents.items {
+                if let Some(text) = item.text {
+                    items.push('\n');
+                    items.push_str(&text);
+                    items.push('\n');
+                }
+            }
+        }
+
+        for _ in open {
+            items.push_str("}\n");
+        }
+
+        code.push_str(&indent(&items));
+        code
+    }
+}
+
+/// Whether a type becomes a struct or an enum.
+fn is_nominal(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Conditional(conditional) => matches!(**conditional, Conditional::Switch(_)),
+        DataType::Structure(structure) => matches!(**structure, Structure::Container(_)),
+        DataType::Util(util) => matches!(**util, Util::Mapper(_) | Util::Bitfield(_)),
+        _ => false,
+    }
+}
+
+/// A pattern matching a switch key.
+enum Pattern {
+    Literal(String),
+    Variant(Ty, String),
+}
+
+impl Pattern {
+    fn render(&self, from: &[String]) -> String {
+        match self {
+            Pattern::Literal(literal) => literal.clone(),
+            Pattern::Variant(ty, variant) => format!("{}::{}", ty.render(from), variant),
+        }
+    }
+}
+
+/// The pattern matching a switch key against values compared as given,
+/// or `None` if no value can match the key.
+fn pattern(key: &Key, case: &str) -> Option<Pattern> {
+    match key {
+        Key::Integer(integer) => parse_integer_key(case)
+            .filter(|value| fits(integer, *value))
+            .map(|value| Pattern::Literal(value.to_string())),
+        Key::Bool => match case {
+            "true" | "false" => Some(Pattern::Literal(case.to_owned())),
+            _ => None,
+        },
+        Key::String => Some(Pattern::Literal(format!("{:?}", case))),
+        Key::Mapper(mapper) => mapper
+            .cases
+            .iter()
+            .find(|(_, name, _)| name == case)
+            .or_else(|| {
+                let value = parse_integer_key(case)?;
+                mapper.cases.iter().find(|(key, _, _)| *key == value)
+            })
+            .map(|(_, _, variant)| Pattern::Variant(mapper.ty.clone(), variant.clone())),
+        Key::Other => None,
+    }
+}
+
+/// Whether an integer fits into a Rust integer type.
+fn fits(integer: &str, value: i128) -> bool {
+    let (min, max): (i128, i128) = match integer {
+        "i8" => (i8::MIN.into(), i8::MAX.into()),
+        "u8" => (0, u8::MAX.into()),
+        "i16" => (i16::MIN.into(), i16::MAX.into()),
+        "u16" => (0, u16::MAX.into()),
+        "i32" => (i32::MIN.into(), i32::MAX.into()),
+        "u32" => (0, u32::MAX.into()),
+        "i64" => (i64::MIN.into(), i64::MAX.into()),
+        _ => (0, u64::MAX.into()),
+    };
+
+    min <= value && value <= max
+}
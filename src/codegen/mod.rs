@@ -0,0 +1,297 @@
+//! Generation of Rust types with `read` and `write` functions from a `Protocol`.
+//!
+//! Containers become structs, switches become enums with a variant per case,
+//! mappers become C-like enums and bitfields become structs of integers. Other
+//! definitions become type aliases, and each namespace becomes a module.
+
+mod generate;
+mod names;
+
+use crate::{ParseError, Protocol};
+use linked_hash_map::LinkedHashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse(ParseError),
+    /// A `DataType::Custom` name is not defined in the scope.
+    UnknownType {
+        path: String,
+        name: String,
+    },
+    /// A native type is neither built in nor mapped to a Rust type.
+    UnsupportedNative(String),
+    /// A `compareTo`, `count` or `countFor` reference does not point to a field.
+    UnresolvedField {
+        path: String,
+        field: String,
+    },
+    /// A switch compares against a field whose values cannot be matched.
+    UnsupportedSwitch(String),
+    /// A mapper maps from a type other than an integer.
+    UnsupportedMapper(String),
+    /// A count references a field which is not an integer.
+    InvalidCount(String),
+    /// A mapper key is not an integer fitting into the mapped type.
+    InvalidMappingKey {
+        path: String,
+        key: String,
+    },
+    /// An array or buffer has neither a count type nor a count.
+    MissingCount(String),
+    /// A bitfield has a field of more than 64 bits, or more than 128 bits.
+    InvalidBitfield(String),
+    /// A type alias refers to itself, or a type must be inlined into itself.
+    RecursiveType(String),
+    /// A loop end value does not fit into a byte.
+    InvalidEndValue(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Parse(err) => write!(f, "parse error: {}", err),
+            Error::UnknownType { path, name } => write!(f, "{}: unknown type \"{}\"", path, name),
+            Error::UnsupportedNative(name) => write!(f, "unsupported native type \"{}\"", name),
+            Error::UnresolvedField { path, field } => {
+                write!(f, "{}: unresolved field reference \"{}\"", path, field)
+            }
+            Error::UnsupportedSwitch(path) => {
+                write!(f, "{}: switch compares against an unsupported type", path)
+            }
+            Error::UnsupportedMapper(path) => {
+                write!(f, "{}: mapper maps from a non-integer type", path)
+            }
+            Error::InvalidCount(path) => write!(f, "{}: count is not an integer field", path),
+            Error::InvalidMappingKey { path, key } => {
+                write!(f, "{}: invalid mapping key \"{}\"", path, key)
+            }
+            Error::MissingCount(path) => {
+                write!(f, "{}: neither count type nor count is specified", path)
+            }
+            Error::InvalidBitfield(path) => write!(f, "{}: unsupported bitfield layout", path),
+            Error::RecursiveType(path) => write!(f, "{}: type is recursive", path),
+            Error::InvalidEndValue(path) => {
+                write!(f, "{}: end value does not fit into a byte", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// Generates Rust code for the types of a protocol.
+///
+/// Native types other than `restBuffer` and `UUID`, generated as a `u128`, must
+/// be mapped to Rust types having
+/// `fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self>` and
+/// `fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()>`.
+pub struct Generator<'a> {
+    protocol: &'a Protocol,
+    natives: LinkedHashMap<String, String>,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(protocol: &'a Protocol) -> Self {
+        Generator {
+            protocol,
+            natives: LinkedHashMap::new(),
+        }
+    }
+
+    /// Maps a native type to a Rust type given by its path, e.g. `crate::types::Uuid`.
+    pub fn native(mut self, name: &str, rust_type: &str) -> Self {
+        self.natives.insert(name.to_owned(), rust_type.to_owned());
+        self
+    }
+
+    /// Generates the code of all definitions, each namespace becoming a module.
+    pub fn generate(&self) -> Result<String> {
+        generate::generate(self)
+    }
+}
+
+/// Generates code for a protocol file from a build script into `OUT_DIR`,
+/// to be included with `include!(concat!(env!("OUT_DIR"), "/<file_name>"))`.
+pub fn build<P: AsRef<Path>>(path: P, file_name: &str, natives: &[(&str, &str)]) -> Result<()> {
+    let path = path.as_ref();
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let protocol = crate::read_protocol(File::open(path)?)?;
+    let generator = natives
+        .iter()
+        .fold(Generator::new(&protocol), |generator, (name, rust_type)| {
+            generator.native(name, rust_type)
+        });
+    let code = generator.generate()?;
+
+    let out_dir = std::env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+    fs::write(PathBuf::from(out_dir).join(file_name), code)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{Decoder, Encoder};
+    use crate::codegen::*;
+    use std::io::{Read, Write};
+
+    /// A Rust type standing for the `UUID` native type of the test protocol.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Uuid(pub u128);
+
+    impl Uuid {
+        pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+            let mut bytes = [0; 16];
+            reader.read_exact(&mut bytes)?;
+
+            Ok(Uuid(u128::from_be_bytes(bytes)))
+        }
+
+        pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+            writer.write_all(&self.0.to_be_bytes())
+        }
+    }
+
+    mod generated {
+        include!("tests/protocol.rs");
+    }
+
+    use generated::play::to_client::*;
+
+    #[test]
+    fn test_generate_protocol() {
+        let protocol = crate::protocol_at("src/codegen/tests/protocol.json");
+        let code = Generator::new(&protocol)
+            .native("UUID", "crate::codegen::tests::Uuid")
+            .generate()
+            .unwrap();
+
+        assert_eq!(code, include_str!("tests/protocol.rs"));
+    }
+
+    #[test]
+    fn test_generate_unsupported_native() {
+        let v1662 = crate::protocol("minecraft_v1662.json");
+
+        match Generator::new(&v1662).generate() {
+            Err(Error::UnsupportedNative(name)) => assert_eq!(name, "optionalNbt"),
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        // `UUID` is built in, unless mapped to a Rust type.
+        let protocol = crate::protocol_at("src/codegen/tests/protocol.json");
+        let code = Generator::new(&protocol).generate().unwrap();
+        assert!(code.contains("pub player_uuid: u128,"));
+    }
+
+    #[test]
+    fn test_generate_minecraft_protocol() {
+        let protocol = crate::protocol("minecraft_v1662.json");
+        let generator = protocol
+            .required_natives()
+            .iter()
+            .fold(Generator::new(&protocol), |generator, id| {
+                generator.native(&id.name, "crate::Native")
+            });
+
+        let code = generator.generate().unwrap();
+        assert!(code.contains("pub struct PacketSpawnEntity {"));
+        assert!(code.contains("pub mod to_client {"));
+    }
+
+    #[test]
+    fn test_read_generated_packet() {
+        let bytes: &[u8] = &[0x01, 0x05, 0x02, 0x07, 0x01];
+        let packet = Packet::read(&mut &bytes[..]).unwrap();
+
+        let expected = Packet {
+            name: PacketName::EntityAction,
+            params: PacketParams::EntityAction(PacketEntityAction {
+                entity_id: 5,
+                action: PacketEntityActionAction::Jump,
+                data: PacketEntityActionData::Jump(PacketEntityActionDataJump {
+                    power: 7,
+                    boost: PacketEntityActionDataJumpBoost::Jump(true),
+                }),
+            }),
+        };
+        assert_eq!(packet, expected);
+
+        let login = PacketLogin {
+            entity_id: -1,
+            game_mode: 1,
+            world_names: vec!["overworld".to_owned()],
+            player_uuid: Uuid(0x0123_4567_89ab_cdef),
+        };
+        let mut bytes = Vec::new();
+        login.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 + 1 + 1 + 10 + 16);
+        assert_eq!(PacketLogin::read(&mut &bytes[..]).unwrap(), login);
+    }
+
+    #[test]
+    fn test_generated_round_trip() {
+        let protocol = crate::protocol_at("src/codegen/tests/protocol.json");
+        let encoder = Encoder::with_namespace(&protocol, "play.toClient").unwrap();
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let samples: &[&[u8]] = &[
+            &[0x01, 0x05, 0x00, 0x3f, 0x80, 0x00, 0x00],
+            &[0x01, 0x05, 0x01],
+            &[0x01, 0x05, 0x02, 0x07, 0x01],
+            &[
+                0x02, 0x05, 0x00, 0x00, 0x07, 0x01, 0x04, 0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x20,
+                0x40, 0x02, 0x03, 0x01, 0x05, 0x01, 0xff,
+            ],
+            &[
+                0x03, 0x00, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xfe, 0x01, 0x00, 0x10, 0x01, 0x02,
+                0x03, 0x04, 0x02, 0xaa, 0xbb, 0xcc,
+            ],
+            &[0x04, 0x01, 0x80, 0x00, 0x05, 0x01, 0x2a, 0x01],
+            &[0x05, 0x01, 0x02, b'h', b'i', 0x00, 0x01, b'x'],
+            &[0x05, 0x00, 0x01],
+        ];
+
+        for bytes in samples {
+            let packet = Packet::read(&mut &bytes[..]).unwrap();
+            let mut written = Vec::new();
+            packet.write(&mut written).unwrap();
+            assert_eq!(written, *bytes, "{:?}", packet);
+
+            let value = decoder.decode_type("packet", *bytes).unwrap();
+            let mut encoded = Vec::new();
+            encoder.encode_type("packet", &value, &mut encoded).unwrap();
+            assert_eq!(encoded, *bytes, "{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Conversion of protodef names into Rust identifiers, and layout of generated code.
+
+use crate::codec::parse_integer_key;
+use std::collections::HashSet;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Splits a name like `packet_spawn`, `entityId` or `HTTPServer` into words.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+
+        for (i, &c) in chars.iter().enumerate() {
+            let boundary = i > 0
+                && c.is_ascii_uppercase()
+                && (!chars[i - 1].is_ascii_uppercase()
+                    || chars
+                        .get(i + 1)
+                        .is_some_and(|next| next.is_ascii_lowercase()));
+
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+
+            word.push(c);
+        }
+
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+
+    words
+}
+
+/// Converts a name into a type name, e.g. `packet_spawn` into `PacketSpawn`.
+pub(super) fn type_name(name: &str) -> String {
+    let mut result = String::new();
+
+    for word in words(name) {
+        let mut chars = word.chars();
+
+        if let Some(first) = chars.next() {
+            result.push(first.to_ascii_uppercase());
+            result.push_str(&chars.as_str().to_ascii_lowercase());
+        }
+    }
+
+    if result == "Self" {
+        result.push('_');
+    }
+
+    result
+}
+
+/// Converts a switch key or a mapped name into an enum variant name.
+pub(super) fn variant_name(key: &str) -> String {
+    match parse_integer_key(key) {
+        Some(integer) if integer < 0 => format!("CaseMinus{}", integer.unsigned_abs()),
+        Some(integer) => format!("Case{}", integer),
+        None => match type_name(key) {
+            name if name.is_empty() => "Case".to_owned(),
+            name if name.starts_with(|c: char| c.is_ascii_digit()) => format!("Case{}", name),
+            name => name,
+        },
+    }
+}
+
+/// Converts a name into a field or module identifier, e.g. `entityId` into `entity_id`.
+pub(super) fn field_ident(name: &str) -> String {
+    let ident = words(name)
+        .iter()
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    match ident.as_str() {
+        "" => "field".to_owned(),
+        "self" | "super" | "crate" => format!("{}_", ident),
+        _ if KEYWORDS.contains(&ident.as_str()) => format!("r#{}", ident),
+        _ if ident.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", ident),
+        _ => ident,
+    }
+}
+
+/// Makes a name unique among the used ones by appending a number.
+pub(super) fn unique(used: &mut HashSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut index = 2;
+
+    while used.contains(&candidate) {
+        candidate = format!("{}{}", name, index);
+        index += 1;
+    }
+
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// Re-indents code by the nesting of its brackets, one level per line at most.
+pub(super) fn indent(code: &str) -> String {
+    let mut result = String::new();
+    // Open brackets, and whether each one indents the lines after it.
+    let mut stack: Vec<bool> = Vec::new();
+
+    for line in code.lines() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            result.push('\n');
+            continue;
+        }
+
+        let closing = line
+            .chars()
+            .take_while(|c| matches!(c, '}' | ')' | ']'))
+            .count();
+
+        for _ in 0..closing {
+            stack.pop();
+        }
+
+        let depth = stack.iter().filter(|&&indents| indents).count();
+        for _ in 0..depth {
+            result.push_str("    ");
+        }
+        result.push_str(line);
+        result.push('\n');
+
+        let opened_before = stack.len();
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for c in line.chars().skip(closing) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                _ if in_string => {}
+                '{' | '(' | '[' => stack.push(false),
+                '}' | ')' | ']' => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+
+        if stack.len() > opened_before {
+            if let Some(last) = stack.last_mut() {
+                *last = true;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codegen::names::*;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(type_name("packet_spawn_entity"), "PacketSpawnEntity");
+        assert_eq!(type_name("entityMetadataItem"), "EntityMetadataItem");
+        assert_eq!(type_name("UUID"), "Uuid");
+        assert_eq!(field_ident("playerUUID"), "player_uuid");
+        assert_eq!(field_ident("HTTPServer"), "http_server");
+        assert_eq!(field_ident("type"), "r#type");
+        assert_eq!(field_ident("self"), "self_");
+        assert_eq!(variant_name("0x1f"), "Case31");
+        assert_eq!(variant_name("-1"), "CaseMinus1");
+        assert_eq!(variant_name("-0x10"), "CaseMinus16");
+        assert_eq!(variant_name("+5"), "Case5");
+        assert_eq!(variant_name("minecraft:brand"), "MinecraftBrand");
+    }
+}
@@ -0,0 +1,201 @@
+// Support functions of the generated code, copied into it as the `runtime` module.
+
+use std::io::{self, Read, Write};
+
+pub fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+pub fn read_bool<R: Read>(reader: &mut R) -> io::Result<bool> {
+    let [byte] = read_array(reader)?;
+
+    Ok(byte != 0)
+}
+
+pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<i32> {
+    let mut result = 0u32;
+
+    for i in 0..5 {
+        let [byte] = read_array(reader)?;
+        result |= u32::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+
+    Err(invalid_data("varint is too long"))
+}
+
+pub fn read_varlong<R: Read>(reader: &mut R) -> io::Result<i64> {
+    let mut result = 0u64;
+
+    for i in 0..10 {
+        let [byte] = read_array(reader)?;
+        result |= u64::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok(result as i64);
+        }
+    }
+
+    Err(invalid_data("varlong is too long"))
+}
+
+pub fn read_zigzag32<R: Read>(reader: &mut R) -> io::Result<i32> {
+    let value = read_varint(reader)? as u32;
+
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+}
+
+pub fn read_zigzag64<R: Read>(reader: &mut R) -> io::Result<i64> {
+    let value = read_varlong(reader)? as u64;
+
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+pub fn read_bytes<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(count.min(4096));
+    reader.by_ref().take(count as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != count {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(bytes)
+}
+
+pub fn read_to_end<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+pub fn read_string<R: Read>(reader: &mut R, count: usize) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader, count)?).map_err(|err| invalid_data(&err.to_string()))
+}
+
+pub fn read_cstring<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut bytes = Vec::new();
+
+    loop {
+        match read_array(reader)? {
+            [0] => break,
+            [byte] => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|err| invalid_data(&err.to_string()))
+}
+
+/// Reads the bytes of a bitfield, most significant first.
+pub fn read_bits<R: Read>(reader: &mut R, count: usize) -> io::Result<u128> {
+    let bytes = read_bytes(reader, count)?;
+
+    Ok(bytes
+        .into_iter()
+        .fold(0, |bits, byte| (bits << 8) | u128::from(byte)))
+}
+
+pub fn sign_extend(raw: u128, size: u32) -> i128 {
+    let shift = 128 - size;
+    ((raw << shift) as i128) >> shift
+}
+
+/// Converts a count read from the data into a length.
+pub fn count<T: std::convert::TryInto<usize>>(value: T) -> io::Result<usize> {
+    value
+        .try_into()
+        .map_err(|_| invalid_data("count is not a non-negative integer"))
+}
+
+/// Converts a length into the value of a count to be written.
+pub fn length<T: std::convert::TryFrom<usize>>(length: usize) -> io::Result<T> {
+    T::try_from(length).map_err(|_| invalid_data("length does not fit into its count type"))
+}
+
+pub fn check_length(expected: usize, found: usize) -> io::Result<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!(
+            "expected {} elements, found {}",
+            expected, found
+        )))
+    }
+}
+
+pub fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(bytes)
+}
+
+pub fn write_varint<W: Write>(writer: &mut W, value: i32) -> io::Result<()> {
+    let mut value = value as u32;
+
+    loop {
+        if value & !0x7f == 0 {
+            return writer.write_all(&[value as u8]);
+        }
+
+        writer.write_all(&[(value & 0x7f) as u8 | 0x80])?;
+        value >>= 7;
+    }
+}
+
+pub fn write_varlong<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+    let mut value = value as u64;
+
+    loop {
+        if value & !0x7f == 0 {
+            return writer.write_all(&[value as u8]);
+        }
+
+        writer.write_all(&[(value & 0x7f) as u8 | 0x80])?;
+        value >>= 7;
+    }
+}
+
+pub fn write_zigzag32<W: Write>(writer: &mut W, value: i32) -> io::Result<()> {
+    write_varint(writer, (value << 1) ^ (value >> 31))
+}
+
+pub fn write_zigzag64<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+    write_varlong(writer, (value << 1) ^ (value >> 63))
+}
+
+pub fn write_cstring<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    if value.contains('\0') {
+        return Err(invalid_data("string contains a null character"));
+    }
+
+    writer.write_all(value.as_bytes())?;
+    writer.write_all(&[0])
+}
+
+/// Writes the bytes of a bitfield, most significant first.
+pub fn write_bits<W: Write>(writer: &mut W, bits: u128, count: usize) -> io::Result<()> {
+    writer.write_all(&bits.to_be_bytes()[16 - count..])
+}
+
+/// Writes an element of a top bit set terminated array, setting the top bit
+/// of its first byte if more elements follow.
+pub fn write_top_bit_set<W: Write>(writer: &mut W, mut bytes: Vec<u8>, more: bool) -> io::Result<()> {
+    match bytes.first_mut() {
+        Some(first) if *first & 0x80 == 0 => {
+            if more {
+                *first |= 0x80;
+            }
+
+            writer.write_all(&bytes)
+        }
+        _ => Err(invalid_data("invalid top bit set terminated array")),
+    }
+}
@@ -0,0 +1,1138 @@
+// @generated by protodef-parser from a protocol definition.
+
+#[allow(dead_code)]
+mod runtime {
+    // Support functions of the generated code, copied into it as the `runtime` module.
+
+    use std::io::{self, Read, Write};
+
+    pub fn invalid_data(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    pub fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        reader.read_exact(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    pub fn read_bool<R: Read>(reader: &mut R) -> io::Result<bool> {
+        let [byte] = read_array(reader)?;
+
+        Ok(byte != 0)
+    }
+
+    pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<i32> {
+        let mut result = 0u32;
+
+        for i in 0..5 {
+            let [byte] = read_array(reader)?;
+            result |= u32::from(byte & 0x7f) << (7 * i);
+
+            if byte & 0x80 == 0 {
+                return Ok(result as i32);
+            }
+        }
+
+        Err(invalid_data("varint is too long"))
+    }
+
+    pub fn read_varlong<R: Read>(reader: &mut R) -> io::Result<i64> {
+        let mut result = 0u64;
+
+        for i in 0..10 {
+            let [byte] = read_array(reader)?;
+            result |= u64::from(byte & 0x7f) << (7 * i);
+
+            if byte & 0x80 == 0 {
+                return Ok(result as i64);
+            }
+        }
+
+        Err(invalid_data("varlong is too long"))
+    }
+
+    pub fn read_zigzag32<R: Read>(reader: &mut R) -> io::Result<i32> {
+        let value = read_varint(reader)? as u32;
+
+        Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+    }
+
+    pub fn read_zigzag64<R: Read>(reader: &mut R) -> io::Result<i64> {
+        let value = read_varlong(reader)? as u64;
+
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    pub fn read_bytes<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(count.min(4096));
+        reader.by_ref().take(count as u64).read_to_end(&mut bytes)?;
+
+        if bytes.len() != count {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn read_to_end<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    pub fn read_string<R: Read>(reader: &mut R, count: usize) -> io::Result<String> {
+        String::from_utf8(read_bytes(reader, count)?).map_err(|err| invalid_data(&err.to_string()))
+    }
+
+    pub fn read_cstring<R: Read>(reader: &mut R) -> io::Result<String> {
+        let mut bytes = Vec::new();
+
+        loop {
+            match read_array(reader)? {
+                [0] => break,
+                [byte] => bytes.push(byte),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|err| invalid_data(&err.to_string()))
+    }
+
+    /// Reads the bytes of a bitfield, most significant first.
+    pub fn read_bits<R: Read>(reader: &mut R, count: usize) -> io::Result<u128> {
+        let bytes = read_bytes(reader, count)?;
+
+        Ok(bytes
+            .into_iter()
+            .fold(0, |bits, byte| (bits << 8) | u128::from(byte)))
+    }
+
+    pub fn sign_extend(raw: u128, size: u32) -> i128 {
+        let shift = 128 - size;
+        ((raw << shift) as i128) >> shift
+    }
+
+    /// Converts a count read from the data into a length.
+    pub fn count<T: std::convert::TryInto<usize>>(value: T) -> io::Result<usize> {
+        value
+            .try_into()
+            .map_err(|_| invalid_data("count is not a non-negative integer"))
+    }
+
+    /// Converts a length into the value of a count to be written.
+    pub fn length<T: std::convert::TryFrom<usize>>(length: usize) -> io::Result<T> {
+        T::try_from(length).map_err(|_| invalid_data("length does not fit into its count type"))
+    }
+
+    pub fn check_length(expected: usize, found: usize) -> io::Result<()> {
+        if expected == found {
+            Ok(())
+        } else {
+            Err(invalid_data(&format!(
+                "expected {} elements, found {}",
+                expected, found
+            )))
+        }
+    }
+
+    pub fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+        writer.write_all(bytes)
+    }
+
+    pub fn write_varint<W: Write>(writer: &mut W, value: i32) -> io::Result<()> {
+        let mut value = value as u32;
+
+        loop {
+            if value & !0x7f == 0 {
+                return writer.write_all(&[value as u8]);
+            }
+
+            writer.write_all(&[(value & 0x7f) as u8 | 0x80])?;
+            value >>= 7;
+        }
+    }
+
+    pub fn write_varlong<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+        let mut value = value as u64;
+
+        loop {
+            if value & !0x7f == 0 {
+                return writer.write_all(&[value as u8]);
+            }
+
+            writer.write_all(&[(value & 0x7f) as u8 | 0x80])?;
+            value >>= 7;
+        }
+    }
+
+    pub fn write_zigzag32<W: Write>(writer: &mut W, value: i32) -> io::Result<()> {
+        write_varint(writer, (value << 1) ^ (value >> 31))
+    }
+
+    pub fn write_zigzag64<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+        write_varlong(writer, (value << 1) ^ (value >> 63))
+    }
+
+    pub fn write_cstring<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+        if value.contains('\0') {
+            return Err(invalid_data("string contains a null character"));
+        }
+
+        writer.write_all(value.as_bytes())?;
+        writer.write_all(&[0])
+    }
+
+    /// Writes the bytes of a bitfield, most significant first.
+    pub fn write_bits<W: Write>(writer: &mut W, bits: u128, count: usize) -> io::Result<()> {
+        writer.write_all(&bits.to_be_bytes()[16 - count..])
+    }
+
+    /// Writes an element of a top bit set terminated array, setting the top bit
+    /// of its first byte if more elements follow.
+    pub fn write_top_bit_set<W: Write>(writer: &mut W, mut bytes: Vec<u8>, more: bool) -> io::Result<()> {
+        match bytes.first_mut() {
+            Some(first) if *first & 0x80 == 0 => {
+                if more {
+                    *first |= 0x80;
+                }
+
+                writer.write_all(&bytes)
+            }
+            _ => Err(invalid_data("invalid top bit set terminated array")),
+        }
+    }
+}
+
+pub type String = std::string::String;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub x: i32,
+    pub z: i32,
+    pub y: i16,
+}
+
+impl Position {
+    pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let bits = runtime::read_bits(reader, 8)?;
+        Ok(Position {
+            x: runtime::sign_extend((bits >> 38) & 0x3ffffff, 26) as i32,
+            z: runtime::sign_extend((bits >> 12) & 0x3ffffff, 26) as i32,
+            y: runtime::sign_extend(bits & 0xfff, 12) as i16,
+        })
+    }
+
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let value = self;
+        let mut bits = 0u128;
+        bits |= ((value.x as u128) & 0x3ffffff) << 38;
+        bits |= ((value.z as u128) & 0x3ffffff) << 12;
+        bits |= (value.y as u128) & 0xfff;
+        runtime::write_bits(writer, bits, 8)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+    pub present: bool,
+    pub anon: SlotAnon,
+}
+
+#[allow(unreachable_patterns)]
+impl Slot {
+    pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let v1 = runtime::read_bool(reader)?;
+        let v2 = match v1 {
+            false => SlotAnon::False,
+            true => SlotAnon::True(SlotAnonTrue::read(reader)?),
+            _ => SlotAnon::Default,
+        };
+        Ok(Slot {
+            present: v1,
+            anon: v2,
+        })
+    }
+
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let value = self;
+        let v0 = value;
+        {
+            let value = &v0.present;
+            runtime::write_bytes(writer, &[u8::from(*value)])?;
+        }
+        {
+            let value = &v0.anon;
+            match value {
+                SlotAnon::False => {}
+                SlotAnon::True(value) => {
+                    SlotAnonTrue::write(value, writer)?;
+                }
+                SlotAnon::Default => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlotAnon {
+    False,
+    True(SlotAnonTrue),
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotAnonTrue {
+    pub item_id: i32,
+    pub item_count: i8,
+}
+
+impl SlotAnonTrue {
+    pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let v1 = runtime::read_varint(reader)?;
+        let v2 = i8::from_be_bytes(runtime::read_array(reader)?);
+        Ok(SlotAnonTrue {
+            item_id: v1,
+            item_count: v2,
+        })
+    }
+
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let value = self;
+        let v0 = value;
+        {
+            let value = &v0.item_id;
+            runtime::write_varint(writer, *value)?;
+        }
+        {
+            let value = &v0.item_count;
+            runtime::write_bytes(writer, &value.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+pub type EntityMetadata = std::vec::Vec<EntityMetadataItem>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityMetadataItem {
+    pub anon: EntityMetadataItemAnon,
+    pub value: EntityMetadataItemValue,
+}
+
+#[allow(unreachable_patterns)]
+impl EntityMetadataItem {
+    pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let v1 = EntityMetadataItemAnon::read(reader)?;
+        let v2 = match v1.r#type {
+            0 => EntityMetadataItemValue::Case0(i8::from_be_bytes(runtime::read_array(reader)?)),
+            1 => EntityMetadataItemValue::Case1(runtime::read_varint(reader)?),
+            2 => EntityMetadataItemValue::Case2({
+                let count = runtime::count(runtime::read_varint(reader)?)?;
+                runtime::read_string(reader, count)?
+            }),
+            3 => EntityMetadataItemValue::Case3(Slot::read(reader)?),
+            4 => EntityMetadataItemValue::Case4(Position::read(reader)?),
+            5 => EntityMetadataItemValue::Case5(if runtime::read_bool(reader)? {
+                Some(<crate::codegen::tests::Uuid>::read(reader)?)
+            } else {
+                None
+            }),
+            _ => EntityMetadataItemValue::Default,
+        };
+        Ok(EntityMetadataItem {
+            anon: v1,
+            value: v2,
+        })
+    }
+
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let value = self;
+        let v0 = value;
+        {
+            let value = &v0.anon;
+            EntityMetadataItemAnon::write(value, writer)?;
+        }
+        {
+            let value = &v0.value;
+            match value {
+                EntityMetadataItemValue::Case0(value) => {
+                    runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                EntityMetadataItemValue::Case1(value) => {
+                    runtime::write_varint(writer, *value)?;
+                }
+                EntityMetadataItemValue::Case2(value) => {
+                    {
+                        let count: i32 = runtime::length(value.len())?;
+                        let value = &count;
+                        runtime::write_varint(writer, *value)?;
+                    }
+                    runtime::write_bytes(writer, value.as_bytes())?;
+                }
+                EntityMetadataItemValue::Case3(value) => {
+                    Slot::write(value, writer)?;
+                }
+                EntityMetadataItemValue::Case4(value) => {
+                    Position::write(value, writer)?;
+                }
+                EntityMetadataItemValue::Case5(value) => {
+                    match value {
+                        Some(value) => {
+                            runtime::write_bytes(writer, &[1])?;
+                            <crate::codegen::tests::Uuid>::write(value, writer)?;
+                        }
+                        None => runtime::write_bytes(writer, &[0])?,
+                    }
+                }
+                EntityMetadataItemValue::Default => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityMetadataItemAnon {
+    pub key: u8,
+    pub r#type: i32,
+}
+
+impl EntityMetadataItemAnon {
+    pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let v1 = u8::from_be_bytes(runtime::read_array(reader)?);
+        let v2 = runtime::read_varint(reader)?;
+        Ok(EntityMetadataItemAnon {
+            key: v1,
+            r#type: v2,
+        })
+    }
+
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let value = self;
+        let v0 = value;
+        {
+            let value = &v0.key;
+            runtime::write_bytes(writer, &value.to_be_bytes())?;
+        }
+        {
+            let value = &v0.r#type;
+            runtime::write_varint(writer, *value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityMetadataItemValue {
+    Case0(i8),
+    Case1(i32),
+    Case2(String),
+    Case3(Slot),
+    Case4(Position),
+    Case5(std::option::Option<crate::codegen::tests::Uuid>),
+    Default,
+}
+
+pub mod play {
+
+    pub mod to_client {
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketLogin {
+            pub entity_id: i32,
+            pub game_mode: u8,
+            pub world_names: std::vec::Vec<super::super::String>,
+            pub player_uuid: crate::codegen::tests::Uuid,
+        }
+
+        impl PacketLogin {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = i32::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v2 = u8::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v3 = {
+                    let count = super::super::runtime::count(super::super::runtime::read_varint(reader)?)?;
+                    let mut items = std::vec::Vec::with_capacity(count.min(1024));
+                    for _ in 0..count {
+                        items.push({
+                            let count = super::super::runtime::count(super::super::runtime::read_varint(reader)?)?;
+                            super::super::runtime::read_string(reader, count)?
+                        });
+                    }
+                    items
+                };
+                let v4 = <crate::codegen::tests::Uuid>::read(reader)?;
+                Ok(PacketLogin {
+                    entity_id: v1,
+                    game_mode: v2,
+                    world_names: v3,
+                    player_uuid: v4,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.entity_id;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let value = &v0.game_mode;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let value = &v0.world_names;
+                    {
+                        let count: i32 = super::super::runtime::length(value.len())?;
+                        let value = &count;
+                        super::super::runtime::write_varint(writer, *value)?;
+                    }
+                    for value in value {
+                        {
+                            let count: i32 = super::super::runtime::length(value.len())?;
+                            let value = &count;
+                            super::super::runtime::write_varint(writer, *value)?;
+                        }
+                        super::super::runtime::write_bytes(writer, value.as_bytes())?;
+                    }
+                }
+                {
+                    let value = &v0.player_uuid;
+                    <crate::codegen::tests::Uuid>::write(value, writer)?;
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketEntityAction {
+            pub entity_id: i32,
+            pub action: PacketEntityActionAction,
+            pub data: PacketEntityActionData,
+        }
+
+        #[allow(unreachable_patterns)]
+        impl PacketEntityAction {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = super::super::runtime::read_varint(reader)?;
+                let v2 = PacketEntityActionAction::read(reader)?;
+                let v3 = match v2 {
+                    PacketEntityActionAction::Start => PacketEntityActionData::Start(PacketEntityActionDataStart::read(reader)?),
+                    PacketEntityActionAction::Jump => PacketEntityActionData::Jump({
+                        let v5 = super::super::runtime::read_varint(reader)?;
+                        let v6 = match v2 {
+                            PacketEntityActionAction::Jump => PacketEntityActionDataJumpBoost::Jump(super::super::runtime::read_bool(reader)?),
+                            _ => PacketEntityActionDataJumpBoost::Default,
+                        };
+                        PacketEntityActionDataJump {
+                            power: v5,
+                            boost: v6,
+                        }
+                    }),
+                    _ => PacketEntityActionData::Default,
+                };
+                Ok(PacketEntityAction {
+                    entity_id: v1,
+                    action: v2,
+                    data: v3,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.entity_id;
+                    super::super::runtime::write_varint(writer, *value)?;
+                }
+                {
+                    let value = &v0.action;
+                    PacketEntityActionAction::write(value, writer)?;
+                }
+                {
+                    let value = &v0.data;
+                    match value {
+                        PacketEntityActionData::Start(value) => {
+                            PacketEntityActionDataStart::write(value, writer)?;
+                        }
+                        PacketEntityActionData::Jump(value) => {
+                            let v4 = value;
+                            {
+                                let value = &v4.power;
+                                super::super::runtime::write_varint(writer, *value)?;
+                            }
+                            {
+                                let value = &v4.boost;
+                                match value {
+                                    PacketEntityActionDataJumpBoost::Jump(value) => {
+                                        super::super::runtime::write_bytes(writer, &[u8::from(*value)])?;
+                                    }
+                                    PacketEntityActionDataJumpBoost::Default => {}
+                                }
+                            }
+                        }
+                        PacketEntityActionData::Default => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum PacketEntityActionAction {
+            Start,
+            Stop,
+            Jump,
+        }
+
+        #[allow(unreachable_patterns)]
+        impl PacketEntityActionAction {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                Ok(match super::super::runtime::read_varint(reader)? {
+                    0 => PacketEntityActionAction::Start,
+                    1 => PacketEntityActionAction::Stop,
+                    2 => PacketEntityActionAction::Jump,
+                    _ => return Err(super::super::runtime::invalid_data("unknown PacketEntityActionAction")),
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let raw: i32 = match *value {
+                    PacketEntityActionAction::Start => 0,
+                    PacketEntityActionAction::Stop => 1,
+                    PacketEntityActionAction::Jump => 2,
+                };
+                let value = &raw;
+                super::super::runtime::write_varint(writer, *value)?;
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum PacketEntityActionData {
+            Start(PacketEntityActionDataStart),
+            Jump(PacketEntityActionDataJump),
+            Default,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketEntityActionDataStart {
+            pub speed: f32,
+        }
+
+        impl PacketEntityActionDataStart {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = f32::from_be_bytes(super::super::runtime::read_array(reader)?);
+                Ok(PacketEntityActionDataStart {
+                    speed: v1,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.speed;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketEntityActionDataJump {
+            pub power: i32,
+            pub boost: PacketEntityActionDataJumpBoost,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum PacketEntityActionDataJumpBoost {
+            Jump(bool),
+            Default,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketEntityMetadata {
+            pub entity_id: i32,
+            pub metadata: super::super::EntityMetadata,
+        }
+
+        impl PacketEntityMetadata {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = super::super::runtime::read_varint(reader)?;
+                let v2 = {
+                    let mut items = std::vec::Vec::new();
+                    loop {
+                        let first: [u8; 1] = super::super::runtime::read_array(reader)?;
+                        if first[0] == 255 {
+                            break;
+                        }
+                        let reader = &mut std::io::Read::chain(&first[..], &mut *reader);
+                        items.push(super::super::EntityMetadataItem::read(reader)?);
+                    }
+                    items
+                };
+                Ok(PacketEntityMetadata {
+                    entity_id: v1,
+                    metadata: v2,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.entity_id;
+                    super::super::runtime::write_varint(writer, *value)?;
+                }
+                {
+                    let value = &v0.metadata;
+                    for value in value {
+                        super::super::EntityMetadataItem::write(value, writer)?;
+                    }
+                    super::super::runtime::write_bytes(writer, &[255])?;
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketChunk {
+            pub x: i32,
+            pub z: i32,
+            pub sections: std::vec::Vec<PacketChunkSectionsItem>,
+            pub heightmap: std::vec::Vec<u8>,
+            pub trailing: std::vec::Vec<u8>,
+        }
+
+        #[allow(unused_variables)]
+        impl PacketChunk {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = i32::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v2 = i32::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v3 = u8::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v4 = {
+                    let count = super::super::runtime::count(v3)?;
+                    let mut items = std::vec::Vec::with_capacity(count.min(1024));
+                    for _ in 0..count {
+                        items.push(PacketChunkSectionsItem::read(reader)?);
+                    }
+                    items
+                };
+                let v5 = {
+                    let count = super::super::runtime::count(super::super::runtime::read_varint(reader)?)?;
+                    super::super::runtime::read_bytes(reader, count)?
+                };
+                let v6 = super::super::runtime::read_to_end(reader)?;
+                Ok(PacketChunk {
+                    x: v1,
+                    z: v2,
+                    sections: v4,
+                    heightmap: v5,
+                    trailing: v6,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.x;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let value = &v0.z;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let count: u8 = super::super::runtime::length(v0.sections.len())?;
+                    let value = &count;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let value = &v0.sections;
+
+                    for value in value {
+                        PacketChunkSectionsItem::write(value, writer)?;
+                    }
+                }
+                {
+                    let value = &v0.heightmap;
+                    {
+                        let count: i32 = super::super::runtime::length(value.len())?;
+                        let value = &count;
+                        super::super::runtime::write_varint(writer, *value)?;
+                    }
+                    super::super::runtime::write_bytes(writer, value)?;
+                }
+                {
+                    let value = &v0.trailing;
+                    super::super::runtime::write_bytes(writer, value)?;
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketChunkSectionsItem {
+            pub block_count: u16,
+            pub palette: std::vec::Vec<i32>,
+        }
+
+        impl PacketChunkSectionsItem {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = u16::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v2 = {
+                    let count = 4;
+                    let mut items = std::vec::Vec::with_capacity(count.min(1024));
+                    for _ in 0..count {
+                        items.push(super::super::runtime::read_varint(reader)?);
+                    }
+                    items
+                };
+                Ok(PacketChunkSectionsItem {
+                    block_count: v1,
+                    palette: v2,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.block_count;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let value = &v0.palette;
+                    super::super::runtime::check_length(4, value.len())?;
+                    for value in value {
+                        super::super::runtime::write_varint(writer, *value)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketEquipment {
+            pub entity_id: i32,
+            pub equipments: std::vec::Vec<PacketEquipmentEquipmentsItem>,
+        }
+
+        impl PacketEquipment {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = super::super::runtime::read_varint(reader)?;
+                let v2 = {
+                    let mut items = std::vec::Vec::new();
+                    loop {
+                        let [byte]: [u8; 1] = super::super::runtime::read_array(reader)?;
+                        let first = [byte & 0x7f];
+                        let reader = &mut std::io::Read::chain(&first[..], &mut *reader);
+                        items.push(PacketEquipmentEquipmentsItem::read(reader)?);
+                        if byte & 0x80 == 0 {
+                            break;
+                        }
+                    }
+                    items
+                };
+                Ok(PacketEquipment {
+                    entity_id: v1,
+                    equipments: v2,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.entity_id;
+                    super::super::runtime::write_varint(writer, *value)?;
+                }
+                {
+                    let value = &v0.equipments;
+                    if value.is_empty() {
+                        return Err(super::super::runtime::invalid_data("empty top bit set terminated array"));
+                    }
+                    let count = value.len();
+                    for (index, value) in value.iter().enumerate() {
+                        let mut bytes = std::vec::Vec::new();
+                        {
+                            let writer = &mut bytes;
+                            PacketEquipmentEquipmentsItem::write(value, writer)?;
+                        }
+                        super::super::runtime::write_top_bit_set(writer, bytes, index + 1 < count)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketEquipmentEquipmentsItem {
+            pub slot: i8,
+            pub item: super::super::Slot,
+        }
+
+        impl PacketEquipmentEquipmentsItem {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = i8::from_be_bytes(super::super::runtime::read_array(reader)?);
+                let v2 = super::super::Slot::read(reader)?;
+                Ok(PacketEquipmentEquipmentsItem {
+                    slot: v1,
+                    item: v2,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.slot;
+                    super::super::runtime::write_bytes(writer, &value.to_be_bytes())?;
+                }
+                {
+                    let value = &v0.item;
+                    super::super::Slot::write(value, writer)?;
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct PacketFlags {
+            pub flags: PacketFlagsFlags,
+            pub name: PacketFlagsName,
+            pub visible: bool,
+            pub reason: PacketFlagsReason,
+        }
+
+        #[allow(unreachable_patterns)]
+        impl PacketFlags {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = PacketFlagsFlags::read(reader)?;
+                let v2 = match v1.named {
+                    1 => PacketFlagsName::Case1({
+                        let count = super::super::runtime::count(super::super::runtime::read_varint(reader)?)?;
+                        super::super::runtime::read_string(reader, count)?
+                    }),
+                    _ => PacketFlagsName::Default,
+                };
+                let v3 = super::super::runtime::read_bool(reader)?;
+                let v4 = match v3 {
+                    false => PacketFlagsReason::False({
+                        let count = super::super::runtime::count(super::super::runtime::read_varint(reader)?)?;
+                        super::super::runtime::read_string(reader, count)?
+                    }),
+                    _ => PacketFlagsReason::Default,
+                };
+                Ok(PacketFlags {
+                    flags: v1,
+                    name: v2,
+                    visible: v3,
+                    reason: v4,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.flags;
+                    PacketFlagsFlags::write(value, writer)?;
+                }
+                {
+                    let value = &v0.name;
+                    match value {
+                        PacketFlagsName::Case1(value) => {
+                            {
+                                let count: i32 = super::super::runtime::length(value.len())?;
+                                let value = &count;
+                                super::super::runtime::write_varint(writer, *value)?;
+                            }
+                            super::super::runtime::write_bytes(writer, value.as_bytes())?;
+                        }
+                        PacketFlagsName::Default => {}
+                    }
+                }
+                {
+                    let value = &v0.visible;
+                    super::super::runtime::write_bytes(writer, &[u8::from(*value)])?;
+                }
+                {
+                    let value = &v0.reason;
+                    match value {
+                        PacketFlagsReason::False(value) => {
+                            {
+                                let count: i32 = super::super::runtime::length(value.len())?;
+                                let value = &count;
+                                super::super::runtime::write_varint(writer, *value)?;
+                            }
+                            super::super::runtime::write_bytes(writer, value.as_bytes())?;
+                        }
+                        PacketFlagsReason::Default => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct PacketFlagsFlags {
+            pub unused: u8,
+            pub hidden: u8,
+            pub named: u8,
+            pub reserved: i8,
+        }
+
+        impl PacketFlagsFlags {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let bits = super::super::runtime::read_bits(reader, 1)?;
+                Ok(PacketFlagsFlags {
+                    unused: ((bits >> 2) & 0x3f) as u8,
+                    hidden: ((bits >> 1) & 0x1) as u8,
+                    named: (bits & 0x1) as u8,
+                    reserved: 0,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let mut bits = 0u128;
+                bits |= ((value.unused as u128) & 0x3f) << 2;
+                bits |= ((value.hidden as u128) & 0x1) << 1;
+                bits |= (value.named as u128) & 0x1;
+                super::super::runtime::write_bits(writer, bits, 1)?;
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum PacketFlagsName {
+            Case1(super::super::String),
+            Default,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum PacketFlagsReason {
+            False(super::super::String),
+            Default,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Packet {
+            pub name: PacketName,
+            pub params: PacketParams,
+        }
+
+        #[allow(unreachable_patterns)]
+        impl Packet {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let v1 = PacketName::read(reader)?;
+                let v2 = match v1 {
+                    PacketName::Login => PacketParams::Login(PacketLogin::read(reader)?),
+                    PacketName::EntityAction => PacketParams::EntityAction(PacketEntityAction::read(reader)?),
+                    PacketName::EntityMetadata => PacketParams::EntityMetadata(PacketEntityMetadata::read(reader)?),
+                    PacketName::Chunk => PacketParams::Chunk(PacketChunk::read(reader)?),
+                    PacketName::Equipment => PacketParams::Equipment(PacketEquipment::read(reader)?),
+                    PacketName::Flags => PacketParams::Flags(PacketFlags::read(reader)?),
+                    _ => PacketParams::Default,
+                };
+                Ok(Packet {
+                    name: v1,
+                    params: v2,
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let v0 = value;
+                {
+                    let value = &v0.name;
+                    PacketName::write(value, writer)?;
+                }
+                {
+                    let value = &v0.params;
+                    match value {
+                        PacketParams::Login(value) => {
+                            PacketLogin::write(value, writer)?;
+                        }
+                        PacketParams::EntityAction(value) => {
+                            PacketEntityAction::write(value, writer)?;
+                        }
+                        PacketParams::EntityMetadata(value) => {
+                            PacketEntityMetadata::write(value, writer)?;
+                        }
+                        PacketParams::Chunk(value) => {
+                            PacketChunk::write(value, writer)?;
+                        }
+                        PacketParams::Equipment(value) => {
+                            PacketEquipment::write(value, writer)?;
+                        }
+                        PacketParams::Flags(value) => {
+                            PacketFlags::write(value, writer)?;
+                        }
+                        PacketParams::Default => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum PacketName {
+            Login,
+            EntityAction,
+            EntityMetadata,
+            Chunk,
+            Equipment,
+            Flags,
+        }
+
+        #[allow(unreachable_patterns)]
+        impl PacketName {
+            pub fn read<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                Ok(match super::super::runtime::read_varint(reader)? {
+                    0 => PacketName::Login,
+                    1 => PacketName::EntityAction,
+                    2 => PacketName::EntityMetadata,
+                    3 => PacketName::Chunk,
+                    4 => PacketName::Equipment,
+                    5 => PacketName::Flags,
+                    _ => return Err(super::super::runtime::invalid_data("unknown PacketName")),
+                })
+            }
+
+            pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let value = self;
+                let raw: i32 = match *value {
+                    PacketName::Login => 0,
+                    PacketName::EntityAction => 1,
+                    PacketName::EntityMetadata => 2,
+                    PacketName::Chunk => 3,
+                    PacketName::Equipment => 4,
+                    PacketName::Flags => 5,
+                };
+                let value = &raw;
+                super::super::runtime::write_varint(writer, *value)?;
+                Ok(())
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum PacketParams {
+            Login(PacketLogin),
+            EntityAction(PacketEntityAction),
+            EntityMetadata(PacketEntityMetadata),
+            Chunk(PacketChunk),
+            Equipment(PacketEquipment),
+            Flags(PacketFlags),
+            Default,
+        }
+    }
+}
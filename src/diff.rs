@@ -0,0 +1,555 @@
+//! Differences between two versions of a protocol, e.g. to write changelogs or
+//! migration code between `minecraft_v1444.json` and `minecraft_v1662.json`.
+
+use crate::codec::parse_integer_key;
+use crate::resolve::{namespaces, TypeId};
+use crate::walk::escape;
+use crate::{
+    Conditional, DataType, Field, Mapper, Protocol, Structure, Switch, TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::fmt;
+
+/// A difference between two protocols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// JSON pointer to the changed node in the new protocol, or in the old one
+    /// for removals, e.g. `/play/toClient/types/packet_chat/1/1/type`.
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    TypeAdded(TypeId),
+    TypeRemoved(TypeId),
+    /// A definition was removed and an equal one added in the same namespace,
+    /// e.g. a renamed packet.
+    TypeRenamed {
+        from: TypeId,
+        to: TypeId,
+    },
+    /// A node has a different type which is not compared in detail.
+    TypeChanged {
+        old: TypeDefinition,
+        new: TypeDefinition,
+    },
+    /// A container field, `None` if it is anonymous.
+    FieldAdded(Option<String>),
+    FieldRemoved(Option<String>),
+    /// The fields present in both versions of a container are in a different order.
+    FieldsReordered {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    /// A switch case, `None` for the default.
+    CaseAdded(Option<String>),
+    CaseRemoved(Option<String>),
+    CompareToChanged {
+        old: String,
+        new: String,
+    },
+    MappingAdded {
+        key: String,
+        name: String,
+    },
+    MappingRemoved {
+        key: String,
+        name: String,
+    },
+    /// A name is mapped from a different key, e.g. a packet got a new ID.
+    MappingChanged {
+        name: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+
+        match &self.kind {
+            ChangeKind::TypeAdded(id) => write!(f, "added type {}", id),
+            ChangeKind::TypeRemoved(id) => write!(f, "removed type {}", id),
+            ChangeKind::TypeRenamed { from, to } => write!(f, "renamed type {} to {}", from, to),
+            ChangeKind::TypeChanged { old, new } => write!(
+                f,
+                "changed type from {} to {}",
+                serde_json::to_string(old).map_err(|_| fmt::Error)?,
+                serde_json::to_string(new).map_err(|_| fmt::Error)?
+            ),
+            ChangeKind::FieldAdded(name) => write!(f, "added field {}", FieldName(name)),
+            ChangeKind::FieldRemoved(name) => write!(f, "removed field {}", FieldName(name)),
+            ChangeKind::FieldsReordered { old, new } => write!(
+                f,
+                "reordered fields from {} to {}",
+                old.join(", "),
+                new.join(", ")
+            ),
+            ChangeKind::CaseAdded(key) => write!(f, "added case {}", CaseKey(key)),
+            ChangeKind::CaseRemoved(key) => write!(f, "removed case {}", CaseKey(key)),
+            ChangeKind::CompareToChanged { old, new } => {
+                write!(f, "compares to \"{}\" instead of \"{}\"", new, old)
+            }
+            ChangeKind::MappingAdded { key, name } => {
+                write!(f, "added mapping {} => \"{}\"", key, name)
+            }
+            ChangeKind::MappingRemoved { key, name } => {
+                write!(f, "removed mapping {} => \"{}\"", key, name)
+            }
+            ChangeKind::MappingChanged { name, old, new } => {
+                write!(f, "remapped \"{}\" from {} to {}", name, old, new)
+            }
+        }
+    }
+}
+
+struct FieldName<'a>(&'a Option<String>);
+
+impl fmt::Display for FieldName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, "\"{}\"", name),
+            None => f.write_str("(anonymous)"),
+        }
+    }
+}
+
+struct CaseKey<'a>(&'a Option<String>);
+
+impl fmt::Display for CaseKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(key) => write!(f, "\"{}\"", key),
+            None => f.write_str("default"),
+        }
+    }
+}
+
+/// Compares two protocols, returning the changes in order of the new protocol,
+/// followed by removed types.
+pub fn diff(old: &Protocol, new: &Protocol) -> Vec<Change> {
+    let old_types = definitions(old);
+    let new_types = definitions(new);
+    let mut changes = Vec::new();
+
+    // Pairs each removed definition with the first equal added one.
+    let mut renamed: LinkedHashMap<&TypeId, &TypeId> = LinkedHashMap::new();
+
+    for (old_id, old_definition) in &old_types {
+        if new_types.contains_key(old_id) {
+            continue;
+        }
+
+        let added = new_types.iter().find(|(new_id, new_definition)| {
+            new_id.namespace == old_id.namespace
+                && !old_types.contains_key(new_id)
+                && !renamed.values().any(|id| id == new_id)
+                && *new_definition == old_definition
+        });
+
+        if let Some((new_id, _)) = added {
+            renamed.insert(new_id, old_id);
+        }
+    }
+
+    for (id, new_definition) in &new_types {
+        let path = id.pointer();
+
+        match old_types.get(id) {
+            Some(old_definition) => {
+                diff_definition(old_definition, new_definition, path, &mut changes)
+            }
+            None => {
+                let kind = match renamed.get(id) {
+                    Some(from) => ChangeKind::TypeRenamed {
+                        from: (*from).clone(),
+                        to: id.clone(),
+                    },
+                    None => ChangeKind::TypeAdded(id.clone()),
+                };
+
+                changes.push(Change { path, kind });
+            }
+        }
+    }
+
+    for id in old_types.keys() {
+        if !new_types.contains_key(id) && !renamed.values().any(|from| *from == id) {
+            changes.push(Change {
+                path: id.pointer(),
+                kind: ChangeKind::TypeRemoved(id.clone()),
+            });
+        }
+    }
+
+    changes
+}
+
+/// All definitions of a protocol, root types first.
+fn definitions(protocol: &Protocol) -> LinkedHashMap<TypeId, &TypeDefinition> {
+    namespaces(protocol)
+        .into_iter()
+        .flat_map(|(_, namespace, types)| {
+            types
+                .into_iter()
+                .map(move |(name, definition)| (TypeId::new(&namespace, name), definition))
+        })
+        .collect()
+}
+
+fn diff_definition(
+    old: &TypeDefinition,
+    new: &TypeDefinition,
+    path: String,
+    changes: &mut Vec<Change>,
+) {
+    match (old, new) {
+        (TypeDefinition::DataType(old), TypeDefinition::DataType(new)) => {
+            diff_type(old, new, path, changes)
+        }
+        (old, new) if old != new => changes.push(Change {
+            path,
+            kind: ChangeKind::TypeChanged {
+                old: old.clone(),
+                new: new.clone(),
+            },
+        }),
+        _ => {}
+    }
+}
+
+fn diff_type(old: &DataType, new: &DataType, path: String, changes: &mut Vec<Change>) {
+    if old == new {
+        return;
+    }
+
+    match (old, new) {
+        (DataType::Structure(old), DataType::Structure(new)) => {
+            match (old.as_ref(), new.as_ref()) {
+                (Structure::Container(old), Structure::Container(new)) => {
+                    return diff_container(old, new, &path, changes);
+                }
+                (Structure::Array(old), Structure::Array(new))
+                    if old.count_type == new.count_type && old.count == new.count =>
+                {
+                    return diff_type(
+                        &old.elements_type,
+                        &new.elements_type,
+                        format!("{}/1/type", path),
+                        changes,
+                    );
+                }
+                _ => {}
+            }
+        }
+        (DataType::Conditional(old), DataType::Conditional(new)) => {
+            match (old.as_ref(), new.as_ref()) {
+                (Conditional::Switch(old), Conditional::Switch(new)) => {
+                    return diff_switch(old, new, &path, changes);
+                }
+                (Conditional::Option(old), Conditional::Option(new)) => {
+                    return diff_type(old, new, format!("{}/1", path), changes);
+                }
+                _ => {}
+            }
+        }
+        (DataType::Util(old), DataType::Util(new)) => {
+            if let (Util::Mapper(old), Util::Mapper(new)) = (old.as_ref(), new.as_ref()) {
+                if old.mappings_type == new.mappings_type {
+                    return diff_mapper(old, new, &path, changes);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    changes.push(Change {
+        path,
+        kind: ChangeKind::TypeChanged {
+            old: TypeDefinition::DataType(old.clone()),
+            new: TypeDefinition::DataType(new.clone()),
+        },
+    });
+}
+
+/// Pairs fields by name, and anonymous fields by their order among anonymous fields.
+fn diff_container(old: &[Field], new: &[Field], path: &str, changes: &mut Vec<Change>) {
+    let key = |fields: &[Field]| -> Vec<(Option<String>, usize)> {
+        let mut anonymous = 0;
+
+        fields
+            .iter()
+            .map(|field| match &field.name {
+                Some(name) if !field.is_anonymous() => (Some(name.clone()), 0),
+                _ => {
+                    anonymous += 1;
+                    (None, anonymous)
+                }
+            })
+            .collect()
+    };
+
+    let old_keys = key(old);
+    let new_keys = key(new);
+
+    let common = |keys: &[(Option<String>, usize)], others: &[(Option<String>, usize)]| {
+        keys.iter()
+            .filter(|key| others.contains(key))
+            .filter_map(|(name, _)| name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let old_order = common(&old_keys, &new_keys);
+    let new_order = common(&new_keys, &old_keys);
+
+    if old_order != new_order {
+        changes.push(Change {
+            path: path.to_owned(),
+            kind: ChangeKind::FieldsReordered {
+                old: old_order,
+                new: new_order,
+            },
+        });
+    }
+
+    for (i, key) in new_keys.iter().enumerate() {
+        let field_path = format!("{}/1/{}", path, i);
+
+        match old_keys.iter().position(|old_key| old_key == key) {
+            Some(j) => diff_type(
+                &old[j].field_type,
+                &new[i].field_type,
+                format!("{}/type", field_path),
+                changes,
+            ),
+            None => changes.push(Change {
+                path: field_path,
+                kind: ChangeKind::FieldAdded(key.0.clone()),
+            }),
+        }
+    }
+
+    for (j, key) in old_keys.iter().enumerate() {
+        if !new_keys.contains(key) {
+            changes.push(Change {
+                path: format!("{}/1/{}", path, j),
+                kind: ChangeKind::FieldRemoved(key.0.clone()),
+            });
+        }
+    }
+}
+
+fn diff_switch(old: &Switch, new: &Switch, path: &str, changes: &mut Vec<Change>) {
+    if old.compare_to != new.compare_to {
+        changes.push(Change {
+            path: format!("{}/1/compareTo", path),
+            kind: ChangeKind::CompareToChanged {
+                old: old.compare_to.clone(),
+                new: new.compare_to.clone(),
+            },
+        });
+    }
+
+    for (key, new_type) in &new.fields {
+        let case_path = format!("{}/1/fields/{}", path, escape(key));
+
+        match old.fields.get(key) {
+            Some(old_type) => diff_type(old_type, new_type, case_path, changes),
+            None => changes.push(Change {
+                path: case_path,
+                kind: ChangeKind::CaseAdded(Some(key.clone())),
+            }),
+        }
+    }
+
+    for key in old.fields.keys() {
+        if !new.fields.contains_key(key) {
+            changes.push(Change {
+                path: format!("{}/1/fields/{}", path, escape(key)),
+                kind: ChangeKind::CaseRemoved(Some(key.clone())),
+            });
+        }
+    }
+
+    let default_path = format!("{}/1/default", path);
+
+    match (&old.default, &new.default) {
+        (Some(old), Some(new)) => diff_type(old, new, default_path, changes),
+        (None, Some(_)) => changes.push(Change {
+            path: default_path,
+            kind: ChangeKind::CaseAdded(None),
+        }),
+        (Some(_), None) => changes.push(Change {
+            path: default_path,
+            kind: ChangeKind::CaseRemoved(None),
+        }),
+        (None, None) => {}
+    }
+}
+
+/// Pairs mappings by name, comparing keys as integers where possible.
+fn diff_mapper(old: &Mapper, new: &Mapper, path: &str, changes: &mut Vec<Change>) {
+    let same_key = |a: &str, b: &str| match (parse_integer_key(a), parse_integer_key(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    };
+
+    let find = |mapper: &Mapper, name: &str| {
+        mapper
+            .mappings
+            .iter()
+            .find(|(_, mapped)| *mapped == name)
+            .map(|(key, _)| key.clone())
+    };
+
+    for (key, name) in &new.mappings {
+        let mapping_path = format!("{}/1/mappings/{}", path, escape(key));
+
+        match find(old, name) {
+            Some(old_key) if same_key(&old_key, key) => {}
+            Some(old_key) => changes.push(Change {
+                path: mapping_path,
+                kind: ChangeKind::MappingChanged {
+                    name: name.clone(),
+                    old: old_key,
+                    new: key.clone(),
+                },
+            }),
+            None => changes.push(Change {
+                path: mapping_path,
+                kind: ChangeKind::MappingAdded {
+                    key: key.clone(),
+                    name: name.clone(),
+                },
+            }),
+        }
+    }
+
+    for (key, name) in &old.mappings {
+        if find(new, name).is_none() {
+            changes.push(Change {
+                path: format!("{}/1/mappings/{}", path, escape(key)),
+                kind: ChangeKind::MappingRemoved {
+                    key: key.clone(),
+                    name: name.clone(),
+                },
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::*;
+    use crate::*;
+
+    #[test]
+    fn test_diff() {
+        let old: Protocol = serde_json::from_str(
+            r#"{
+                "types": {
+                    "varint": "native",
+                    "string": "native",
+                    "packet_chat": ["container", [
+                        {"name": "message", "type": "string"},
+                        {"name": "position", "type": "i8"}
+                    ]],
+                    "packet_spawn": ["container", [
+                        {"name": "entityId", "type": "varint"},
+                        {"name": "x", "type": "f64"},
+                        {"name": "y", "type": "f64"}
+                    ]],
+                    "packet_keep_alive": ["container", [{"name": "id", "type": "i32"}]],
+                    "packet_removed": ["container", []],
+                    "packet": ["container", [
+                        {"name": "name", "type": ["mapper", {"type": "varint", "mappings": {
+                            "0x00": "chat", "0x01": "spawn", "0x02": "keep_alive", "0x03": "removed"
+                        }}]},
+                        {"name": "params", "type": ["switch", {"compareTo": "name", "fields": {
+                            "chat": "packet_chat",
+                            "spawn": "packet_spawn",
+                            "keep_alive": "packet_keep_alive",
+                            "removed": "packet_removed"
+                        }}]}
+                    ]]
+                }
+            }"#,
+        )
+        .unwrap();
+        let new: Protocol = serde_json::from_str(
+            r#"{
+                "types": {
+                    "varint": "native",
+                    "string": "native",
+                    "packet_chat": ["container", [
+                        {"name": "message", "type": "string"},
+                        {"name": "position", "type": "varint"},
+                        {"name": "sender", "type": "string"}
+                    ]],
+                    "packet_spawn": ["container", [
+                        {"name": "entityId", "type": "varint"},
+                        {"name": "y", "type": "f64"},
+                        {"name": "x", "type": "f64"}
+                    ]],
+                    "packet_heartbeat": ["container", [{"name": "id", "type": "i32"}]],
+                    "packet": ["container", [
+                        {"name": "name", "type": ["mapper", {"type": "varint", "mappings": {
+                            "0": "chat", "0x02": "spawn", "0x01": "heartbeat"
+                        }}]},
+                        {"name": "params", "type": ["switch", {"compareTo": "name", "fields": {
+                            "chat": "packet_chat",
+                            "spawn": "packet_spawn",
+                            "heartbeat": "packet_heartbeat"
+                        }}]}
+                    ]]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let changes: Vec<String> = diff(&old, &new).iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                "/types/packet_chat/1/1/type: changed type from \"i8\" to \"varint\"",
+                "/types/packet_chat/1/2: added field \"sender\"",
+                "/types/packet_spawn: reordered fields from entityId, x, y to entityId, y, x",
+                "/types/packet_heartbeat: renamed type packet_keep_alive to packet_heartbeat",
+                "/types/packet/1/0/type/1/mappings/0x02: remapped \"spawn\" from 0x01 to 0x02",
+                "/types/packet/1/0/type/1/mappings/0x01: added mapping 0x01 => \"heartbeat\"",
+                "/types/packet/1/0/type/1/mappings/0x02: removed mapping 0x02 => \"keep_alive\"",
+                "/types/packet/1/0/type/1/mappings/0x03: removed mapping 0x03 => \"removed\"",
+                "/types/packet/1/1/type/1/fields/heartbeat: added case \"heartbeat\"",
+                "/types/packet/1/1/type/1/fields/keep_alive: removed case \"keep_alive\"",
+                "/types/packet/1/1/type/1/fields/removed: removed case \"removed\"",
+                "/types/packet_removed: removed type packet_removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_protocols_data() {
+        let old = protocol("minecraft_v1444.json");
+        let new = protocol("minecraft_v1662.json");
+
+        assert_eq!(diff(&old, &old), Vec::new());
+
+        let changes = diff(&old, &new);
+
+        assert!(changes.iter().any(|change| matches!(
+            &change.kind,
+            ChangeKind::MappingChanged { name, .. } if name == "spawn_entity_living"
+        )));
+        assert!(changes.iter().any(|change| matches!(
+            &change.kind,
+            ChangeKind::TypeAdded(id) if id.to_string() == "play.toServer.packet_generate_structure"
+        )));
+        assert!(changes.iter().any(|change| matches!(
+            &change.kind,
+            ChangeKind::TypeRemoved(id) if id.to_string() == "play.toClient.packet_spawn_entity_weather"
+        )));
+    }
+}
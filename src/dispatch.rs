@@ -0,0 +1,287 @@
+//! Routing of packets by ID, from the `packet` type every Minecraft namespace
+//! defines as a container of a `name` mapper and a `params` switch on `name`:
+//!
+//! ```json
+//! "packet": ["container", [
+//!   { "name": "name", "type": ["mapper", { "type": "varint", "mappings": { "0x00": "chat" } }] },
+//!   { "name": "params", "type": ["switch", { "compareTo": "name", "fields": { "chat": "packet_chat" } }] }
+//! ]]
+//! ```
+
+use crate::mapping::{numeric_type, MappingError, Mappings};
+use crate::resolve::{namespaces, Scope};
+use crate::{Conditional, DataType, Field, Mapper, Numeric, Protocol, Structure, Switch, Util};
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Name of the type dispatching the packets of a namespace.
+const PACKET: &str = "packet";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DispatchError {
+    UnknownNamespace(String),
+    /// The namespace does not define a `packet` type of its own.
+    MissingPacket(String),
+    /// `packet` is not a container of a mapper and a switch on it.
+    UnrecognizedPacket(String),
+    /// The mapper does not map distinct integer IDs to distinct names.
+    InvalidMapping(MappingError),
+    /// A packet name has no case in the switch, which has no default.
+    MissingCase(String),
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DispatchError::UnknownNamespace(path) => write!(f, "unknown namespace \"{}\"", path),
+            DispatchError::MissingPacket(path) => {
+                write!(f, "namespace \"{}\" does not define a packet type", path)
+            }
+            DispatchError::UnrecognizedPacket(path) => write!(
+                f,
+                "packet type of namespace \"{}\" is not a mapper and a switch on it",
+                path
+            ),
+            DispatchError::InvalidMapping(error) => write!(f, "{}", error),
+            DispatchError::MissingCase(name) => write!(f, "no case for packet \"{}\"", name),
+        }
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+impl From<MappingError> for DispatchError {
+    fn from(error: MappingError) -> Self {
+        DispatchError::InvalidMapping(error)
+    }
+}
+
+/// A packet of a dispatch table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet<'a> {
+    pub id: i128,
+    pub name: &'a str,
+    /// The type of the packet's fields as written in its switch case, usually a
+    /// type name like `packet_chat` to be resolved in the namespace.
+    pub data_type: &'a DataType,
+}
+
+/// The packets of a namespace by ID and by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchTable<'a> {
+    /// Dot-separated path of the namespace, e.g. `play.toClient`.
+    pub namespace: String,
+    /// Type of the packet IDs.
+    pub id_type: Numeric,
+    packets: LinkedHashMap<i128, Packet<'a>>,
+    ids: HashMap<&'a str, i128>,
+}
+
+impl<'a> DispatchTable<'a> {
+    /// Builds the table from the `packet` type defined by a namespace, e.g. `play.toClient`.
+    pub fn new(protocol: &'a Protocol, namespace: &str) -> Result<Self, DispatchError> {
+        let scope = Scope::new(protocol, namespace)
+            .ok_or_else(|| DispatchError::UnknownNamespace(namespace.to_owned()))?;
+
+        let packet = match scope.find(PACKET) {
+            Some((id, definition)) if id.namespace == namespace => definition.data_type(),
+            _ => None,
+        };
+        let packet = packet.ok_or_else(|| DispatchError::MissingPacket(namespace.to_owned()))?;
+
+        let (mapper, switch) = recognize(packet)
+            .ok_or_else(|| DispatchError::UnrecognizedPacket(namespace.to_owned()))?;
+
+        let id_type = numeric_type(mapper, &scope)
+            .ok_or_else(|| MappingError::UnsupportedType(mapper.mappings_type.clone()))?;
+        let mappings = Mappings::new(mapper, &id_type)?;
+
+        let mut packets = LinkedHashMap::new();
+        let mut ids = HashMap::new();
+
+        for name in mapper.mappings.values() {
+            let id = mappings.key(name).expect("mapped name");
+            let data_type = switch
+                .fields
+                .get(name)
+                .or(switch.default.as_ref())
+                .ok_or_else(|| DispatchError::MissingCase(name.clone()))?;
+
+            ids.insert(name.as_str(), id);
+            packets.insert(
+                id,
+                Packet {
+                    id,
+                    name,
+                    data_type,
+                },
+            );
+        }
+
+        Ok(DispatchTable {
+            namespace: namespace.to_owned(),
+            id_type,
+            packets,
+            ids,
+        })
+    }
+
+    pub fn get(&self, id: i128) -> Option<&Packet<'a>> {
+        self.packets.get(&id)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Packet<'a>> {
+        self.ids.get(name).and_then(|id| self.packets.get(id))
+    }
+
+    /// Packets in order of the mappings.
+    pub fn packets(&self) -> impl Iterator<Item = &Packet<'a>> {
+        self.packets.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+}
+
+/// Dispatch tables of all namespaces defining a `packet` type of the usual shape,
+/// in order of declaration. Namespaces with a `packet` of another shape are skipped.
+pub fn dispatch_tables(protocol: &Protocol) -> Result<Vec<DispatchTable<'_>>, DispatchError> {
+    let mut tables = Vec::new();
+
+    for (_, namespace, _) in namespaces(protocol) {
+        match DispatchTable::new(protocol, &namespace) {
+            Ok(table) => tables.push(table),
+            Err(DispatchError::MissingPacket(_)) | Err(DispatchError::UnrecognizedPacket(_)) => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Finds a mapper field and a switch on it among the fields of a container.
+fn recognize(packet: &DataType) -> Option<(&Mapper, &Switch)> {
+    let fields: &[Field] = match packet {
+        DataType::Structure(structure) => match structure.as_ref() {
+            Structure::Container(fields) => fields,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    fields.iter().find_map(|field| {
+        let name = field.name.as_ref()?;
+        let mapper = match &field.field_type {
+            DataType::Util(util) => match util.as_ref() {
+                Util::Mapper(mapper) => mapper,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let switch = fields.iter().find_map(|field| match &field.field_type {
+            DataType::Conditional(conditional) => match conditional.as_ref() {
+                Conditional::Switch(switch) if switch.compare_to == *name => Some(switch),
+                _ => None,
+            },
+            _ => None,
+        })?;
+
+        Some((mapper, switch))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dispatch::*;
+    use crate::*;
+
+    #[test]
+    fn test_dispatch_table() {
+        let protocol = protocol("minecraft_v1662.json");
+        let table = DispatchTable::new(&protocol, "play.toClient").unwrap();
+
+        assert_eq!(table.id_type, Numeric::VarInt);
+
+        let packet = table.get(0x47).unwrap();
+        assert_eq!(packet.name, "entity_equipment");
+        assert_eq!(
+            packet.data_type,
+            &DataType::Custom("packet_entity_equipment".to_owned())
+        );
+        assert_eq!(table.by_name("entity_equipment"), Some(packet));
+        assert_eq!(table.packets().next().unwrap().name, "spawn_entity");
+        assert!(table.get(0x7f).is_none());
+
+        assert_eq!(
+            DispatchTable::new(&protocol, ""),
+            Err(DispatchError::MissingPacket(String::new()))
+        );
+        assert_eq!(
+            DispatchTable::new(&protocol, "play.nowhere"),
+            Err(DispatchError::UnknownNamespace("play.nowhere".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_tables() {
+        let v1662 = protocol("minecraft_v1662.json");
+        let tables = dispatch_tables(&v1662).unwrap();
+        let namespaces: Vec<&str> = tables
+            .iter()
+            .map(|table| table.namespace.as_str())
+            .collect();
+
+        assert_eq!(
+            namespaces,
+            vec![
+                "handshaking.toClient",
+                "handshaking.toServer",
+                "status.toClient",
+                "status.toServer",
+                "login.toClient",
+                "login.toServer",
+                "play.toClient",
+                "play.toServer",
+            ]
+        );
+        assert_eq!(tables[1].get(0).unwrap().name, "set_protocol");
+
+        for name in &["minecraft_v030c.json", "minecraft_v1444.json"] {
+            dispatch_tables(&protocol(name)).unwrap();
+        }
+
+        // `lu8` is a native type of unknown width.
+        assert_eq!(
+            dispatch_tables(&protocol("diablo2.json")).unwrap_err(),
+            DispatchError::InvalidMapping(MappingError::UnsupportedType("lu8".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_missing_case() {
+        let protocol: Protocol = serde_json::from_str(
+            r#"{
+                "types": {"varint": "native"},
+                "play": {"types": {
+                    "packet": ["container", [
+                        {"name": "name", "type": ["mapper", {"type": "varint", "mappings": {"0x00": "chat"}}]},
+                        {"name": "params", "type": ["switch", {"compareTo": "name", "fields": {}}]}
+                    ]]
+                }}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            DispatchTable::new(&protocol, "play"),
+            Err(DispatchError::MissingCase("chat".to_owned()))
+        );
+    }
+}
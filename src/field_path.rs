@@ -0,0 +1,427 @@
+//! References from switches and arrays to other fields, like `"../action"`,
+//! `"flags/present"` or `"$compareTo"`, and their resolution to field definitions.
+
+use crate::resolve::Scope;
+use crate::{BitField, Conditional, DataType, Field, Protocol, Structure, TypeDefinition, Util};
+use std::fmt;
+use std::str::FromStr;
+
+/// How many custom types are followed when looking up the fields of a type.
+const MAX_ALIAS_DEPTH: usize = 32;
+
+/// A parsed `compareTo`, `count` or `countFor` reference.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldPath {
+    /// `$name`, a parameter of the template the reference appears in.
+    Parameter(String),
+    /// Leaves `parents` enclosing containers with `..`, then descends through
+    /// the named fields, e.g. `../flags/present`.
+    Relative { parents: usize, fields: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldPathError {
+    /// The path names no field, e.g. `""` or `".."`.
+    Empty,
+    /// A `..` segment follows a field name, e.g. `flags/../action`.
+    MisplacedParent,
+    /// A `$` parameter is not the whole path, e.g. `../$compareTo`.
+    MisplacedParameter,
+}
+
+impl fmt::Display for FieldPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldPathError::Empty => f.write_str("field path names no field"),
+            FieldPathError::MisplacedParent => f.write_str("\"..\" follows a field name"),
+            FieldPathError::MisplacedParameter => {
+                f.write_str("parameter is not the whole field path")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FieldPathError {}
+
+impl FromStr for FieldPath {
+    type Err = FieldPathError;
+
+    /// Parses a path, ignoring empty and `.` segments like the codec does.
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = path.strip_prefix('$') {
+            return match name {
+                "" => Err(FieldPathError::Empty),
+                name if name.contains('/') => Err(FieldPathError::MisplacedParameter),
+                name => Ok(FieldPath::Parameter(name.to_owned())),
+            };
+        }
+
+        let mut parents = 0;
+        let mut fields = Vec::new();
+
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." if fields.is_empty() => parents += 1,
+                ".." => return Err(FieldPathError::MisplacedParent),
+                name if name.starts_with('$') => return Err(FieldPathError::MisplacedParameter),
+                name => fields.push(name.to_owned()),
+            }
+        }
+
+        if fields.is_empty() {
+            return Err(FieldPathError::Empty);
+        }
+
+        Ok(FieldPath::Relative { parents, fields })
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldPath::Parameter(name) => write!(f, "${}", name),
+            FieldPath::Relative { parents, fields } => {
+                for _ in 0..*parents {
+                    f.write_str("../")?;
+                }
+
+                f.write_str(&fields.join("/"))
+            }
+        }
+    }
+}
+
+/// The definition a field path refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    Field(&'a Field),
+    BitField(&'a BitField),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// The path is a template parameter, substituted when the template is instantiated.
+    Parameter(String),
+    /// The path leaves the outermost container, so depends on where the definition is used.
+    Escapes,
+    /// No visible field has this name.
+    UnknownField(String),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveError::Parameter(name) => write!(f, "parameter \"${}\" is not bound", name),
+            ResolveError::Escapes => f.write_str("field path leaves the outermost container"),
+            ResolveError::UnknownField(name) => write!(f, "unknown field \"{}\"", name),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// A field visible to references, with the position of the container field it comes from.
+struct Member<'a> {
+    name: &'a str,
+    target: Target<'a>,
+    /// Namespace the type of the field is resolved from.
+    namespace: String,
+    position: usize,
+}
+
+/// The fields of a container being read.
+struct Frame<'a> {
+    members: Vec<Member<'a>>,
+    /// Position of the field being read.
+    current: usize,
+}
+
+/// The containers enclosing a reference, innermost last, as pushed while walking
+/// a definition. Arrays and switches do not have frames of their own.
+pub struct Context<'a> {
+    protocol: &'a Protocol,
+    frames: Vec<Frame<'a>>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(protocol: &'a Protocol) -> Self {
+        Context {
+            protocol,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Enters a container of a definition in `namespace`, reading its first field.
+    pub fn push(&mut self, fields: &'a [Field], namespace: &str) {
+        let mut members = Vec::new();
+
+        for (position, field) in fields.iter().enumerate() {
+            field_members(self.protocol, field, namespace, position, 0, &mut members);
+        }
+
+        self.frames.push(Frame {
+            members,
+            current: 0,
+        });
+    }
+
+    pub fn pop(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Moves to the field at `position` of the innermost container.
+    pub fn advance(&mut self, position: usize) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.current = position;
+        }
+    }
+
+    /// Resolves a path against the fields read before the current ones, as a
+    /// `compareTo` or `count` is.
+    pub fn resolve(&self, path: &FieldPath) -> Result<Target<'a>, ResolveError> {
+        self.lookup(path, true)
+    }
+
+    /// Resolves a path against all fields of the containers, as a `countFor` is.
+    pub fn resolve_declared(&self, path: &FieldPath) -> Result<Target<'a>, ResolveError> {
+        self.lookup(path, false)
+    }
+
+    fn lookup(&self, path: &FieldPath, read: bool) -> Result<Target<'a>, ResolveError> {
+        let (parents, fields) = match path {
+            FieldPath::Parameter(name) => return Err(ResolveError::Parameter(name.clone())),
+            FieldPath::Relative { parents, fields } => (*parents, fields),
+        };
+
+        if parents >= self.frames.len() {
+            return Err(ResolveError::Escapes);
+        }
+
+        let frame = &self.frames[self.frames.len() - 1 - parents];
+        let (first, rest) = fields.split_first().ok_or(ResolveError::Escapes)?;
+
+        let mut member = frame
+            .members
+            .iter()
+            .rev()
+            .find(|member| member.name == first && (!read || member.position < frame.current))
+            .ok_or_else(|| ResolveError::UnknownField(first.clone()))?;
+
+        let mut members;
+
+        for name in rest {
+            members = self.members_of(member);
+            member = members
+                .iter()
+                .find(|member| member.name == name)
+                .ok_or_else(|| ResolveError::UnknownField(name.clone()))?;
+        }
+
+        Ok(member.target)
+    }
+
+    fn members_of(&self, member: &Member<'a>) -> Vec<Member<'a>> {
+        let mut members = Vec::new();
+
+        if let Target::Field(field) = member.target {
+            type_members(
+                self.protocol,
+                &field.field_type,
+                &member.namespace,
+                0,
+                0,
+                &mut members,
+            );
+        }
+
+        members
+    }
+}
+
+/// Collects the fields a container field makes visible: itself, or the fields
+/// of its type if it is anonymous.
+fn field_members<'a>(
+    protocol: &'a Protocol,
+    field: &'a Field,
+    namespace: &str,
+    position: usize,
+    depth: usize,
+    members: &mut Vec<Member<'a>>,
+) {
+    match &field.name {
+        Some(name) if !field.is_anonymous() => members.push(Member {
+            name,
+            target: Target::Field(field),
+            namespace: namespace.to_owned(),
+            position,
+        }),
+        _ => type_members(
+            protocol,
+            &field.field_type,
+            namespace,
+            position,
+            depth,
+            members,
+        ),
+    }
+}
+
+/// Collects the fields of a container or bitfield type, or of every case of a switch.
+fn type_members<'a>(
+    protocol: &'a Protocol,
+    data_type: &'a DataType,
+    namespace: &str,
+    position: usize,
+    depth: usize,
+    members: &mut Vec<Member<'a>>,
+) {
+    match data_type {
+        DataType::Structure(structure) => {
+            if let Structure::Container(fields) = structure.as_ref() {
+                for field in fields {
+                    field_members(protocol, field, namespace, position, depth, members);
+                }
+            }
+        }
+        DataType::Util(util) => {
+            if let Util::Bitfield(fields) = util.as_ref() {
+                members.extend(fields.iter().map(|field| Member {
+                    name: &field.name,
+                    target: Target::BitField(field),
+                    namespace: namespace.to_owned(),
+                    position,
+                }));
+            }
+        }
+        DataType::Conditional(conditional) => {
+            if let Conditional::Switch(switch) = conditional.as_ref() {
+                for data_type in switch.fields.values().chain(switch.default.as_ref()) {
+                    type_members(protocol, data_type, namespace, position, depth, members);
+                }
+            }
+        }
+        DataType::Custom(name) if depth < MAX_ALIAS_DEPTH => {
+            let found = Scope::new(protocol, namespace).and_then(|scope| scope.find(name));
+
+            if let Some((id, TypeDefinition::DataType(data_type))) = found {
+                type_members(
+                    protocol,
+                    data_type,
+                    &id.namespace,
+                    position,
+                    depth + 1,
+                    members,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field_path::*;
+    use crate::*;
+
+    fn container(data_type: &DataType) -> &[Field] {
+        match data_type {
+            DataType::Structure(structure) => match structure.as_ref() {
+                Structure::Container(fields) => fields,
+                structure => panic!("unexpected structure: {:?}", structure),
+            },
+            data_type => panic!("unexpected type: {:?}", data_type),
+        }
+    }
+
+    #[test]
+    fn test_parse_field_path() {
+        let relative = |parents, fields: &[&str]| FieldPath::Relative {
+            parents,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+        };
+
+        assert_eq!("action".parse(), Ok(relative(0, &["action"])));
+        assert_eq!("../action".parse(), Ok(relative(1, &["action"])));
+        assert_eq!(
+            "../flags/has_custom_suggestions".parse(),
+            Ok(relative(1, &["flags", "has_custom_suggestions"]))
+        );
+        assert_eq!("./flags/".parse(), Ok(relative(0, &["flags"])));
+        assert_eq!(
+            "$compareTo".parse(),
+            Ok(FieldPath::Parameter("compareTo".to_owned()))
+        );
+
+        assert_eq!("".parse::<FieldPath>(), Err(FieldPathError::Empty));
+        assert_eq!("../..".parse::<FieldPath>(), Err(FieldPathError::Empty));
+        assert_eq!(
+            "flags/../action".parse::<FieldPath>(),
+            Err(FieldPathError::MisplacedParent)
+        );
+        assert_eq!(
+            "../$compareTo".parse::<FieldPath>(),
+            Err(FieldPathError::MisplacedParameter)
+        );
+
+        assert_eq!(
+            relative(2, &["flags", "present"]).to_string(),
+            "../../flags/present"
+        );
+    }
+
+    #[test]
+    fn test_resolve_field_path() {
+        let json = r#"{
+            "types": {
+                "varint": "native",
+                "flags": ["bitfield", [{"name": "present", "size": 8, "signed": false}]],
+                "packet": ["container", [
+                    {"name": "action", "type": "varint"},
+                    {"name": "flags", "type": "flags"},
+                    {"name": "data", "type": ["container", [
+                        {"name": "kind", "type": "varint"},
+                        {"anon": true, "type": ["container", [{"name": "hidden", "type": "varint"}]]}
+                    ]]}
+                ]]
+            }
+        }"#;
+
+        let protocol: Protocol = serde_json::from_str(json).unwrap();
+        let packet = container(protocol.types["packet"].data_type().unwrap());
+        let data = container(&packet[2].field_type);
+        let hidden = &container(&data[1].field_type)[0];
+
+        let mut context = Context::new(&protocol);
+        context.push(packet, "");
+        context.advance(2);
+        context.push(data, "");
+        context.advance(1);
+
+        let resolve = |path: &str| context.resolve(&path.parse().unwrap());
+
+        assert_eq!(resolve("kind"), Ok(Target::Field(&data[0])));
+        assert_eq!(resolve("../action"), Ok(Target::Field(&packet[0])));
+        match resolve("../flags/present") {
+            Ok(Target::BitField(field)) => assert_eq!(field.name, "present"),
+            target => panic!("unexpected target: {:?}", target),
+        }
+        assert_eq!(
+            resolve("hidden"),
+            Err(ResolveError::UnknownField("hidden".to_owned()))
+        );
+        assert_eq!(
+            context.resolve_declared(&"hidden".parse().unwrap()),
+            Ok(Target::Field(hidden))
+        );
+        assert_eq!(
+            resolve("../flags/missing"),
+            Err(ResolveError::UnknownField("missing".to_owned()))
+        );
+        assert_eq!(resolve("../../action"), Err(ResolveError::Escapes));
+        assert_eq!(
+            resolve("$compareTo"),
+            Err(ResolveError::Parameter("compareTo".to_owned()))
+        );
+    }
+}
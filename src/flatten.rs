@@ -0,0 +1,278 @@
+//! Effective fields of containers, with anonymous fields merged into them.
+//!
+//! A field with `anon: true` has no name of its own: the fields of its
+//! container, or of the container selected by its switch, become fields of the
+//! parent container. This is how references like `compareTo` see them, and how
+//! decoded values are laid out.
+
+use crate::walk::escape;
+use crate::{Conditional, DataType, Field, Structure};
+
+/// A field of a container after merging anonymous fields into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatField<'a> {
+    pub name: &'a str,
+    pub field: &'a Field,
+    /// JSON pointer to the field relative to the container, e.g. `/1/1/type/1/fields/true/1/0`.
+    pub path: String,
+    /// Switch cases the field is only present in, outermost first.
+    pub cases: Vec<Case<'a>>,
+}
+
+/// A case of an anonymous switch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case<'a> {
+    /// JSON pointer to the switch relative to the container.
+    pub switch: String,
+    /// Key of the case, or `None` for the default.
+    pub key: Option<&'a str>,
+}
+
+/// Two fields with the same name which can be present at the same time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision<'a> {
+    pub name: &'a str,
+    pub first: String,
+    pub second: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flattened<'a> {
+    /// Named fields in order of declaration.
+    pub fields: Vec<FlatField<'a>>,
+    pub collisions: Vec<Collision<'a>>,
+    /// Pointers to anonymous fields whose fields are not known without resolving
+    /// their type, e.g. a custom type name.
+    pub opaque: Vec<String>,
+}
+
+impl<'a> FlatField<'a> {
+    /// Whether the field is present in every value of the container.
+    pub fn is_conditional(&self) -> bool {
+        !self.cases.is_empty()
+    }
+
+    /// Whether two fields are in different cases of the same switch, so never present together.
+    pub fn excludes(&self, other: &FlatField) -> bool {
+        self.cases.iter().any(|case| {
+            other
+                .cases
+                .iter()
+                .any(|other| case.switch == other.switch && case.key != other.key)
+        })
+    }
+}
+
+/// Computes the effective fields of a container and reports name collisions.
+pub fn flatten(fields: &[Field]) -> Flattened<'_> {
+    let mut flattened = Flattened {
+        fields: Vec::new(),
+        collisions: Vec::new(),
+        opaque: Vec::new(),
+    };
+
+    flatten_fields(fields, "", &[], &mut flattened);
+
+    for (i, field) in flattened.fields.iter().enumerate() {
+        for other in &flattened.fields[..i] {
+            if field.name == other.name && !field.excludes(other) {
+                flattened.collisions.push(Collision {
+                    name: field.name,
+                    first: other.path.clone(),
+                    second: field.path.clone(),
+                });
+            }
+        }
+    }
+
+    flattened
+}
+
+fn flatten_fields<'a>(
+    fields: &'a [Field],
+    prefix: &str,
+    cases: &[Case<'a>],
+    flattened: &mut Flattened<'a>,
+) {
+    for (i, field) in fields.iter().enumerate() {
+        let path = format!("{}/1/{}", prefix, i);
+
+        match &field.name {
+            Some(name) if !field.is_anonymous() => flattened.fields.push(FlatField {
+                name,
+                field,
+                path,
+                cases: cases.to_vec(),
+            }),
+            _ => flatten_type(
+                &field.field_type,
+                &format!("{}/type", path),
+                cases,
+                flattened,
+            ),
+        }
+    }
+}
+
+/// Merges the fields of the type of an anonymous field.
+fn flatten_type<'a>(
+    data_type: &'a DataType,
+    path: &str,
+    cases: &[Case<'a>],
+    flattened: &mut Flattened<'a>,
+) {
+    match data_type {
+        // Values of other structures are not maps, so they have no fields to merge.
+        DataType::Structure(structure) => {
+            if let Structure::Container(fields) = structure.as_ref() {
+                flatten_fields(fields, path, cases, flattened)
+            }
+        }
+        DataType::Conditional(conditional) => match conditional.as_ref() {
+            Conditional::Switch(switch) => {
+                let branches = switch
+                    .fields()
+                    .iter()
+                    .map(|(key, data_type)| {
+                        let pointer = format!("{}/1/fields/{}", path, escape(key));
+                        (Some(key.as_str()), data_type, pointer)
+                    })
+                    .chain(
+                        switch
+                            .default()
+                            .map(|data_type| (None, data_type, format!("{}/1/default", path))),
+                    );
+
+                for (key, data_type, pointer) in branches {
+                    let mut cases = cases.to_vec();
+                    cases.push(Case {
+                        switch: path.to_owned(),
+                        key,
+                    });
+
+                    flatten_type(data_type, &pointer, &cases, flattened);
+                }
+            }
+            Conditional::Option(_) => {}
+        },
+        DataType::Parameterized { .. } | DataType::Custom(_) => {
+            flattened.opaque.push(path.to_owned())
+        }
+        DataType::Numeric(_) | DataType::Primitive(_) | DataType::Util(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flatten::*;
+    use crate::*;
+
+    fn container(json: &str) -> Vec<Field> {
+        match serde_json::from_str(json).expect("Failed to parse container") {
+            Structure::Container(fields) => fields,
+            structure => panic!("unexpected structure: {:?}", structure),
+        }
+    }
+
+    #[test]
+    fn test_flatten_switch_branches() {
+        let fields = container(
+            r#"["container", [
+                {"name": "present", "type": "bool"},
+                {"anon": true, "type": ["switch", {
+                    "compareTo": "present",
+                    "fields": {
+                        "false": "void",
+                        "true": ["container", [
+                            {"name": "itemId", "type": "varint"},
+                            {"anon": true, "type": ["container", [
+                                {"name": "count", "type": "i8"}
+                            ]]}
+                        ]]
+                    },
+                    "default": ["container", [{"name": "itemId", "type": "i32"}]]
+                }]},
+                {"anon": true, "type": "extra"}
+            ]]"#,
+        );
+
+        let flattened = flatten(&fields);
+        let names: Vec<(&str, &str)> = flattened
+            .fields
+            .iter()
+            .map(|field| (field.name, field.path.as_str()))
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                ("present", "/1/0"),
+                ("itemId", "/1/1/type/1/fields/true/1/0"),
+                ("count", "/1/1/type/1/fields/true/1/1/type/1/0"),
+                ("itemId", "/1/1/type/1/default/1/0"),
+            ]
+        );
+        assert!(!flattened.fields[0].is_conditional());
+        assert_eq!(
+            flattened.fields[1].cases,
+            vec![Case {
+                switch: "/1/1/type".to_owned(),
+                key: Some("true"),
+            }]
+        );
+        assert_eq!(flattened.collisions, Vec::new());
+        assert_eq!(flattened.opaque, vec!["/1/2/type".to_owned()]);
+    }
+
+    #[test]
+    fn test_flatten_collisions() {
+        let fields = container(
+            r#"["container", [
+                {"name": "id", "type": "varint"},
+                {"anon": true, "type": ["container", [{"name": "id", "type": "u8"}]]},
+                {"anon": true, "type": ["switch", {
+                    "compareTo": "id",
+                    "fields": {"1": ["container", [{"name": "id", "type": "u8"}]]}
+                }]}
+            ]]"#,
+        );
+
+        let collisions = flatten(&fields).collisions;
+
+        assert_eq!(
+            collisions,
+            vec![
+                Collision {
+                    name: "id",
+                    first: "/1/0".to_owned(),
+                    second: "/1/1/type/1/0".to_owned(),
+                },
+                Collision {
+                    name: "id",
+                    first: "/1/0".to_owned(),
+                    second: "/1/2/type/1/fields/1/1/0".to_owned(),
+                },
+                Collision {
+                    name: "id",
+                    first: "/1/1/type/1/0".to_owned(),
+                    second: "/1/2/type/1/fields/1/1/0".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flatten_protocols_data() {
+        for (_, protocol) in all_protocols() {
+            for (_, _, types) in crate::resolve::namespaces(&protocol) {
+                for (_, definition) in types {
+                    if let Some(DataType::Structure(structure)) = definition.data_type() {
+                        if let Structure::Container(fields) = structure.as_ref() {
+                            assert_eq!(flatten(fields).collisions, Vec::new());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,30 +1,70 @@
 use linked_hash_map::LinkedHashMap;
 use serde::de;
 use serde::de::{SeqAccess, Unexpected, Visitor};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::fmt;
-use std::io::Read;
+use std::io::{Read, Write};
+
+pub mod codec;
+pub mod codegen;
+pub mod diff;
+pub mod dispatch;
+pub mod field_path;
+pub mod flatten;
+pub mod mapping;
+mod parse;
+pub mod resolve;
+pub mod size;
+pub mod validate;
+pub mod value;
+mod walk;
+
+pub use parse::ParseError;
+pub use value::{Value, ValueRef};
+
+/// Reads a protocol, locating the node that failed to parse on error.
+pub fn read_protocol<R: Read>(mut reader: R) -> Result<Protocol, ParseError> {
+    let mut source = String::new();
+    reader
+        .read_to_string(&mut source)
+        .map_err(serde_json::Error::io)?;
+
+    parse::parse_protocol(&source)
+}
 
-pub fn read_protocol<R: Read>(reader: R) -> serde_json::Result<Protocol> {
-    serde_json::from_reader(reader)
+/// Writes a protocol as pretty-printed protodef JSON, which reads back into an equal `Protocol`.
+pub fn write_protocol<W: Write>(writer: W, protocol: &Protocol) -> serde_json::Result<()> {
+    serde_json::to_writer_pretty(writer, protocol)
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Protocol {
-    pub types: LinkedHashMap<String, DataType>,
+    pub types: LinkedHashMap<String, TypeDefinition>,
     #[serde(flatten)]
     pub namespaces: LinkedHashMap<String, Namespace>,
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum Namespace {
     Map(LinkedHashMap<String, Namespace>),
+    Definition(TypeDefinition),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TypeDefinition {
+    /// A type declared as `"native"`, implemented by the runtime.
+    #[serde(
+        deserialize_with = "deserialize_native",
+        serialize_with = "serialize_native"
+    )]
+    Native,
     DataType(DataType),
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum DataType {
     Conditional(Box<Conditional>),
@@ -32,42 +72,58 @@ pub enum DataType {
     Primitive(Primitive),
     Structure(Box<Structure>),
     Util(Box<Util>),
+    /// Reference to a template type with `$` placeholders, e.g.
+    /// `["particleData", { "compareTo": "particleId" }]`.
+    #[serde(
+        deserialize_with = "deserialize_parameterized",
+        serialize_with = "serialize_parameterized"
+    )]
+    Parameterized {
+        name: String,
+        args: LinkedHashMap<String, String>,
+    },
     Custom(String),
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Conditional {
     Switch(Switch),
     Option(DataType),
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Switch {
+    #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
     #[serde(rename = "compareTo")]
     compare_to: String,
     fields: LinkedHashMap<String, DataType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     default: Option<DataType>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Numeric {
     Byte { signed: bool },
     Short { signed: bool, byte_order: ByteOrder },
     Int { signed: bool, byte_order: ByteOrder },
     Long { signed: bool, byte_order: ByteOrder },
+    Int128 { signed: bool, byte_order: ByteOrder },
     Float { byte_order: ByteOrder },
     Double { byte_order: ByteOrder },
     VarInt,
+    VarLong,
+    ZigZag32,
+    ZigZag64,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ByteOrder {
     BigEndian,
     LittleEndian,
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Primitive {
     #[serde(rename = "bool")]
@@ -77,7 +133,7 @@ pub enum Primitive {
     Void,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Structure {
     /// Represents a list of values with same type.
     Array(Array),
@@ -87,19 +143,20 @@ pub enum Structure {
     Count(Count),
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Array {
     /// The type of length prefix.
-    #[serde(rename = "countType")]
+    #[serde(rename = "countType", skip_serializing_if = "Option::is_none")]
     pub count_type: Option<DataType>,
     /// A reference to the field counting the elements, or a fixed size.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub count: Option<ArrayCount>,
     /// The type of the elements.
     #[serde(rename = "type")]
     pub elements_type: DataType,
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum ArrayCount {
     /// Reference to the field counting the elements.
@@ -108,17 +165,18 @@ pub enum ArrayCount {
     FixedLength(u32),
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Field {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     #[serde(rename = "type")]
     pub field_type: DataType,
-    // Useless.
-    #[serde(rename = "anon")]
+    /// Whether the fields of the type, a container or a switch, are merged into the parent container.
+    #[serde(rename = "anon", skip_serializing_if = "Option::is_none")]
     anonymous: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Count {
     /// The type of count.
     #[serde(rename = "type")]
@@ -128,7 +186,7 @@ pub struct Count {
     pub count_for: String,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Util {
     Buffer(Buffer),
     Mapper(Mapper),
@@ -138,39 +196,347 @@ pub enum Util {
     TopBitSetTerminatedArray(Box<Structure>),
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Buffer {
     /// The type of length prefix.
-    #[serde(rename = "countType")]
+    #[serde(rename = "countType", skip_serializing_if = "Option::is_none")]
     pub count_type: Option<DataType>,
     /// A reference to the field counting the elements, or a fixed size.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub count: Option<ArrayCount>,
     /// Represent rest bytes as-is.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub rest: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Mapper {
     #[serde(rename = "type")]
     pub mappings_type: String,
     pub mappings: LinkedHashMap<String, String>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct BitField {
     pub name: String,
     pub size: usize,
     pub signed: bool,
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Loop {
+    /// The name the loop is declared with, e.g. `entityMetadataLoop`.
+    #[serde(skip)]
+    pub name: String,
     #[serde(rename = "endVal")]
     pub end_val: u32,
     #[serde(rename = "type")]
     pub data_type: DataType,
 }
 
+impl Protocol {
+    /// The node at a dot-separated path into the namespaces, e.g. `play.toClient`
+    /// or `play.toClient.types.packet_chat`.
+    pub fn get(&self, path: &str) -> Option<&Namespace> {
+        let mut names = path.split('.');
+        let mut current = self.namespaces.get(names.next()?)?;
+
+        for name in names {
+            current = match current {
+                Namespace::Map(map) => map.get(name)?,
+                Namespace::Definition(_) => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    /// Namespaces having their own `types` along with their dot-separated paths,
+    /// in order of declaration. The root types are not included.
+    pub fn leaf_namespaces(&self) -> impl Iterator<Item = (String, &Namespace)> {
+        fn collect<'a>(
+            map: &'a LinkedHashMap<String, Namespace>,
+            path: &str,
+            leaves: &mut Vec<(String, &'a Namespace)>,
+        ) {
+            for (name, namespace) in map {
+                if let Namespace::Map(children) = namespace {
+                    if name == "types" {
+                        continue;
+                    }
+
+                    let path = if path.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}.{}", path, name)
+                    };
+
+                    if let Some(Namespace::Map(_)) = children.get("types") {
+                        leaves.push((path.clone(), namespace));
+                    }
+
+                    collect(children, &path, leaves);
+                }
+            }
+        }
+
+        let mut leaves = Vec::new();
+        collect(&self.namespaces, "", &mut leaves);
+        leaves.into_iter()
+    }
+
+    /// Looks up a type as it is visible from the namespace at a dot-separated path,
+    /// falling back to enclosing namespaces and the root types.
+    pub fn lookup(&self, namespace: &str, name: &str) -> Option<&TypeDefinition> {
+        resolve::Scope::new(self, namespace)?.lookup(name)
+    }
+}
+
+impl Namespace {
+    /// The definitions in the `types` of a namespace, in order of declaration.
+    pub fn types(&self) -> impl Iterator<Item = (&String, &TypeDefinition)> {
+        let types = match self {
+            Namespace::Map(map) => match map.get("types") {
+                Some(Namespace::Map(types)) => Some(types),
+                _ => None,
+            },
+            Namespace::Definition(_) => None,
+        };
+
+        types
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, definition)| match definition {
+                Namespace::Definition(definition) => Some((name, definition)),
+                Namespace::Map(_) => None,
+            })
+    }
+}
+
+impl Switch {
+    /// Creates a switch comparing against a field, e.g. `../action`, without cases.
+    pub fn new(compare_to: &str) -> Self {
+        Switch {
+            name: None,
+            compare_to: compare_to.to_owned(),
+            fields: LinkedHashMap::new(),
+            default: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Adds a case, replacing any previous case with the same key.
+    pub fn with_field(mut self, key: &str, data_type: DataType) -> Self {
+        self.fields.insert(key.to_owned(), data_type);
+        self
+    }
+
+    pub fn with_default(mut self, data_type: DataType) -> Self {
+        self.default = Some(data_type);
+        self
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Reference to the field the cases are selected by.
+    pub fn compare_to(&self) -> &str {
+        &self.compare_to
+    }
+
+    /// Types of the cases by their keys.
+    pub fn fields(&self) -> &LinkedHashMap<String, DataType> {
+        &self.fields
+    }
+
+    /// Type of values matching no case.
+    pub fn default(&self) -> Option<&DataType> {
+        self.default.as_ref()
+    }
+}
+
+impl Mapper {
+    /// The underlying type if it is a built-in numeric type, e.g. `varint`.
+    pub fn numeric_type(&self) -> Option<Numeric> {
+        match codec::mapper_type(self) {
+            DataType::Numeric(numeric) => Some(numeric),
+            _ => None,
+        }
+    }
+
+    /// Parses the keys as integers of the underlying type, which must be a built-in
+    /// integer type. Use `Mappings::new` for types declared in the protocol.
+    pub fn parse_mappings(&self) -> Result<mapping::Mappings, mapping::MappingError> {
+        match self.numeric_type() {
+            Some(numeric) => mapping::Mappings::new(self, &numeric),
+            None => Err(mapping::MappingError::UnsupportedType(
+                self.mappings_type.clone(),
+            )),
+        }
+    }
+}
+
+impl Field {
+    pub fn new(name: &str, field_type: DataType) -> Self {
+        Field {
+            name: Some(name.to_owned()),
+            field_type,
+            anonymous: None,
+        }
+    }
+
+    /// Creates an anonymous field, whose container or switch fields are merged into the parent.
+    pub fn anonymous(field_type: DataType) -> Self {
+        Field {
+            name: None,
+            field_type,
+            anonymous: Some(true),
+        }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous == Some(true)
+    }
+}
+
+impl TypeDefinition {
+    pub fn is_native(&self) -> bool {
+        matches!(self, TypeDefinition::Native)
+    }
+
+    /// Returns the type, unless it is native.
+    pub fn data_type(&self) -> Option<&DataType> {
+        match self {
+            TypeDefinition::Native => None,
+            TypeDefinition::DataType(data_type) => Some(data_type),
+        }
+    }
+}
+
+impl Numeric {
+    /// The name of the type in protodef, e.g. `li32`.
+    pub fn name(&self) -> &'static str {
+        use ByteOrder::*;
+
+        match self {
+            Numeric::Byte { signed: true } => "i8",
+            Numeric::Byte { signed: false } => "u8",
+            Numeric::Short { signed, byte_order } => match (signed, byte_order) {
+                (true, BigEndian) => "i16",
+                (false, BigEndian) => "u16",
+                (true, LittleEndian) => "li16",
+                (false, LittleEndian) => "lu16",
+            },
+            Numeric::Int { signed, byte_order } => match (signed, byte_order) {
+                (true, BigEndian) => "i32",
+                (false, BigEndian) => "u32",
+                (true, LittleEndian) => "li32",
+                (false, LittleEndian) => "lu32",
+            },
+            Numeric::Long { signed, byte_order } => match (signed, byte_order) {
+                (true, BigEndian) => "i64",
+                (false, BigEndian) => "u64",
+                (true, LittleEndian) => "li64",
+                (false, LittleEndian) => "lu64",
+            },
+            Numeric::Int128 { signed, byte_order } => match (signed, byte_order) {
+                (true, BigEndian) => "i128",
+                (false, BigEndian) => "u128",
+                (true, LittleEndian) => "li128",
+                (false, LittleEndian) => "lu128",
+            },
+            Numeric::Float {
+                byte_order: BigEndian,
+            } => "f32",
+            Numeric::Float {
+                byte_order: LittleEndian,
+            } => "lf32",
+            Numeric::Double {
+                byte_order: BigEndian,
+            } => "f64",
+            Numeric::Double {
+                byte_order: LittleEndian,
+            } => "lf64",
+            Numeric::VarInt => "varint",
+            Numeric::VarLong => "varlong",
+            Numeric::ZigZag32 => "zigzag32",
+            Numeric::ZigZag64 => "zigzag64",
+        }
+    }
+
+    /// The smallest and largest value of an integer type, or `None` for floats.
+    /// The range of `u128` is cut off at `i128::MAX`.
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        let range: (i128, i128) = match self {
+            Numeric::Byte { signed: true } => (i8::MIN.into(), i8::MAX.into()),
+            Numeric::Byte { signed: false } => (0, u8::MAX.into()),
+            Numeric::Short { signed: true, .. } => (i16::MIN.into(), i16::MAX.into()),
+            Numeric::Short { signed: false, .. } => (0, u16::MAX.into()),
+            Numeric::Int { signed: true, .. } | Numeric::VarInt | Numeric::ZigZag32 => {
+                (i32::MIN.into(), i32::MAX.into())
+            }
+            Numeric::Int { signed: false, .. } => (0, u32::MAX.into()),
+            Numeric::Long { signed: true, .. } | Numeric::VarLong | Numeric::ZigZag64 => {
+                (i64::MIN.into(), i64::MAX.into())
+            }
+            Numeric::Long { signed: false, .. } => (0, u64::MAX.into()),
+            Numeric::Int128 { signed: true, .. } => (i128::MIN, i128::MAX),
+            Numeric::Int128 { signed: false, .. } => (0, i128::MAX),
+            Numeric::Float { .. } | Numeric::Double { .. } => return None,
+        };
+
+        Some(range)
+    }
+}
+
+fn deserialize_native<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    if value == "native" {
+        Ok(())
+    } else {
+        Err(de::Error::invalid_value(
+            Unexpected::Str(&value),
+            &"\"native\"",
+        ))
+    }
+}
+
+fn serialize_native<S>(serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str("native")
+}
+
+fn deserialize_parameterized<'de, D>(
+    deserializer: D,
+) -> Result<(String, LinkedHashMap<String, String>), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer)
+}
+
+fn serialize_parameterized<S>(
+    name: &str,
+    args: &LinkedHashMap<String, String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    (name, args).serialize(serializer)
+}
+
 struct NumericVisitor;
 
 impl<'de> Visitor<'de> for NumericVisitor {
@@ -235,6 +601,22 @@ impl<'de> Visitor<'de> for NumericVisitor {
                 signed: false,
                 byte_order: ByteOrder::LittleEndian,
             }),
+            "i128" => Ok(Numeric::Int128 {
+                signed: true,
+                byte_order: ByteOrder::BigEndian,
+            }),
+            "u128" => Ok(Numeric::Int128 {
+                signed: false,
+                byte_order: ByteOrder::BigEndian,
+            }),
+            "li128" => Ok(Numeric::Int128 {
+                signed: true,
+                byte_order: ByteOrder::LittleEndian,
+            }),
+            "lu128" => Ok(Numeric::Int128 {
+                signed: false,
+                byte_order: ByteOrder::LittleEndian,
+            }),
             "f32" => Ok(Numeric::Float {
                 byte_order: ByteOrder::BigEndian,
             }),
@@ -248,7 +630,10 @@ impl<'de> Visitor<'de> for NumericVisitor {
                 byte_order: ByteOrder::LittleEndian,
             }),
             "varint" => Ok(Numeric::VarInt),
-            _ => Err(de::Error::invalid_value(Unexpected::Str(&value), &self)),
+            "varlong" => Ok(Numeric::VarLong),
+            "zigzag32" => Ok(Numeric::ZigZag32),
+            "zigzag64" => Ok(Numeric::ZigZag64),
+            _ => Err(de::Error::invalid_value(Unexpected::Str(value), &self)),
         }
     }
 }
@@ -262,6 +647,15 @@ impl<'de> Deserialize<'de> for Numeric {
     }
 }
 
+impl Serialize for Numeric {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
 struct ConditionalVisitor;
 
 impl<'de> Visitor<'de> for ConditionalVisitor {
@@ -294,30 +688,10 @@ impl<'de> Visitor<'de> for ConditionalVisitor {
 
                 Ok(Conditional::Option(data_type))
             }
-            unknown_variant => {
-                // Attempt to find switch with wrong type.
-                let mut map: HashMap<String, String> = seq.next_element()?.ok_or_else(|| {
-                    de::Error::unknown_variant(unknown_variant, &["switch", "option"])
-                })?;
-
-                if map.len() == 1 {
-                    if let Some(compare_to) = map.remove("compareTo") {
-                        let switch = Switch {
-                            name: Some(unknown_variant.to_owned()),
-                            compare_to,
-                            fields: LinkedHashMap::new(),
-                            default: None,
-                        };
-
-                        return Ok(Conditional::Switch(switch));
-                    }
-                }
-
-                Err(de::Error::unknown_variant(
-                    unknown_variant,
-                    &["switch", "option"],
-                ))
-            }
+            unknown_variant => Err(de::Error::unknown_variant(
+                unknown_variant,
+                &["switch", "option"],
+            )),
         }
     }
 }
@@ -331,6 +705,18 @@ impl<'de> Deserialize<'de> for Conditional {
     }
 }
 
+impl Serialize for Conditional {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Conditional::Switch(switch) => ("switch", switch).serialize(serializer),
+            Conditional::Option(data_type) => ("option", data_type).serialize(serializer),
+        }
+    }
+}
+
 struct StructureVisitor;
 
 impl<'de> Visitor<'de> for StructureVisitor {
@@ -387,6 +773,19 @@ impl<'de> Deserialize<'de> for Structure {
     }
 }
 
+impl Serialize for Structure {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Structure::Array(array) => ("array", array).serialize(serializer),
+            Structure::Container(fields) => ("container", fields).serialize(serializer),
+            Structure::Count(count) => ("count", count).serialize(serializer),
+        }
+    }
+}
+
 struct UtilVisitor;
 
 impl<'de> Visitor<'de> for UtilVisitor {
@@ -450,13 +849,15 @@ impl<'de> Visitor<'de> for UtilVisitor {
             }
             unknown_variant => {
                 // This is what happens when the nodejs developers write a "cool" spec.
-                let loop_util: Loop = seq.next_element()?.ok_or_else(|| {
+                let mut loop_util: Loop = seq.next_element()?.ok_or_else(|| {
                     de::Error::unknown_variant(
                         unknown_variant,
                         &["buffer", "mapper", "bitfield", "pstring"],
                     )
                 })?;
 
+                loop_util.name = unknown_variant.to_owned();
+
                 Ok(Util::Loop(Box::new(loop_util)))
             }
         }
@@ -472,30 +873,187 @@ impl<'de> Deserialize<'de> for Util {
     }
 }
 
+#[derive(Serialize)]
+struct PrefixedStringOptions<'a> {
+    #[serde(rename = "countType")]
+    count_type: &'a DataType,
+}
+
+#[derive(Serialize)]
+struct TopBitSetTerminatedArrayOptions<'a> {
+    #[serde(rename = "type")]
+    structure: &'a Structure,
+}
+
+impl Serialize for Util {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Util::Buffer(buffer) => ("buffer", buffer).serialize(serializer),
+            Util::Mapper(mapper) => ("mapper", mapper).serialize(serializer),
+            Util::Bitfield(bitfields) => ("bitfield", bitfields).serialize(serializer),
+            Util::PrefixedString { count_type } => {
+                ("pstring", PrefixedStringOptions { count_type }).serialize(serializer)
+            }
+            Util::Loop(loop_util) => (&loop_util.name, loop_util).serialize(serializer),
+            Util::TopBitSetTerminatedArray(structure) => (
+                "topBitSetTerminatedArray",
+                TopBitSetTerminatedArrayOptions { structure },
+            )
+                .serialize(serializer),
+        }
+    }
+}
+
+/// Reads a protocol from the `test` directory.
+#[cfg(test)]
+pub(crate) fn protocol(name: &str) -> Protocol {
+    protocol_at(format!("test/{}", name))
+}
+
+/// Reads a protocol from a path relative to the crate root.
+#[cfg(test)]
+pub(crate) fn protocol_at<P: AsRef<std::path::Path>>(path: P) -> Protocol {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)
+        .unwrap_or_else(|err| panic!("Failed to open {:?}: {}", path, err));
+
+    read_protocol(file).unwrap_or_else(|err| panic!("Failed to read {:?}: {}", path, err))
+}
+
+/// Reads every protocol of the `test` directory, along with its path.
+#[cfg(test)]
+pub(crate) fn all_protocols() -> Vec<(std::path::PathBuf, Protocol)> {
+    std::fs::read_dir("test")
+        .expect("Failed to open test folder")
+        .map(|entry| {
+            let path = entry.expect("Failed to get test folder entry").path();
+            let protocol = protocol_at(&path);
+
+            (path, protocol)
+        })
+        .collect()
+}
+
+/// Builds a map value from its entries in order.
+#[cfg(test)]
+pub(crate) fn map(entries: Vec<(&str, value::Value)>) -> value::Value {
+    value::Value::Map(
+        entries
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
-    use serde_test::{assert_de_tokens, Token};
-    use std::fs;
+    use serde_test::{assert_de_tokens, assert_ser_tokens, Token};
     use std::fs::File;
 
     #[test]
     fn test_decode_protocols_data() {
-        let paths = fs::read_dir("test").expect("Failed to open test folder");
+        // Reading fails naming the protocol that cannot be read.
+        assert!(!all_protocols().is_empty());
+    }
+
+    #[test]
+    fn test_namespace_paths() {
+        let file = File::open("test/minecraft_v1662.json").expect("Failed to open protocol");
+        let protocol = read_protocol(file).expect("Failed to read protocol");
+
+        assert!(matches!(
+            protocol.get("play.toClient"),
+            Some(Namespace::Map(_))
+        ));
+        assert!(matches!(
+            protocol.get("play.toClient.types.packet_chat"),
+            Some(Namespace::Definition(TypeDefinition::DataType(_)))
+        ));
+        assert!(protocol
+            .get("play.toClient.types.packet_chat.message")
+            .is_none());
+        assert!(protocol.get("play.nowhere").is_none());
+        assert!(protocol.get("").is_none());
+
+        let paths: Vec<String> = protocol.leaf_namespaces().map(|(path, _)| path).collect();
+        assert_eq!(paths.len(), 8);
+        assert_eq!(paths[0], "handshaking.toClient");
+        assert_eq!(paths[7], "play.toServer");
+
+        let (_, namespace) = protocol.leaf_namespaces().nth(1).unwrap();
+        let names: Vec<&String> = namespace.types().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "packet_set_protocol",
+                "packet_legacy_server_list_ping",
+                "packet"
+            ]
+        );
 
-        for entry_res in paths.into_iter() {
-            let entry = entry_res.expect("Failed to get test folder entry");
-            let file = File::open(entry.path()).expect("Failed to read file");
+        assert!(protocol.lookup("play.toClient", "packet_chat").is_some());
+        assert_eq!(
+            protocol.lookup("play.toClient", "varint"),
+            Some(&TypeDefinition::Native)
+        );
+        assert!(protocol
+            .lookup("play.toServer", "packet_map_chunk")
+            .is_none());
+        assert!(protocol.lookup("play.nowhere", "varint").is_none());
+    }
 
-            let name = entry
-                .file_name()
-                .into_string()
-                .expect("Failed to get entry name");
+    #[test]
+    fn test_round_trip_protocols_data() {
+        for (path, protocol) in all_protocols() {
+            let json: serde_json::Value =
+                serde_json::from_reader(File::open(&path).expect("Failed to read file"))
+                    .expect("Failed to read JSON");
 
-            read_protocol(&file).expect(&format!("Failed to read \"{}\" protocol", name));
+            let mut written = Vec::new();
+            write_protocol(&mut written, &protocol).expect("Failed to write protocol");
+
+            let written_json: serde_json::Value =
+                serde_json::from_slice(&written).expect("Failed to read written JSON");
+            assert_eq!(written_json, json, "{:?} does not round-trip", path);
+
+            let reread = read_protocol(&written[..]).expect("Failed to read written protocol");
+            assert_eq!(reread, protocol, "{:?} does not round-trip", path);
         }
     }
 
+    #[test]
+    fn test_encode_numeric() {
+        assert_ser_tokens(
+            &Numeric::Short {
+                signed: false,
+                byte_order: ByteOrder::LittleEndian,
+            },
+            &[Token::Str("lu16")],
+        );
+    }
+
+    #[test]
+    fn test_encode_native_definition() {
+        assert_ser_tokens(&TypeDefinition::Native, &[Token::Str("native")]);
+    }
+
+    #[test]
+    fn test_decode_native_definition() {
+        assert_de_tokens(&TypeDefinition::Native, &[Token::String("native")]);
+    }
+
+    #[test]
+    fn test_decode_type_definition() {
+        assert_de_tokens(
+            &TypeDefinition::DataType(DataType::Custom("varint_alias".to_string())),
+            &[Token::String("varint_alias")],
+        );
+    }
+
     #[test]
     fn test_decode_i8() {
         assert_de_tokens(&Numeric::Byte { signed: true }, &[Token::String("i8")]);
@@ -1040,16 +1598,62 @@ mod tests {
     }
 
     #[test]
-    fn test_decode_bad_switch() {
-        let conditional = Conditional::Switch(Switch {
-            name: Some("particleData".to_string()),
-            compare_to: "particleId".to_string(),
-            fields: Default::default(),
-            default: None,
-        });
+    fn test_build_switch_and_fields() {
+        let switch = Switch::new("action")
+            .with_name("data")
+            .with_field("0", DataType::Primitive(Primitive::Boolean))
+            .with_default(DataType::Primitive(Primitive::Void));
+        let fields = vec![
+            Field::new("action", DataType::Numeric(Numeric::VarInt)),
+            Field::anonymous(DataType::Conditional(Box::new(Conditional::Switch(switch)))),
+        ];
+
+        let json = serde_json::to_value(Structure::Container(fields.clone())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!(["container", [
+                {"name": "action", "type": "varint"},
+                {"type": ["switch", {
+                    "name": "data",
+                    "compareTo": "action",
+                    "fields": {"0": "bool"},
+                    "default": "void"
+                }], "anon": true}
+            ]])
+        );
+
+        assert!(!fields[0].is_anonymous());
+        assert!(fields[1].is_anonymous());
+
+        match &fields[1].field_type {
+            DataType::Conditional(conditional) => match conditional.as_ref() {
+                Conditional::Switch(switch) => {
+                    assert_eq!(switch.name(), Some("data"));
+                    assert_eq!(switch.compare_to(), "action");
+                    assert_eq!(switch.fields().len(), 1);
+                    assert_eq!(
+                        switch.default(),
+                        Some(&DataType::Primitive(Primitive::Void))
+                    );
+                }
+                conditional => panic!("unexpected conditional: {:?}", conditional),
+            },
+            data_type => panic!("unexpected type: {:?}", data_type),
+        }
+    }
+
+    #[test]
+    fn test_decode_parameterized() {
+        let mut args = LinkedHashMap::new();
+        args.insert("compareTo".to_string(), "particleId".to_string());
+
+        let data_type = DataType::Parameterized {
+            name: "particleData".to_string(),
+            args,
+        };
 
         assert_de_tokens(
-            &conditional,
+            &data_type,
             &[
                 Token::Seq { len: Some(2) },
                 Token::String("particleData"),
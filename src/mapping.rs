@@ -0,0 +1,200 @@
+//! Mapper keys interpreted as integers of the mapper's underlying type, so that
+//! e.g. `"0x0a"` and `"10"` denote the same key.
+
+use crate::codec::parse_integer_key;
+use crate::resolve::Scope;
+use crate::{DataType, Mapper, Numeric, TypeDefinition};
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappingError {
+    /// The underlying type is not an integer type, e.g. `f32` or a custom type.
+    UnsupportedType(String),
+    /// A key is not a decimal or hex integer.
+    InvalidKey(String),
+    /// A key does not fit into the underlying type.
+    KeyOutOfRange(String),
+    /// Two keys denote the same integer, e.g. `"0x00"` and `"0"`.
+    DuplicateKey { first: String, second: String },
+    /// Two keys map to the same name.
+    DuplicateValue(String),
+}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MappingError::UnsupportedType(name) => {
+                write!(f, "mapper type \"{}\" is not an integer type", name)
+            }
+            MappingError::InvalidKey(key) => write!(f, "mapper key \"{}\" is not an integer", key),
+            MappingError::KeyOutOfRange(key) => {
+                write!(f, "mapper key \"{}\" is out of range of its type", key)
+            }
+            MappingError::DuplicateKey { first, second } => {
+                write!(f, "mapper keys \"{}\" and \"{}\" are equal", first, second)
+            }
+            MappingError::DuplicateValue(name) => {
+                write!(f, "mapper maps several keys to \"{}\"", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MappingError {}
+
+/// The mappings of a mapper with integer keys, looked up in both directions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mappings {
+    names: LinkedHashMap<i128, String>,
+    keys: HashMap<String, i128>,
+}
+
+impl Mappings {
+    /// Parses the keys of a mapper as integers of `numeric`, which may be the
+    /// resolution of a custom `mappings_type`.
+    pub fn new(mapper: &Mapper, numeric: &Numeric) -> Result<Self, MappingError> {
+        let (min, max) = numeric
+            .integer_range()
+            .ok_or_else(|| MappingError::UnsupportedType(numeric.name().to_owned()))?;
+
+        let mut names = LinkedHashMap::new();
+        let mut keys = HashMap::new();
+        let mut sources: HashMap<i128, &str> = HashMap::new();
+
+        for (source, name) in &mapper.mappings {
+            let key = parse_integer_key(source)
+                .ok_or_else(|| MappingError::InvalidKey(source.clone()))?;
+
+            if key < min || key > max {
+                return Err(MappingError::KeyOutOfRange(source.clone()));
+            }
+
+            if let Some(first) = sources.insert(key, source) {
+                return Err(MappingError::DuplicateKey {
+                    first: first.to_owned(),
+                    second: source.clone(),
+                });
+            }
+
+            if keys.insert(name.clone(), key).is_some() {
+                return Err(MappingError::DuplicateValue(name.clone()));
+            }
+
+            names.insert(key, name.clone());
+        }
+
+        Ok(Mappings { names, keys })
+    }
+
+    /// The name a key maps to.
+    pub fn name(&self, key: i128) -> Option<&str> {
+        self.names.get(&key).map(String::as_str)
+    }
+
+    /// The key mapping to a name.
+    pub fn key(&self, name: &str) -> Option<i128> {
+        self.keys.get(name).copied()
+    }
+
+    /// Keys and names in order of declaration.
+    pub fn iter(&self) -> impl Iterator<Item = (i128, &str)> {
+        self.names.iter().map(|(key, name)| (*key, name.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// The underlying type of a mapper if it is numeric, either built-in or an alias
+/// declared in the scope.
+pub(crate) fn numeric_type(mapper: &Mapper, scope: &Scope) -> Option<Numeric> {
+    mapper
+        .numeric_type()
+        .or_else(|| match scope.find(&mapper.mappings_type) {
+            Some((_, TypeDefinition::DataType(DataType::Numeric(numeric)))) => {
+                Some(numeric.clone())
+            }
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapping::*;
+    use crate::*;
+
+    fn mapper(json: &str) -> Mapper {
+        match serde_json::from_str(json).expect("Failed to parse mapper") {
+            Util::Mapper(mapper) => mapper,
+            util => panic!("unexpected util: {:?}", util),
+        }
+    }
+
+    #[test]
+    fn test_parse_mappings() {
+        let mappings = mapper(
+            r#"["mapper", {"type": "i8", "mappings": {"0x00": "zero", "-1": "minus", "10": "ten"}}]"#,
+        )
+        .parse_mappings()
+        .unwrap();
+
+        assert_eq!(mappings.name(0), Some("zero"));
+        assert_eq!(mappings.name(-1), Some("minus"));
+        assert_eq!(mappings.name(0xa), Some("ten"));
+        assert_eq!(mappings.name(1), None);
+        assert_eq!(mappings.key("ten"), Some(10));
+        assert_eq!(mappings.key("one"), None);
+        assert_eq!(
+            mappings.iter().collect::<Vec<_>>(),
+            vec![(0, "zero"), (-1, "minus"), (10, "ten")]
+        );
+    }
+
+    #[test]
+    fn test_parse_mappings_errors() {
+        let error = |json: &str| mapper(json).parse_mappings().unwrap_err();
+
+        assert_eq!(
+            error(r#"["mapper", {"type": "u8", "mappings": {"0": "a", "0x00": "b"}}]"#),
+            MappingError::DuplicateKey {
+                first: "0".to_owned(),
+                second: "0x00".to_owned()
+            }
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "varint", "mappings": {"0": "a", "1": "a"}}]"#),
+            MappingError::DuplicateValue("a".to_owned())
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "u8", "mappings": {"256": "a"}}]"#),
+            MappingError::KeyOutOfRange("256".to_owned())
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "u8", "mappings": {"-1": "a"}}]"#),
+            MappingError::KeyOutOfRange("-1".to_owned())
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "i32", "mappings": {"one": "a"}}]"#),
+            MappingError::InvalidKey("one".to_owned())
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "i32", "mappings": {"+5": "a"}}]"#),
+            MappingError::InvalidKey("+5".to_owned())
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "f32", "mappings": {}}]"#),
+            MappingError::UnsupportedType("f32".to_owned())
+        );
+        assert_eq!(
+            error(r#"["mapper", {"type": "packet_id", "mappings": {}}]"#),
+            MappingError::UnsupportedType("packet_id".to_owned())
+        );
+    }
+}
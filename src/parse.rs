@@ -0,0 +1,385 @@
+//! Reading of protocols with errors pointing to the node that failed to parse.
+//!
+//! `DataType` and `Namespace` are untagged, so serde only reports that a whole
+//! definition matched no variant. On failure the document is walked again as a
+//! `serde_json::Value` to find the innermost node that does not parse, and that
+//! node is parsed as the variant its name selects to get a meaningful error.
+
+use crate::walk::escape;
+use crate::{BitField, Conditional, DataType, Field, Loop, Protocol, Structure, Util};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// An error reading a protocol, located by a JSON pointer and a position in the source.
+#[derive(Debug)]
+pub struct ParseError {
+    /// JSON pointer to the node that failed to parse, e.g.
+    /// `/play/toClient/types/packet_spawn_entity/1/3/type`. Empty for syntax
+    /// errors and errors of the document as a whole.
+    pub pointer: String,
+    pub line: usize,
+    pub column: usize,
+    /// The error of the node, as parsed by the variant its name selects.
+    pub error: serde_json::Error,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.pointer.is_empty() {
+            write!(f, "{}", self.error)
+        } else {
+            write!(
+                f,
+                "{}: {} at line {} column {}",
+                self.pointer, self.error, self.line, self.column
+            )
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(error: serde_json::Error) -> Self {
+        ParseError {
+            pointer: String::new(),
+            line: error.line(),
+            column: error.column(),
+            error,
+        }
+    }
+}
+
+/// Parses a protocol, locating the failing node on error.
+pub(crate) fn parse_protocol(source: &str) -> Result<Protocol, ParseError> {
+    let error = match serde_json::from_str(source) {
+        Ok(protocol) => return Ok(protocol),
+        Err(error) => error,
+    };
+
+    let document: Value = serde_json::from_str(source)?;
+
+    let found = match &document {
+        Value::Object(namespace) => find_in_namespace(namespace, ""),
+        _ => None,
+    };
+
+    match found {
+        Some((pointer, error)) => {
+            let (line, column) = locate(source, &pointer)
+                .map(|offset| position(source, offset))
+                .unwrap_or((0, 0));
+
+            Err(ParseError {
+                pointer,
+                line,
+                column,
+                error,
+            })
+        }
+        None => Err(error.into()),
+    }
+}
+
+/// What a node is parsed as.
+#[derive(Clone, Copy)]
+enum Kind {
+    DataType,
+    Field,
+    BitField,
+}
+
+fn find_in_namespace(
+    namespace: &Map<String, Value>,
+    pointer: &str,
+) -> Option<(String, serde_json::Error)> {
+    for (name, value) in namespace {
+        let pointer = format!("{}/{}", pointer, escape(name));
+
+        let found = match value {
+            Value::Object(types) if name == "types" => types.iter().find_map(|(name, value)| {
+                find(
+                    value,
+                    format!("{}/{}", pointer, escape(name)),
+                    Kind::DataType,
+                )
+            }),
+            Value::Object(namespace) => find_in_namespace(namespace, &pointer),
+            value => find(value, pointer, Kind::DataType),
+        };
+
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    None
+}
+
+/// Finds the innermost node under `value` which fails to parse.
+fn find(value: &Value, pointer: String, kind: Kind) -> Option<(String, serde_json::Error)> {
+    let error = match kind {
+        Kind::DataType => DataType::deserialize(value).err()?,
+        Kind::Field => Field::deserialize(value).err()?,
+        Kind::BitField => BitField::deserialize(value).err()?,
+    };
+
+    for (child_pointer, child, child_kind) in children(value, kind) {
+        let found = find(child, format!("{}{}", pointer, child_pointer), child_kind);
+
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    let error = match kind {
+        Kind::DataType => variant_error(value).unwrap_or(error),
+        _ => error,
+    };
+
+    Some((pointer, error))
+}
+
+/// Nodes nested in a node, with their JSON pointers relative to it.
+fn children(value: &Value, kind: Kind) -> Vec<(String, &Value, Kind)> {
+    let mut children = Vec::new();
+
+    match (kind, value) {
+        (Kind::DataType, Value::Array(items)) => {
+            match (items.first().and_then(Value::as_str), items.get(1)) {
+                (Some("option"), Some(data_type)) => {
+                    children.push(("/1".to_owned(), data_type, Kind::DataType));
+                }
+                (Some("container"), Some(Value::Array(fields))) => {
+                    for (i, field) in fields.iter().enumerate() {
+                        children.push((format!("/1/{}", i), field, Kind::Field));
+                    }
+                }
+                (Some("bitfield"), Some(Value::Array(fields))) => {
+                    for (i, field) in fields.iter().enumerate() {
+                        children.push((format!("/1/{}", i), field, Kind::BitField));
+                    }
+                }
+                (_, Some(Value::Object(options))) => {
+                    for key in &["type", "countType", "default"] {
+                        if let Some(data_type) = options.get(*key) {
+                            children.push((format!("/1/{}", key), data_type, Kind::DataType));
+                        }
+                    }
+
+                    if let Some(Value::Object(fields)) = options.get("fields") {
+                        for (key, data_type) in fields {
+                            let pointer = format!("/1/fields/{}", escape(key));
+                            children.push((pointer, data_type, Kind::DataType));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        (Kind::Field, Value::Object(field)) => {
+            if let Some(data_type) = field.get("type") {
+                children.push(("/type".to_owned(), data_type, Kind::DataType));
+            }
+        }
+        _ => {}
+    }
+
+    children
+}
+
+/// The error of a type node parsed as the variant its name selects, e.g. as a
+/// switch for `["switch", {...}]`, rather than the untagged `DataType` error.
+fn variant_error(value: &Value) -> Option<serde_json::Error> {
+    let items = value.as_array()?;
+    let name = items.first()?.as_str()?;
+
+    match name {
+        "switch" | "option" => Conditional::deserialize(value).err(),
+        "container" | "array" | "count" => Structure::deserialize(value).err(),
+        "buffer" | "mapper" | "bitfield" | "pstring" | "topBitSetTerminatedArray" => {
+            Util::deserialize(value).err()
+        }
+        _ => match items.get(1) {
+            Some(Value::Object(options)) if options.contains_key("endVal") => {
+                Loop::deserialize(&items[1]).err()
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Byte offset of the node a JSON pointer refers to.
+fn locate(source: &str, pointer: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut offset = skip_whitespace(bytes, 0);
+
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+
+        match bytes.get(offset)? {
+            b'{' => {
+                offset += 1;
+
+                loop {
+                    offset = skip_whitespace(bytes, offset);
+                    let end = skip_value(bytes, offset)?;
+                    let key: String = serde_json::from_str(&source[offset..end]).ok()?;
+
+                    offset = skip_whitespace(bytes, end);
+                    if bytes.get(offset) != Some(&b':') {
+                        return None;
+                    }
+                    offset = skip_whitespace(bytes, offset + 1);
+
+                    if key == segment {
+                        break;
+                    }
+
+                    offset = skip_separator(bytes, skip_value(bytes, offset)?)?;
+                }
+            }
+            b'[' => {
+                let index: usize = segment.parse().ok()?;
+                offset = skip_whitespace(bytes, offset + 1);
+
+                for _ in 0..index {
+                    offset = skip_separator(bytes, skip_value(bytes, offset)?)?;
+                    offset = skip_whitespace(bytes, offset);
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    Some(offset)
+}
+
+fn skip_whitespace(bytes: &[u8], mut offset: usize) -> usize {
+    while bytes.get(offset).is_some_and(u8::is_ascii_whitespace) {
+        offset += 1;
+    }
+
+    offset
+}
+
+/// Skips the comma after a value, failing at the end of an object or array.
+fn skip_separator(bytes: &[u8], offset: usize) -> Option<usize> {
+    let offset = skip_whitespace(bytes, offset);
+
+    match bytes.get(offset)? {
+        b',' => Some(offset + 1),
+        _ => None,
+    }
+}
+
+/// Offset just past the value starting at `offset`.
+fn skip_value(bytes: &[u8], mut offset: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    loop {
+        let byte = *bytes.get(offset)?;
+        offset += 1;
+
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' if in_string => escaped = true,
+            b'"' => {
+                in_string = !in_string;
+
+                if !in_string && depth == 0 {
+                    return Some(offset);
+                }
+            }
+            _ if in_string => {}
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth = depth.checked_sub(1)?;
+
+                if depth == 0 {
+                    return Some(offset);
+                }
+            }
+            b',' | b':' if depth == 0 => return Some(offset - 1),
+            byte if depth == 0 && byte.is_ascii_whitespace() => return Some(offset - 1),
+            _ => {}
+        }
+    }
+}
+
+/// One-based line and column of a byte offset.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_parse_error_location() {
+        let json = r#"{
+  "types": {
+    "varint": "native"
+  },
+  "play": {
+    "toClient": {
+      "types": {
+        "packet_spawn_entity": [
+          "container",
+          [
+            { "name": "entityId", "type": "varint" },
+            {
+              "name": "objectData",
+              "type": ["switch", { "compareTo": "type", "fields": [] }]
+            }
+          ]
+        ]
+      }
+    }
+  }
+}"#;
+
+        let error = read_protocol(json.as_bytes()).unwrap_err();
+
+        assert_eq!(
+            error.pointer,
+            "/play/toClient/types/packet_spawn_entity/1/1/type"
+        );
+        assert_eq!((error.line, error.column), (14, 23));
+        assert_eq!(
+            error.error.to_string(),
+            "invalid type: sequence, expected a map"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_field() {
+        let json = r#"{"types": {"packet": ["container", [{"name": "id"}]]}}"#;
+        let error = read_protocol(json.as_bytes()).unwrap_err();
+
+        assert_eq!(error.pointer, "/types/packet/1/0");
+        assert_eq!((error.line, error.column), (1, 37));
+        assert_eq!(error.error.to_string(), "missing field `type`");
+    }
+
+    #[test]
+    fn test_parse_error_syntax() {
+        let error = read_protocol(&b"{\"types\": {]}"[..]).unwrap_err();
+
+        assert_eq!(error.pointer, "");
+        assert_eq!((error.line, error.column), (1, 12));
+        assert!(error.error.is_syntax());
+    }
+}
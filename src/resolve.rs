@@ -0,0 +1,662 @@
+//! Resolution of `DataType::Custom` names to the type definitions they refer to.
+//!
+//! Types are looked up in the `types` of the namespace they are used in, then
+//! in the `types` of each enclosing namespace, then in the root `types`.
+//!
+//! A definition using `$name` placeholders is a template, referred to with
+//! `DataType::Parameterized` and instantiated by substituting its arguments.
+
+use crate::walk::{children, children_mut, escape, type_references};
+use crate::{
+    ArrayCount, Conditional, DataType, Namespace, Protocol, Structure, TypeDefinition, Util,
+};
+use linked_hash_map::LinkedHashMap;
+use std::fmt;
+
+/// Types visible from a namespace.
+pub struct Scope<'a> {
+    root: &'a LinkedHashMap<String, TypeDefinition>,
+    /// Paths and `types` of the enclosing namespaces, outermost first.
+    namespaces: Vec<(String, &'a LinkedHashMap<String, Namespace>)>,
+}
+
+impl<'a> Scope<'a> {
+    /// Creates the scope of a namespace at a dot-separated path like `play.toClient`,
+    /// or of the root types for an empty path. Returns `None` if there is no such namespace.
+    pub fn new(protocol: &'a Protocol, path: &str) -> Option<Self> {
+        let mut namespaces = Vec::new();
+        let mut current = &protocol.namespaces;
+        let mut current_path = String::new();
+
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            current = match current.get(name) {
+                Some(Namespace::Map(map)) => map,
+                _ => return None,
+            };
+
+            if !current_path.is_empty() {
+                current_path.push('.');
+            }
+            current_path.push_str(name);
+
+            if let Some(Namespace::Map(types)) = current.get("types") {
+                namespaces.push((current_path.clone(), types));
+            }
+        }
+
+        Some(Scope {
+            root: &protocol.types,
+            namespaces,
+        })
+    }
+
+    /// Looks up the definition of a type.
+    pub fn lookup(&self, name: &str) -> Option<&'a TypeDefinition> {
+        self.find(name).map(|(_, definition)| definition)
+    }
+
+    /// Looks up the definition of a type along with the namespace it is defined in.
+    pub fn find(&self, name: &str) -> Option<(TypeId, &'a TypeDefinition)> {
+        for (path, types) in self.namespaces.iter().rev() {
+            if let Some(Namespace::Definition(definition)) = types.get(name) {
+                return Some((TypeId::new(path, name), definition));
+            }
+        }
+
+        self.root
+            .get(name)
+            .map(|definition| (TypeId::new("", name), definition))
+    }
+
+    /// Looks up a template and substitutes its placeholders with the arguments.
+    pub fn instantiate(
+        &self,
+        name: &str,
+        args: &LinkedHashMap<String, String>,
+    ) -> Option<DataType> {
+        self.lookup(name)
+            .and_then(TypeDefinition::data_type)
+            .map(|template| instantiate(template, args))
+    }
+}
+
+/// Identifies a type definition by the namespace it is defined in and its name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeId {
+    /// Dot-separated namespace path, empty for the root types.
+    pub namespace: String,
+    pub name: String,
+}
+
+impl TypeId {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        TypeId {
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
+    /// JSON pointer to the definition, e.g. `/play/toClient/types/packet`.
+    pub fn pointer(&self) -> String {
+        let mut pointer = String::new();
+
+        for name in self.namespace.split('.').filter(|name| !name.is_empty()) {
+            pointer.push('/');
+            pointer.push_str(&escape(name));
+        }
+
+        format!("{}/types/{}", pointer, escape(&self.name))
+    }
+}
+
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.namespace.is_empty() {
+            f.write_str(&self.name)
+        } else {
+            write!(f, "{}.{}", self.namespace, self.name)
+        }
+    }
+}
+
+/// A resolved type definition.
+#[derive(Debug)]
+pub struct Definition<'a> {
+    pub type_definition: &'a TypeDefinition,
+    /// Types referenced from this definition, in order of appearance.
+    pub references: Vec<Reference>,
+}
+
+impl<'a> Definition<'a> {
+    pub fn is_native(&self) -> bool {
+        self.type_definition.is_native()
+    }
+}
+
+/// A resolved reference to a type definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// JSON pointer to the node containing the reference.
+    pub path: String,
+    pub target: TypeId,
+}
+
+/// A reference to a type that is not visible from where it is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingReference {
+    /// JSON pointer to the node containing the reference.
+    pub path: String,
+    /// Namespace the reference is looked up from.
+    pub namespace: String,
+    pub name: String,
+}
+
+impl fmt::Display for DanglingReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown type \"{}\" at {}", self.name, self.path)
+    }
+}
+
+impl std::error::Error for DanglingReference {}
+
+/// The graph of type definitions of a protocol and the references between them.
+#[derive(Debug)]
+pub struct Resolution<'a> {
+    definitions: LinkedHashMap<TypeId, Definition<'a>>,
+}
+
+impl<'a> Resolution<'a> {
+    pub fn get(&self, id: &TypeId) -> Option<&Definition<'a>> {
+        self.definitions.get(id)
+    }
+
+    /// All definitions, root types first, then namespaces in order of declaration.
+    pub fn definitions(&self) -> impl Iterator<Item = (&TypeId, &Definition<'a>)> {
+        self.definitions.iter()
+    }
+
+    /// Definitions referring to the given type, along with each such reference.
+    pub fn referrers(&self, id: &TypeId) -> Vec<(&TypeId, &Reference)> {
+        self.definitions
+            .iter()
+            .flat_map(|(referrer, definition)| {
+                definition
+                    .references
+                    .iter()
+                    .filter(|reference| &reference.target == id)
+                    .map(move |reference| (referrer, reference))
+            })
+            .collect()
+    }
+}
+
+/// Resolves every type reference of a protocol, or returns all references
+/// that cannot be resolved.
+pub fn resolve(protocol: &Protocol) -> Result<Resolution<'_>, Vec<DanglingReference>> {
+    let mut definitions = LinkedHashMap::new();
+    let mut dangling = Vec::new();
+
+    for (scope, namespace, types) in namespaces(protocol) {
+        for (name, type_definition) in types {
+            let id = TypeId::new(&namespace, name);
+            let mut references = Vec::new();
+
+            for_each_reference(type_definition, id.pointer(), &mut |path, name| {
+                match scope.find(name) {
+                    Some((target, _)) => references.push(Reference { path, target }),
+                    // Type parameters are substituted when a template is instantiated.
+                    None if name.starts_with('$') => {}
+                    None => dangling.push(DanglingReference {
+                        path,
+                        namespace: namespace.clone(),
+                        name: name.to_owned(),
+                    }),
+                }
+            });
+
+            definitions.insert(
+                id,
+                Definition {
+                    type_definition,
+                    references,
+                },
+            );
+        }
+    }
+
+    if dangling.is_empty() {
+        Ok(Resolution { definitions })
+    } else {
+        Err(dangling)
+    }
+}
+
+impl Protocol {
+    /// Native types declared by the protocol, in order of declaration.
+    pub fn natives(&self) -> Vec<TypeId> {
+        namespaces(self)
+            .into_iter()
+            .flat_map(|(_, namespace, types)| {
+                types
+                    .into_iter()
+                    .filter(|(_, definition)| definition.is_native())
+                    .map(move |(name, _)| TypeId::new(&namespace, name))
+            })
+            .collect()
+    }
+
+    /// Native types the protocol uses, in order of first use.
+    ///
+    /// Natives standing for types this crate parses, like `varint` or `container`,
+    /// are not included, as they are never referred to by name.
+    pub fn required_natives(&self) -> Vec<TypeId> {
+        let mut natives = Vec::new();
+
+        for (scope, _, types) in namespaces(self) {
+            for (_, type_definition) in types {
+                for_each_reference(type_definition, String::new(), &mut |_, name| {
+                    if let Some((id, TypeDefinition::Native)) = scope.find(name) {
+                        if !natives.contains(&id) {
+                            natives.push(id);
+                        }
+                    }
+                });
+            }
+        }
+
+        natives
+    }
+}
+
+/// Scope, path and definitions of a namespace.
+pub(crate) type NamespaceTypes<'a> = (Scope<'a>, String, Vec<(&'a String, &'a TypeDefinition)>);
+
+/// Scopes and definitions of the root types and of every namespace having its own `types`.
+pub(crate) fn namespaces(protocol: &Protocol) -> Vec<NamespaceTypes<'_>> {
+    let root = Scope::new(protocol, "").expect("root scope exists");
+    let mut namespaces = vec![(root, String::new(), protocol.types.iter().collect())];
+
+    for (path, namespace) in protocol.leaf_namespaces() {
+        let scope = Scope::new(protocol, &path).expect("leaf namespace exists");
+        namespaces.push((scope, path, namespace.types().collect()));
+    }
+
+    namespaces
+}
+
+/// The definition of a type with every reference to a non-native type replaced
+/// by the definition it refers to, and templates instantiated, recursively.
+///
+/// References to types being expanded, which would expand endlessly, and
+/// references that cannot be resolved are left as-is.
+pub fn expand(protocol: &Protocol, id: &TypeId) -> Option<TypeDefinition> {
+    let scope = Scope::new(protocol, &id.namespace)?;
+    let definition = match scope.find(&id.name) {
+        Some((found, definition)) if found == *id => definition,
+        _ => return None,
+    };
+
+    let mut data_type = match definition {
+        TypeDefinition::Native => return Some(TypeDefinition::Native),
+        TypeDefinition::DataType(data_type) => data_type.clone(),
+    };
+
+    expand_type(
+        protocol,
+        &id.namespace,
+        &mut data_type,
+        &mut vec![id.clone()],
+    );
+
+    Some(TypeDefinition::DataType(data_type))
+}
+
+fn expand_type(
+    protocol: &Protocol,
+    namespace: &str,
+    data_type: &mut DataType,
+    expanding: &mut Vec<TypeId>,
+) {
+    let scope = match Scope::new(protocol, namespace) {
+        Some(scope) => scope,
+        None => return,
+    };
+
+    let found = match data_type {
+        DataType::Custom(name) => match scope.find(name) {
+            Some((id, TypeDefinition::DataType(definition))) => Some((id, definition.clone())),
+            _ => None,
+        },
+        DataType::Parameterized { name, args } => match scope.find(name) {
+            Some((id, TypeDefinition::DataType(template))) => {
+                Some((id, instantiate(template, args)))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match found {
+        Some((id, mut definition)) if !expanding.contains(&id) => {
+            let namespace = id.namespace.clone();
+            expanding.push(id);
+            expand_type(protocol, &namespace, &mut definition, expanding);
+            expanding.pop();
+
+            *data_type = definition;
+        }
+        Some(_) => {}
+        None => {
+            for child in children_mut(data_type) {
+                expand_type(protocol, namespace, child, expanding);
+            }
+        }
+    }
+}
+
+/// Calls `f` with each type reference of a definition and its JSON pointer.
+fn for_each_reference(
+    type_definition: &TypeDefinition,
+    path: String,
+    f: &mut dyn FnMut(String, &str),
+) {
+    if let TypeDefinition::DataType(data_type) = type_definition {
+        visit(data_type, path, f);
+    }
+}
+
+/// Names of the `$` placeholders of a template, without the `$`, in order of appearance.
+pub fn parameters(template: &DataType) -> Vec<String> {
+    let mut parameters = Vec::new();
+
+    visit_placeholders(&mut template.clone(), &mut |value| {
+        if let Some(name) = value.strip_prefix('$') {
+            if !parameters.iter().any(|parameter| parameter == name) {
+                parameters.push(name.to_owned());
+            }
+        }
+    });
+
+    parameters
+}
+
+/// Substitutes `$name` placeholders of a template with the arguments.
+///
+/// Placeholders can stand for field references (`compareTo`, `count`, `countFor`)
+/// and type names. Placeholders without an argument are left as-is.
+pub fn instantiate(template: &DataType, args: &LinkedHashMap<String, String>) -> DataType {
+    let mut data_type = template.clone();
+
+    visit_placeholders(&mut data_type, &mut |value| {
+        let argument = value
+            .strip_prefix('$')
+            .and_then(|name| args.get(name))
+            .cloned();
+
+        if let Some(argument) = argument {
+            *value = argument;
+        }
+    });
+
+    data_type
+}
+
+/// Calls `f` with each string of a type tree that can hold a placeholder.
+fn visit_placeholders(data_type: &mut DataType, f: &mut dyn FnMut(&mut String)) {
+    fn visit_count(count: Option<&mut ArrayCount>, f: &mut dyn FnMut(&mut String)) {
+        if let Some(ArrayCount::FieldReference(path)) = count {
+            f(path);
+        }
+    }
+
+    fn visit_structure(structure: &mut Structure, f: &mut dyn FnMut(&mut String)) {
+        match structure {
+            Structure::Array(array) => {
+                if let Some(count_type) = &mut array.count_type {
+                    visit_placeholders(count_type, f);
+                }
+                visit_count(array.count.as_mut(), f);
+                visit_placeholders(&mut array.elements_type, f);
+            }
+            Structure::Container(fields) => {
+                for field in fields {
+                    visit_placeholders(&mut field.field_type, f);
+                }
+            }
+            Structure::Count(count) => {
+                visit_placeholders(&mut count.count_type, f);
+                f(&mut count.count_for);
+            }
+        }
+    }
+
+    match data_type {
+        DataType::Conditional(conditional) => match conditional.as_mut() {
+            Conditional::Switch(switch) => {
+                f(&mut switch.compare_to);
+
+                for (_, data_type) in switch.fields.iter_mut() {
+                    visit_placeholders(data_type, f);
+                }
+
+                if let Some(default) = &mut switch.default {
+                    visit_placeholders(default, f);
+                }
+            }
+            Conditional::Option(data_type) => visit_placeholders(data_type, f),
+        },
+        DataType::Structure(structure) => visit_structure(structure, f),
+        DataType::Util(util) => match util.as_mut() {
+            Util::Buffer(buffer) => {
+                if let Some(count_type) = &mut buffer.count_type {
+                    visit_placeholders(count_type, f);
+                }
+                visit_count(buffer.count.as_mut(), f);
+            }
+            Util::Mapper(mapper) => f(&mut mapper.mappings_type),
+            Util::PrefixedString { count_type } => visit_placeholders(count_type, f),
+            Util::Loop(loop_util) => visit_placeholders(&mut loop_util.data_type, f),
+            Util::TopBitSetTerminatedArray(structure) => visit_structure(structure, f),
+            Util::Bitfield(_) => {}
+        },
+        DataType::Parameterized { args, .. } => {
+            for (_, value) in args.iter_mut() {
+                f(value);
+            }
+        }
+        DataType::Custom(name) => f(name),
+        DataType::Numeric(_) | DataType::Primitive(_) => {}
+    }
+}
+
+/// Calls `f` with each type reference in a type tree and its JSON pointer.
+fn visit(data_type: &DataType, path: String, f: &mut dyn FnMut(String, &str)) {
+    for (pointer, name) in type_references(data_type) {
+        f(format!("{}{}", path, pointer), name);
+    }
+
+    for (pointer, child) in children(data_type) {
+        visit(child, format!("{}{}", path, pointer), f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resolve::*;
+    use crate::*;
+    use linked_hash_map::LinkedHashMap;
+
+    fn from_json(json: &str) -> Protocol {
+        serde_json::from_str(json).expect("Failed to read protocol")
+    }
+
+    #[test]
+    fn test_resolve_protocols_data() {
+        for (path, protocol) in all_protocols() {
+            let dangling = resolve(&protocol).err();
+
+            // This spec uses types like "slot" and "position_iii" without defining them.
+            if path.ends_with("minecraft_v170.json") {
+                let dangling = dangling.expect("Dangling references are not reported");
+                assert!(dangling.iter().any(|reference| reference.name == "slot"));
+            } else {
+                assert!(dangling.is_none(), "{:?}: {:?}", path, dangling);
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_namespace_shadowing() {
+        let protocol = from_json(
+            r#"{
+                "types": { "varint": "native", "id": "u8" },
+                "play": {
+                    "types": { "id": "varint" },
+                    "toClient": {
+                        "types": { "packet": ["container", [{ "name": "id", "type": "id" }]] }
+                    }
+                }
+            }"#,
+        );
+
+        let resolution = resolve(&protocol).unwrap();
+        let packet = resolution
+            .get(&TypeId::new("play.toClient", "packet"))
+            .unwrap();
+
+        assert_eq!(
+            packet.references,
+            vec![Reference {
+                path: "/play/toClient/types/packet/1/0/type".to_owned(),
+                target: TypeId::new("play", "id"),
+            }]
+        );
+        assert!(resolution
+            .get(&TypeId::new("", "varint"))
+            .unwrap()
+            .is_native());
+
+        let referrers: Vec<_> = resolution
+            .referrers(&TypeId::new("play", "id"))
+            .into_iter()
+            .map(|(id, _)| id.to_string())
+            .collect();
+        assert_eq!(referrers, vec!["play.toClient.packet"]);
+    }
+
+    #[test]
+    fn test_resolve_dangling_references() {
+        let protocol = from_json(
+            r#"{
+                "types": { "string": ["pstring", { "countType": "varint" }] },
+                "login": {
+                    "types": {
+                        "packet": ["container", [
+                            { "name": "name", "type": "string" },
+                            { "name": "slot", "type": ["option", "slot"] }
+                        ]]
+                    }
+                }
+            }"#,
+        );
+
+        assert_eq!(
+            resolve(&protocol).unwrap_err(),
+            vec![DanglingReference {
+                path: "/login/types/packet/1/1/type/1".to_owned(),
+                namespace: "login".to_owned(),
+                name: "slot".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_instantiate_template() {
+        let protocol = protocol("minecraft_v1662.json");
+        let template = protocol.types["entityMetadataItem"].data_type().unwrap();
+
+        assert_eq!(parameters(template), vec!["compareTo"]);
+
+        let mut args = LinkedHashMap::new();
+        args.insert("compareTo".to_owned(), "type".to_owned());
+
+        match instantiate(template, &args) {
+            DataType::Conditional(conditional) => match *conditional {
+                Conditional::Switch(switch) => assert_eq!(switch.compare_to, "type"),
+                conditional => panic!("Unexpected {:?}", conditional),
+            },
+            data_type => panic!("Unexpected {:?}", data_type),
+        }
+    }
+
+    #[test]
+    fn test_natives() {
+        let protocol = protocol("minecraft_v1662.json");
+
+        let natives = protocol.natives();
+        assert_eq!(natives.len(), 24);
+        assert_eq!(natives[0], TypeId::new("", "varint"));
+
+        let required: Vec<String> = protocol
+            .required_natives()
+            .into_iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(required, vec!["optionalNbt", "UUID", "nbt", "restBuffer"]);
+    }
+
+    #[test]
+    fn test_scope_find() {
+        let protocol = protocol("minecraft_v1662.json");
+        let scope = Scope::new(&protocol, "play.toClient").unwrap();
+
+        let (id, _) = scope.find("packet").unwrap();
+        assert_eq!(id, TypeId::new("play.toClient", "packet"));
+
+        let (id, _) = scope.find("slot").unwrap();
+        assert_eq!(id, TypeId::new("", "slot"));
+
+        assert!(scope.find("packet_unknown").is_none());
+        assert!(Scope::new(&protocol, "play.nowhere").is_none());
+    }
+
+    #[test]
+    fn test_expand() {
+        let protocol = from_json(
+            r#"{
+                "types": {
+                    "varint": "native",
+                    "nbt": "native",
+                    "node": ["container", [
+                        {"name": "tag", "type": "nbt"},
+                        {"name": "children", "type": ["array", {"countType": "varint", "type": "node"}]}
+                    ]],
+                    "typed": ["switch", {"compareTo": "$kind", "fields": {"0": "node"}}],
+                    "tree": ["container", [
+                        {"name": "kind", "type": "varint"},
+                        {"name": "root", "type": ["typed", {"kind": "kind"}]}
+                    ]]
+                }
+            }"#,
+        );
+
+        let expanded = expand(&protocol, &TypeId::new("", "tree")).unwrap();
+        let expected: TypeDefinition = serde_json::from_str(
+            r#"["container", [
+                {"name": "kind", "type": "varint"},
+                {"name": "root", "type": ["switch", {"compareTo": "kind", "fields": {"0": ["container", [
+                    {"name": "tag", "type": "nbt"},
+                    {"name": "children", "type": ["array", {"countType": "varint", "type": "node"}]}
+                ]]}}]}
+            ]]"#,
+        )
+        .unwrap();
+
+        assert_eq!(expanded, expected);
+        assert_eq!(
+            expand(&protocol, &TypeId::new("", "nbt")),
+            Some(TypeDefinition::Native)
+        );
+        assert_eq!(expand(&protocol, &TypeId::new("play", "tree")), None);
+    }
+}
@@ -0,0 +1,380 @@
+//! Static analysis of how many bytes values of a type take on the wire.
+
+use crate::codec::{mapper_type, Builtin, NativeRegistry};
+use crate::resolve::{namespaces, Scope, TypeId};
+use crate::{
+    ArrayCount, Conditional, DataType, Numeric, Primitive, Protocol, Structure, TypeDefinition,
+    Util,
+};
+use std::fmt;
+
+/// The number of bytes values of a type take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeInfo {
+    /// Every value takes this many bytes.
+    Fixed(usize),
+    /// Values take between `min` and `max` bytes, inclusive.
+    Bounded(usize, usize),
+    /// Values have no upper bound, e.g. strings, or one that cannot be told statically.
+    Variable,
+}
+
+impl SizeInfo {
+    /// The size of every value, if it is the same for all.
+    pub fn fixed(&self) -> Option<usize> {
+        match *self {
+            SizeInfo::Fixed(size) => Some(size),
+            _ => None,
+        }
+    }
+
+    /// The largest size of a value, if bounded.
+    pub fn max(&self) -> Option<usize> {
+        match *self {
+            SizeInfo::Fixed(size) | SizeInfo::Bounded(_, size) => Some(size),
+            SizeInfo::Variable => None,
+        }
+    }
+
+    /// The size of a value of this type followed by one of another type.
+    fn then(self, next: SizeInfo) -> SizeInfo {
+        match (self, next) {
+            (SizeInfo::Fixed(first), SizeInfo::Fixed(second)) => {
+                SizeInfo::Fixed(first.saturating_add(second))
+            }
+            (SizeInfo::Variable, _) | (_, SizeInfo::Variable) => SizeInfo::Variable,
+            (first, second) => bounded(
+                first.min().saturating_add(second.min()),
+                first.max_size().saturating_add(second.max_size()),
+            ),
+        }
+    }
+
+    /// The size of a value of either this type or another.
+    fn or(self, other: SizeInfo) -> SizeInfo {
+        match (self, other) {
+            (SizeInfo::Variable, _) | (_, SizeInfo::Variable) => SizeInfo::Variable,
+            (first, second) => bounded(
+                first.min().min(second.min()),
+                first.max_size().max(second.max_size()),
+            ),
+        }
+    }
+
+    fn times(self, count: usize) -> SizeInfo {
+        match self {
+            SizeInfo::Fixed(size) => SizeInfo::Fixed(size.saturating_mul(count)),
+            SizeInfo::Bounded(min, max) => {
+                bounded(min.saturating_mul(count), max.saturating_mul(count))
+            }
+            SizeInfo::Variable if count == 0 => SizeInfo::Fixed(0),
+            SizeInfo::Variable => SizeInfo::Variable,
+        }
+    }
+
+    /// The smallest size of a value, zero if unknown.
+    pub fn min(&self) -> usize {
+        match *self {
+            SizeInfo::Fixed(size) | SizeInfo::Bounded(size, _) => size,
+            SizeInfo::Variable => 0,
+        }
+    }
+
+    fn max_size(&self) -> usize {
+        self.max().unwrap_or(usize::MAX)
+    }
+}
+
+fn bounded(min: usize, max: usize) -> SizeInfo {
+    if min == max {
+        SizeInfo::Fixed(min)
+    } else {
+        SizeInfo::Bounded(min, max)
+    }
+}
+
+impl fmt::Display for SizeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SizeInfo::Fixed(1) => f.write_str("1 byte"),
+            SizeInfo::Fixed(size) => write!(f, "{} bytes", size),
+            SizeInfo::Bounded(min, max) => write!(f, "{} to {} bytes", min, max),
+            SizeInfo::Variable => f.write_str("variable size"),
+        }
+    }
+}
+
+/// The size of values of a type, resolving type names in a scope. Types referring
+/// to themselves, and native types other than `UUID`, are of variable size.
+pub fn size_of(data_type: &DataType, scope: &Scope) -> SizeInfo {
+    size_with(data_type, scope, None)
+}
+
+/// The size of values of a type, taking the sizes of native types implemented
+/// by a registry from it.
+pub fn size_of_with_natives(
+    data_type: &DataType,
+    scope: &Scope,
+    natives: &dyn NativeRegistry,
+) -> SizeInfo {
+    size_with(data_type, scope, Some(natives))
+}
+
+pub(crate) fn size_with(
+    data_type: &DataType,
+    scope: &Scope,
+    natives: Option<&dyn NativeRegistry>,
+) -> SizeInfo {
+    Sizes {
+        scope,
+        natives,
+        seen: Vec::new(),
+    }
+    .size_of(data_type)
+}
+
+/// A type whose size differs between two versions of a protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeChange {
+    pub id: TypeId,
+    pub old: SizeInfo,
+    pub new: SizeInfo,
+}
+
+impl fmt::Display for SizeChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.id, self.old, self.new)
+    }
+}
+
+/// Types defined in both protocols whose sizes differ, in order of the new protocol.
+pub fn size_changes(old: &Protocol, new: &Protocol) -> Vec<SizeChange> {
+    let mut changes = Vec::new();
+
+    for (scope, namespace, types) in namespaces(new) {
+        let old_scope = match Scope::new(old, &namespace) {
+            Some(scope) => scope,
+            None => continue,
+        };
+
+        for (name, definition) in types {
+            let id = TypeId::new(&namespace, name);
+            let old_definition = match old_scope.find(name) {
+                Some((found, definition)) if found == id => definition,
+                _ => continue,
+            };
+
+            let (old_size, new_size) = match (old_definition.data_type(), definition.data_type()) {
+                (Some(old_type), Some(new_type)) => {
+                    (size_of(old_type, &old_scope), size_of(new_type, &scope))
+                }
+                _ => continue,
+            };
+
+            if old_size != new_size {
+                changes.push(SizeChange {
+                    id,
+                    old: old_size,
+                    new: new_size,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+struct Sizes<'s, 'a> {
+    scope: &'s Scope<'a>,
+    natives: Option<&'s dyn NativeRegistry>,
+    /// Names of the types being sized, to stop at recursive ones.
+    seen: Vec<String>,
+}
+
+impl Sizes<'_, '_> {
+    fn size_of(&mut self, data_type: &DataType) -> SizeInfo {
+        match data_type {
+            DataType::Conditional(conditional) => match conditional.as_ref() {
+                Conditional::Switch(switch) => {
+                    // A value matching no case and no default is void.
+                    let otherwise = match &switch.default {
+                        Some(default) => self.size_of(default),
+                        None => SizeInfo::Fixed(0),
+                    };
+
+                    switch
+                        .fields
+                        .values()
+                        .fold(otherwise, |size, case| size.or(self.size_of(case)))
+                }
+                Conditional::Option(data_type) => {
+                    let present = SizeInfo::Fixed(1).then(self.size_of(data_type));
+                    SizeInfo::Fixed(1).or(present)
+                }
+            },
+            DataType::Numeric(numeric) => numeric_size(numeric),
+            DataType::Primitive(primitive) => match primitive {
+                Primitive::Boolean => SizeInfo::Fixed(1),
+                Primitive::String => SizeInfo::Variable,
+                Primitive::Void => SizeInfo::Fixed(0),
+            },
+            DataType::Structure(structure) => match structure.as_ref() {
+                Structure::Array(array) => match (&array.count_type, &array.count) {
+                    (None, Some(ArrayCount::FixedLength(length))) => {
+                        self.size_of(&array.elements_type).times(*length as usize)
+                    }
+                    _ => SizeInfo::Variable,
+                },
+                Structure::Container(fields) => {
+                    fields.iter().fold(SizeInfo::Fixed(0), |size, field| {
+                        size.then(self.size_of(&field.field_type))
+                    })
+                }
+                Structure::Count(count) => self.size_of(&count.count_type),
+            },
+            DataType::Util(util) => match util.as_ref() {
+                Util::Buffer(buffer) => match (&buffer.count_type, &buffer.count, buffer.rest) {
+                    (None, Some(ArrayCount::FixedLength(length)), None | Some(false)) => {
+                        SizeInfo::Fixed(*length as usize)
+                    }
+                    _ => SizeInfo::Variable,
+                },
+                Util::Mapper(mapper) => self.size_of(&mapper_type(mapper)),
+                Util::Bitfield(fields) => SizeInfo::Fixed(
+                    fields
+                        .iter()
+                        .map(|field| field.size)
+                        .sum::<usize>()
+                        .div_ceil(8),
+                ),
+                Util::PrefixedString { .. } | Util::Loop(_) | Util::TopBitSetTerminatedArray(_) => {
+                    SizeInfo::Variable
+                }
+            },
+            DataType::Parameterized { name, args } => match self.scope.instantiate(name, args) {
+                Some(data_type) => self.size_of_named(name, &data_type),
+                None => SizeInfo::Variable,
+            },
+            DataType::Custom(name) => match self.scope.lookup(name) {
+                Some(TypeDefinition::DataType(data_type)) => self.size_of_named(name, data_type),
+                Some(TypeDefinition::Native) => match (self.natives, Builtin::of(name)) {
+                    (Some(natives), _) if natives.contains(name) => natives.size(name),
+                    (_, Some(Builtin::Numeric(numeric))) => numeric_size(&numeric),
+                    _ => SizeInfo::Variable,
+                },
+                None => SizeInfo::Variable,
+            },
+        }
+    }
+
+    fn size_of_named(&mut self, name: &str, data_type: &DataType) -> SizeInfo {
+        if self.seen.iter().any(|seen| seen == name) {
+            return SizeInfo::Variable;
+        }
+
+        self.seen.push(name.to_owned());
+        let size = self.size_of(data_type);
+        self.seen.pop();
+
+        size
+    }
+}
+
+fn numeric_size(numeric: &Numeric) -> SizeInfo {
+    match numeric {
+        Numeric::Byte { .. } => SizeInfo::Fixed(1),
+        Numeric::Short { .. } => SizeInfo::Fixed(2),
+        Numeric::Int { .. } | Numeric::Float { .. } => SizeInfo::Fixed(4),
+        Numeric::Long { .. } | Numeric::Double { .. } => SizeInfo::Fixed(8),
+        Numeric::Int128 { .. } => SizeInfo::Fixed(16),
+        Numeric::VarInt | Numeric::ZigZag32 => SizeInfo::Bounded(1, 5),
+        Numeric::VarLong | Numeric::ZigZag64 => SizeInfo::Bounded(1, 10),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resolve::Scope;
+    use crate::size::*;
+    use crate::*;
+
+    fn size(scope: &Scope, json: &str) -> SizeInfo {
+        let data_type: DataType = serde_json::from_str(json).expect("Failed to parse type");
+        size_of(&data_type, scope)
+    }
+
+    #[test]
+    fn test_size_of() {
+        let protocol = protocol("minecraft_v1662.json");
+        let scope = Scope::new(&protocol, "play.toClient").unwrap();
+
+        assert_eq!(size(&scope, r#""varint""#), SizeInfo::Bounded(1, 5));
+        assert_eq!(size(&scope, r#""position""#), SizeInfo::Fixed(8));
+        assert_eq!(size(&scope, r#""UUID""#), SizeInfo::Fixed(16));
+        assert_eq!(size(&scope, r#""lu128""#), SizeInfo::Fixed(16));
+        assert_eq!(size(&scope, r#""packet_update_time""#), SizeInfo::Fixed(16));
+        assert_eq!(
+            size(&scope, r#""packet_entity_velocity""#),
+            SizeInfo::Bounded(7, 11)
+        );
+        assert_eq!(size(&scope, r#""string""#), SizeInfo::Variable);
+        assert_eq!(size(&scope, r#""packet""#), SizeInfo::Variable);
+        assert_eq!(
+            size(&scope, r#"["array", {"count": 3, "type": "varint"}]"#),
+            SizeInfo::Bounded(3, 15)
+        );
+        assert_eq!(
+            size(&scope, r#"["buffer", {"count": 16}]"#),
+            SizeInfo::Fixed(16)
+        );
+        assert_eq!(
+            size(&scope, r#"["option", "i64"]"#),
+            SizeInfo::Bounded(1, 9)
+        );
+        assert_eq!(
+            size(
+                &scope,
+                r#"["switch", {"compareTo": "kind", "fields": {"0": "i32", "1": "i16"}, "default": "f32"}]"#
+            ),
+            SizeInfo::Bounded(2, 4)
+        );
+        assert_eq!(
+            size(
+                &scope,
+                r#"["switch", {"compareTo": "kind", "fields": {"0": "i32"}}]"#
+            ),
+            SizeInfo::Bounded(0, 4)
+        );
+        assert_eq!(SizeInfo::Bounded(1, 5).to_string(), "1 to 5 bytes");
+    }
+
+    #[test]
+    fn test_size_changes() {
+        let changes = size_changes(
+            &protocol("minecraft_v1444.json"),
+            &protocol("minecraft_v1662.json"),
+        );
+        let change = |name: &str| {
+            changes
+                .iter()
+                .find(|change| change.id.to_string() == name)
+                .cloned()
+        };
+
+        assert_eq!(changes.len(), 4);
+        assert_eq!(change("play.toClient.packet_update_time"), None);
+        assert_eq!(
+            change("play.toClient.packet_named_entity_spawn")
+                .map(|change| (change.old, change.new)),
+            Some((SizeInfo::Variable, SizeInfo::Bounded(43, 47)))
+        );
+        assert_eq!(
+            change("play.toServer.packet_abilities").map(|change| change.to_string()),
+            Some("play.toServer.packet_abilities: 9 bytes -> 1 byte".to_owned())
+        );
+        assert_eq!(
+            change("play.toServer.packet_use_entity").map(|change| (change.old, change.new)),
+            Some((SizeInfo::Bounded(2, 27), SizeInfo::Bounded(3, 28)))
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Semantic checks of a parsed `Protocol`, reporting inconsistencies that
+//! deserialization does not catch, like field references pointing nowhere.
+
+use crate::field_path::{Context, FieldPath, ResolveError};
+use crate::mapping::{numeric_type, MappingError, Mappings};
+use crate::resolve::{namespaces, Scope};
+use crate::walk::{children, type_references};
+use crate::{ArrayCount, Conditional, DataType, Field, Protocol, Structure, TypeDefinition, Util};
+use std::fmt;
+
+/// A problem found in a protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// JSON pointer to the offending node, e.g. `/play/toClient/types/packet/1/1/type/1/compareTo`.
+    pub path: String,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A type name is not defined in the scope it is used in.
+    UnknownType(String),
+    /// A `countFor` does not name a field of the enclosing container.
+    UnresolvedCountFor(String),
+    /// An array or buffer `count` does not point to a field read before it.
+    UnresolvedCount(String),
+    /// A switch `compareTo` does not point to a field read before it.
+    UnresolvedCompareTo(String),
+    /// The sizes of the fields of a bitfield do not sum to a multiple of 8 bits.
+    UnalignedBitfield(usize),
+    /// The keys of a mapper are not distinct integers of its type, or its names are not distinct.
+    InvalidMapping(MappingError),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+
+        match &self.kind {
+            DiagnosticKind::UnknownType(name) => write!(f, "unknown type \"{}\"", name),
+            DiagnosticKind::UnresolvedCountFor(field) => {
+                write!(f, "count is for unknown field \"{}\"", field)
+            }
+            DiagnosticKind::UnresolvedCount(field) => {
+                write!(f, "count refers to unknown field \"{}\"", field)
+            }
+            DiagnosticKind::UnresolvedCompareTo(field) => {
+                write!(f, "switch compares to unknown field \"{}\"", field)
+            }
+            DiagnosticKind::UnalignedBitfield(size) => {
+                write!(
+                    f,
+                    "bitfield of {} bits is not a whole number of bytes",
+                    size
+                )
+            }
+            DiagnosticKind::InvalidMapping(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Checks every definition of a protocol, returning the problems in order of appearance.
+///
+/// References leaving the definition they appear in, e.g. `../action` from the
+/// top of a definition, and references within templates are not checked, as they
+/// depend on where the definition is used.
+pub fn validate(protocol: &Protocol) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        context: Context::new(protocol),
+        diagnostics: Vec::new(),
+    };
+
+    for (scope, namespace, types) in namespaces(protocol) {
+        for (name, definition) in types {
+            if let TypeDefinition::DataType(data_type) = definition {
+                let path = crate::resolve::TypeId::new(&namespace, name).pointer();
+                validator.check(data_type, path, &scope, &namespace);
+            }
+        }
+    }
+
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    context: Context<'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, path: String, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic { path, kind });
+    }
+
+    fn check(&mut self, data_type: &'a DataType, path: String, scope: &Scope<'a>, namespace: &str) {
+        for (pointer, name) in type_references(data_type) {
+            // Type parameters are substituted when a template is instantiated.
+            if scope.find(name).is_none() && !name.starts_with('$') {
+                self.report(
+                    format!("{}{}", path, pointer),
+                    DiagnosticKind::UnknownType(name.to_owned()),
+                );
+            }
+        }
+
+        match data_type {
+            DataType::Conditional(conditional) => {
+                if let Conditional::Switch(switch) = conditional.as_ref() {
+                    if self.is_unresolved(&switch.compare_to, true) {
+                        self.report(
+                            format!("{}/1/compareTo", path),
+                            DiagnosticKind::UnresolvedCompareTo(switch.compare_to.clone()),
+                        );
+                    }
+                }
+            }
+            DataType::Structure(structure) => {
+                if let Structure::Container(fields) = structure.as_ref() {
+                    return self.check_container(fields, path, scope, namespace);
+                }
+
+                self.check_structure(structure, &path);
+            }
+            DataType::Util(util) => match util.as_ref() {
+                Util::Buffer(buffer) => self.check_count(buffer.count.as_ref(), &path),
+                Util::Bitfield(fields) => {
+                    let size: usize = fields.iter().map(|field| field.size).sum();
+
+                    if size % 8 != 0 {
+                        self.report(
+                            format!("{}/1", path),
+                            DiagnosticKind::UnalignedBitfield(size),
+                        );
+                    }
+                }
+                Util::Mapper(mapper) => {
+                    // Native and more deeply aliased types cannot be checked.
+                    let numeric = numeric_type(mapper, scope);
+
+                    if let Some(Err(error)) = numeric.map(|numeric| Mappings::new(mapper, &numeric))
+                    {
+                        self.report(
+                            format!("{}/1/mappings", path),
+                            DiagnosticKind::InvalidMapping(error),
+                        );
+                    }
+                }
+                Util::TopBitSetTerminatedArray(structure) => {
+                    self.check_structure(structure, &format!("{}/1/type", path))
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        for (pointer, child) in children(data_type) {
+            self.check(child, format!("{}{}", path, pointer), scope, namespace);
+        }
+    }
+
+    fn check_structure(&mut self, structure: &'a Structure, path: &str) {
+        if let Structure::Array(array) = structure {
+            self.check_count(array.count.as_ref(), path);
+        }
+    }
+
+    fn check_count(&mut self, count: Option<&ArrayCount>, path: &str) {
+        if let Some(ArrayCount::FieldReference(reference)) = count {
+            if self.is_unresolved(reference, true) {
+                self.report(
+                    format!("{}/1/count", path),
+                    DiagnosticKind::UnresolvedCount(reference.clone()),
+                );
+            }
+        }
+    }
+
+    fn check_container(
+        &mut self,
+        fields: &'a [Field],
+        path: String,
+        scope: &Scope<'a>,
+        namespace: &str,
+    ) {
+        self.context.push(fields, namespace);
+
+        for (i, field) in fields.iter().enumerate() {
+            let field_path = format!("{}/1/{}/type", path, i);
+            self.context.advance(i);
+
+            if let DataType::Structure(structure) = &field.field_type {
+                if let Structure::Count(count) = structure.as_ref() {
+                    if self.is_unresolved(&count.count_for, false) {
+                        self.report(
+                            format!("{}/1/countFor", field_path),
+                            DiagnosticKind::UnresolvedCountFor(count.count_for.clone()),
+                        );
+                    }
+                }
+            }
+
+            self.check(&field.field_type, field_path, scope, namespace);
+        }
+
+        self.context.pop();
+    }
+
+    /// Whether a reference like `name`, `../action` or `flags/present` names no
+    /// field of the enclosing containers, only those read so far if `read` is set.
+    ///
+    /// References which cannot be checked, like those leaving the definition, are
+    /// not reported.
+    fn is_unresolved(&self, reference: &str, read: bool) -> bool {
+        let path: FieldPath = match reference.parse() {
+            Ok(path) => path,
+            Err(_) => return true,
+        };
+
+        let resolved = if read {
+            self.context.resolve(&path)
+        } else {
+            self.context.resolve_declared(&path)
+        };
+
+        matches!(resolved, Err(ResolveError::UnknownField(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validate::*;
+    use crate::*;
+
+    #[test]
+    fn test_validate_protocols_data() {
+        for (path, protocol) in all_protocols() {
+            let diagnostics = validate(&protocol);
+
+            // The 1.7 protocol uses types it does not define, like `slot`.
+            if path.ends_with("minecraft_v170.json") {
+                assert_eq!(diagnostics.len(), 22);
+                assert!(diagnostics
+                    .iter()
+                    .all(|diagnostic| matches!(diagnostic.kind, DiagnosticKind::UnknownType(_))));
+            } else {
+                assert_eq!(diagnostics, Vec::new(), "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let json = r#"{
+            "types": {
+                "varint": "native",
+                "u8": "native",
+                "container": "native",
+                "switch": "native",
+                "array": "native",
+                "count": "native",
+                "bitfield": "native",
+                "mapper": "native",
+                "packet": ["container", [
+                    {"name": "flags", "type": ["bitfield", [
+                        {"name": "present", "size": 1, "signed": false},
+                        {"name": "kind", "size": 3, "signed": false}
+                    ]]},
+                    {"name": "length", "type": ["count", {"type": "varint", "countFor": "item"}]},
+                    {"name": "items", "type": ["array", {"count": "size", "type": "u8"}]},
+                    {"name": "data", "type": ["switch", {
+                        "compareTo": "flags/present",
+                        "fields": {"1": ["container", [
+                            {"name": "inner", "type": ["switch", {
+                                "compareTo": "../flags/missing",
+                                "fields": {"0": "uuid"}
+                            }]}
+                        ]]}
+                    }]},
+                    {"name": "late", "type": ["switch", {"compareTo": "after", "fields": {}}]},
+                    {"name": "after", "type": "varint"},
+                    {"name": "kind", "type": ["mapper", {"type": "u8", "mappings": {"0": "a", "0x00": "b"}}]}
+                ]]
+            }
+        }"#;
+
+        let protocol: Protocol = serde_json::from_str(json).unwrap();
+        let diagnostics: Vec<String> = validate(&protocol)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "/types/packet/1/0/type/1: bitfield of 4 bits is not a whole number of bytes",
+                "/types/packet/1/1/type/1/countFor: count is for unknown field \"item\"",
+                "/types/packet/1/2/type/1/count: count refers to unknown field \"size\"",
+                "/types/packet/1/3/type/1/fields/1/1/0/type/1/compareTo: switch compares to unknown field \"../flags/missing\"",
+                "/types/packet/1/3/type/1/fields/1/1/0/type/1/fields/0: unknown type \"uuid\"",
+                "/types/packet/1/4/type/1/compareTo: switch compares to unknown field \"after\"",
+                "/types/packet/1/6/type/1/mappings: mapper keys \"0\" and \"0x00\" are equal",
+            ]
+        );
+    }
+}
@@ -0,0 +1,526 @@
+use crate::Numeric;
+use linked_hash_map::LinkedHashMap;
+use std::convert::TryFrom;
+
+/// A dynamic value produced by decoding data described by a `DataType`.
+///
+/// Each `Numeric` decodes into the variant of its width (`varint` into `I32`),
+/// the `UUID` native into `U128`,
+/// `Primitive::String` and `pstring` into `String`, buffers into `Bytes`,
+/// arrays and loops into `List`, containers and bitfields into `Map`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Value of a `void` type.
+    Void,
+    Bool(bool),
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F32(f32),
+    F64(f64),
+    String(String),
+    /// Raw bytes of a buffer.
+    Bytes(Vec<u8>),
+    /// Elements of an array or a loop.
+    List(Vec<Value>),
+    /// Named values of a container or a bitfield.
+    Map(LinkedHashMap<String, Value>),
+    /// Value of an optional type.
+    Option(Option<Box<Value>>),
+}
+
+impl Value {
+    /// A short name of the kind of this value, used in error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Void => "void",
+            Value::Bool(_) => "bool",
+            Value::I8(_) => "i8",
+            Value::U8(_) => "u8",
+            Value::I16(_) => "i16",
+            Value::U16(_) => "u16",
+            Value::I32(_) => "i32",
+            Value::U32(_) => "u32",
+            Value::I64(_) => "i64",
+            Value::U64(_) => "u64",
+            Value::I128(_) => "i128",
+            Value::U128(_) => "u128",
+            Value::F32(_) => "f32",
+            Value::F64(_) => "f64",
+            Value::String(_) => "string",
+            Value::Bytes(_) => "bytes",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Option(_) => "option",
+        }
+    }
+
+    pub fn is_void(&self) -> bool {
+        matches!(self, Value::Void)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns an integer of any width.
+    pub fn as_i128(&self) -> Option<i128> {
+        match *self {
+            Value::I8(value) => Some(value.into()),
+            Value::U8(value) => Some(value.into()),
+            Value::I16(value) => Some(value.into()),
+            Value::U16(value) => Some(value.into()),
+            Value::I32(value) => Some(value.into()),
+            Value::U32(value) => Some(value.into()),
+            Value::I64(value) => Some(value.into()),
+            Value::U64(value) => Some(value.into()),
+            Value::I128(value) => Some(value),
+            Value::U128(value) => i128::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns a non-negative integer of any width, including those beyond `i128::MAX`.
+    pub fn as_u128(&self) -> Option<u128> {
+        match *self {
+            Value::U128(value) => Some(value),
+            ref value => value.as_i128().and_then(|value| u128::try_from(value).ok()),
+        }
+    }
+
+    /// Returns an integer of any width if it fits into `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|value| i64::try_from(value).ok())
+    }
+
+    /// Returns an integer of any width if it fits into `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128().and_then(|value| u64::try_from(value).ok())
+    }
+
+    /// Returns a float or an integer as `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::F32(value) => Some(value.into()),
+            Value::F64(value) => Some(value),
+            ref value => value.as_i128().map(|value| value as f64),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&LinkedHashMap<String, Value>> {
+        match self {
+            Value::Map(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns a field of a map value.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.as_map().and_then(|map| map.get(name))
+    }
+
+    /// Converts a number into the variant the numeric type decodes into,
+    /// e.g. a `U64` read from JSON into the `I16` of an `i16` field. 128-bit
+    /// types also accept the decimal strings they are converted into JSON as.
+    ///
+    /// Returns `None` if the value is not a number or is out of range.
+    pub fn coerce(&self, numeric: &Numeric) -> Option<Value> {
+        macro_rules! integer {
+            ($variant:ident) => {
+                self.as_i128()
+                    .and_then(|value| TryFrom::try_from(value).ok())
+                    .map(Value::$variant)
+            };
+        }
+
+        match numeric {
+            Numeric::Byte { signed: true } => integer!(I8),
+            Numeric::Byte { signed: false } => integer!(U8),
+            Numeric::Short { signed: true, .. } => integer!(I16),
+            Numeric::Short { signed: false, .. } => integer!(U16),
+            Numeric::Int { signed: true, .. } => integer!(I32),
+            Numeric::Int { signed: false, .. } => integer!(U32),
+            Numeric::Long { signed: true, .. } => integer!(I64),
+            Numeric::Long { signed: false, .. } => integer!(U64),
+            Numeric::Int128 { signed: true, .. } => {
+                integer!(I128).or_else(|| self.as_str()?.parse().ok().map(Value::I128))
+            }
+            Numeric::Int128 { signed: false, .. } => self
+                .as_u128()
+                .or_else(|| self.as_str()?.parse().ok())
+                .map(Value::U128),
+            Numeric::Float { .. } => self.as_f64().map(|value| Value::F32(value as f32)),
+            Numeric::Double { .. } => self.as_f64().map(Value::F64),
+            Numeric::VarInt | Numeric::ZigZag32 => integer!(I32),
+            Numeric::VarLong | Numeric::ZigZag64 => integer!(I64),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from!(
+    bool => Bool,
+    i8 => I8,
+    u8 => U8,
+    i16 => I16,
+    u16 => U16,
+    i32 => I32,
+    u32 => U32,
+    i64 => I64,
+    u64 => U64,
+    i128 => I128,
+    u128 => U128,
+    f32 => F32,
+    f64 => F64,
+    String => String,
+    Vec<u8> => Bytes,
+    Vec<Value> => List,
+    LinkedHashMap<String, Value> => Map
+);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        Value::Option(value.map(|value| Box::new(value.into())))
+    }
+}
+
+/// Converts a value into JSON. Void and absent options become `null`,
+/// bytes become an array of numbers, 128-bit integers that do not fit into
+/// 64 bits become decimal strings and non-finite floats become `null`.
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        use serde_json::Value as Json;
+
+        match value {
+            Value::Void | Value::Option(None) => Json::Null,
+            Value::Bool(value) => Json::Bool(value),
+            Value::I8(value) => value.into(),
+            Value::U8(value) => value.into(),
+            Value::I16(value) => value.into(),
+            Value::U16(value) => value.into(),
+            Value::I32(value) => value.into(),
+            Value::U32(value) => value.into(),
+            Value::I64(value) => value.into(),
+            Value::U64(value) => value.into(),
+            Value::I128(value) => match i64::try_from(value) {
+                Ok(value) => value.into(),
+                Err(_) => Json::String(value.to_string()),
+            },
+            Value::U128(value) => match u64::try_from(value) {
+                Ok(value) => value.into(),
+                Err(_) => Json::String(value.to_string()),
+            },
+            Value::F32(value) => {
+                serde_json::Number::from_f64(value.into()).map_or(Json::Null, Json::Number)
+            }
+            Value::F64(value) => {
+                serde_json::Number::from_f64(value).map_or(Json::Null, Json::Number)
+            }
+            Value::String(value) => Json::String(value),
+            Value::Bytes(value) => value.into(),
+            Value::List(values) => values.into_iter().map(Json::from).collect(),
+            Value::Map(values) => values
+                .into_iter()
+                .map(|(name, value)| (name, Json::from(value)))
+                .collect(),
+            Value::Option(Some(value)) => (*value).into(),
+        }
+    }
+}
+
+/// Converts JSON into a value. Integers become `I64`, or `U64` if they do
+/// not fit, and `null` becomes `Void`; encoders accept any integer width
+/// that fits the encoded type, and `Void` as an absent option.
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value as Json;
+
+        match value {
+            Json::Null => Value::Void,
+            Json::Bool(value) => Value::Bool(value),
+            Json::Number(number) => match (number.as_i64(), number.as_u64()) {
+                (Some(value), _) => Value::I64(value),
+                (None, Some(value)) => Value::U64(value),
+                _ => Value::F64(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            Json::String(value) => Value::String(value),
+            Json::Array(values) => Value::List(values.into_iter().map(Value::from).collect()),
+            Json::Object(values) => Value::Map(
+                values
+                    .into_iter()
+                    .map(|(name, value)| (name, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// A value decoded from a byte slice, borrowing the strings and bytes it was read from.
+///
+/// Containers, arrays and options have their own variants so that their parts
+/// can borrow; everything else is held as an owned `Value`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'b> {
+    /// A value holding no data of the input, e.g. a number or the name a mapper maps to.
+    Owned(Value),
+    /// Value of `cstring` or `pstring`.
+    Str(&'b str),
+    /// Raw bytes of a buffer.
+    Bytes(&'b [u8]),
+    List(Vec<ValueRef<'b>>),
+    Map(LinkedHashMap<String, ValueRef<'b>>),
+    Option(Option<Box<ValueRef<'b>>>),
+}
+
+impl<'b> ValueRef<'b> {
+    /// The kind of the value, as given by `Value::kind` for its owned counterpart.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ValueRef::Owned(value) => value.kind(),
+            ValueRef::Str(_) => "string",
+            ValueRef::Bytes(_) => "bytes",
+            ValueRef::List(_) => "list",
+            ValueRef::Map(_) => "map",
+            ValueRef::Option(_) => "option",
+        }
+    }
+
+    /// Returns an integer of any width.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            ValueRef::Owned(value) => value.as_i128(),
+            _ => None,
+        }
+    }
+
+    /// Returns a string, borrowed from the input unless it is a mapped name.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ValueRef::Owned(value) => value.as_str(),
+            ValueRef::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'b [u8]> {
+        match *self {
+            ValueRef::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[ValueRef<'b>]> {
+        match self {
+            ValueRef::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&LinkedHashMap<String, ValueRef<'b>>> {
+        match self {
+            ValueRef::Map(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns a field of a map value.
+    pub fn get(&self, name: &str) -> Option<&ValueRef<'b>> {
+        self.as_map().and_then(|map| map.get(name))
+    }
+
+    /// Copies the borrowed parts, e.g. to keep the value after the input is gone.
+    pub fn to_value(&self) -> Value {
+        match self {
+            ValueRef::Owned(value) => value.clone(),
+            ValueRef::Str(value) => Value::String((*value).to_owned()),
+            ValueRef::Bytes(value) => Value::Bytes(value.to_vec()),
+            ValueRef::List(values) => Value::List(values.iter().map(ValueRef::to_value).collect()),
+            ValueRef::Map(values) => Value::Map(
+                values
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.to_value()))
+                    .collect(),
+            ),
+            ValueRef::Option(value) => {
+                Value::Option(value.as_ref().map(|value| Box::new(value.to_value())))
+            }
+        }
+    }
+}
+
+/// Wraps an owned value, giving lists, maps and options their own variants.
+impl From<Value> for ValueRef<'_> {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::List(values) => ValueRef::List(values.into_iter().map(ValueRef::from).collect()),
+            Value::Map(values) => ValueRef::Map(
+                values
+                    .into_iter()
+                    .map(|(name, value)| (name, value.into()))
+                    .collect(),
+            ),
+            Value::Option(value) => ValueRef::Option(value.map(|value| Box::new((*value).into()))),
+            value => ValueRef::Owned(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{Decoder, Encoder};
+    use crate::value::Value;
+    use crate::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_value_to_json() {
+        let value = map(vec![
+            ("id", Value::I32(-3)),
+            ("data", Value::Bytes(vec![1, 2])),
+            ("flag", Value::from(Some(true))),
+            ("nothing", Value::Option(None)),
+            ("name", Value::from("test")),
+            ("uuid", Value::U128(u128::MAX)),
+        ]);
+
+        let json: serde_json::Value = value.into();
+
+        assert_eq!(
+            json,
+            json!({
+                "id": -3,
+                "data": [1, 2],
+                "flag": true,
+                "nothing": null,
+                "name": "test",
+                "uuid": "340282366920938463463374607431768211455"
+            })
+        );
+    }
+
+    #[test]
+    fn test_value_from_json() {
+        let value = Value::from(json!({"a": [1, 18446744073709551615u64, 1.5], "b": null}));
+
+        assert_eq!(
+            value.get("a"),
+            Some(&Value::List(vec![
+                Value::I64(1),
+                Value::U64(u64::MAX),
+                Value::F64(1.5)
+            ]))
+        );
+        assert_eq!(value.get("b"), Some(&Value::Void));
+    }
+
+    #[test]
+    fn test_value_coerce() {
+        let short = Numeric::Short {
+            signed: true,
+            byte_order: ByteOrder::BigEndian,
+        };
+
+        assert_eq!(Value::I64(-5).coerce(&short), Some(Value::I16(-5)));
+        assert_eq!(Value::U64(40000).coerce(&short), None);
+        assert_eq!(Value::I64(7).coerce(&Numeric::VarInt), Some(Value::I32(7)));
+        assert_eq!(Value::from("7").coerce(&Numeric::VarInt), None);
+
+        let uuid = Numeric::Int128 {
+            signed: false,
+            byte_order: ByteOrder::BigEndian,
+        };
+        assert_eq!(Value::I64(7).coerce(&uuid), Some(Value::U128(7)));
+        assert_eq!(Value::I64(-1).coerce(&uuid), None);
+        assert_eq!(Value::from("12").coerce(&uuid), Some(Value::U128(12)));
+    }
+
+    #[test]
+    fn test_value_json_round_trip() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "handshaking.toServer").unwrap();
+        let encoder = Encoder::with_namespace(&protocol, "handshaking.toServer").unwrap();
+
+        let bytes = [
+            0x00, 0xf2, 0x03, 0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't', 0x63,
+            0xdd, 0x01,
+        ];
+
+        let json: serde_json::Value = decoder.decode_type("packet", &bytes[..]).unwrap().into();
+        let value = Value::from(json);
+
+        let mut encoded = Vec::new();
+        encoder.encode_type("packet", &value, &mut encoded).unwrap();
+
+        assert_eq!(encoded, bytes);
+    }
+
+    #[test]
+    fn test_rest_buffer_json_round_trip() {
+        let protocol = protocol("minecraft_v1662.json");
+        let decoder = Decoder::with_namespace(&protocol, "play.toClient").unwrap();
+        let encoder = Encoder::with_namespace(&protocol, "play.toClient").unwrap();
+
+        let bytes = [0x02, b'h', b'i', 0x01, 0x02, 0xff];
+
+        let json: serde_json::Value = decoder
+            .decode_type("packet_custom_payload", &bytes[..])
+            .unwrap()
+            .into();
+        assert_eq!(json, json!({"channel": "hi", "data": [1, 2, 255]}));
+
+        let mut encoded = Vec::new();
+        encoder
+            .encode_type("packet_custom_payload", &Value::from(json), &mut encoded)
+            .unwrap();
+
+        assert_eq!(encoded, bytes);
+    }
+}
@@ -0,0 +1,134 @@
+//! Traversal of `DataType` trees along with JSON pointers to their nodes.
+
+use crate::codec::mapper_type;
+use crate::{Conditional, DataType, Structure, Util};
+
+/// Direct children of a type with their JSON pointers relative to it.
+pub(crate) fn children(data_type: &DataType) -> Vec<(String, &DataType)> {
+    let mut children = Vec::new();
+
+    match data_type {
+        DataType::Conditional(conditional) => match conditional.as_ref() {
+            Conditional::Switch(switch) => {
+                for (key, data_type) in &switch.fields {
+                    children.push((format!("/1/fields/{}", escape(key)), data_type));
+                }
+
+                if let Some(default) = &switch.default {
+                    children.push(("/1/default".to_owned(), default));
+                }
+            }
+            Conditional::Option(data_type) => children.push(("/1".to_owned(), data_type)),
+        },
+        DataType::Structure(structure) => structure_children("", structure, &mut children),
+        DataType::Util(util) => match util.as_ref() {
+            Util::Buffer(buffer) => {
+                if let Some(count_type) = &buffer.count_type {
+                    children.push(("/1/countType".to_owned(), count_type));
+                }
+            }
+            Util::PrefixedString { count_type } => {
+                children.push(("/1/countType".to_owned(), count_type));
+            }
+            Util::Loop(loop_util) => children.push(("/1/type".to_owned(), &loop_util.data_type)),
+            Util::TopBitSetTerminatedArray(structure) => {
+                structure_children("/1/type", structure, &mut children)
+            }
+            Util::Mapper(_) | Util::Bitfield(_) => {}
+        },
+        DataType::Numeric(_)
+        | DataType::Primitive(_)
+        | DataType::Parameterized { .. }
+        | DataType::Custom(_) => {}
+    }
+
+    children
+}
+
+fn structure_children<'a>(
+    prefix: &str,
+    structure: &'a Structure,
+    children: &mut Vec<(String, &'a DataType)>,
+) {
+    match structure {
+        Structure::Array(array) => {
+            if let Some(count_type) = &array.count_type {
+                children.push((format!("{}/1/countType", prefix), count_type));
+            }
+
+            children.push((format!("{}/1/type", prefix), &array.elements_type));
+        }
+        Structure::Container(fields) => {
+            for (i, field) in fields.iter().enumerate() {
+                children.push((format!("{}/1/{}/type", prefix, i), &field.field_type));
+            }
+        }
+        Structure::Count(count) => children.push((format!("{}/1/type", prefix), &count.count_type)),
+    }
+}
+
+/// Direct children of a type, in the same order as `children`.
+pub(crate) fn children_mut(data_type: &mut DataType) -> Vec<&mut DataType> {
+    fn structure_children_mut(structure: &mut Structure) -> Vec<&mut DataType> {
+        match structure {
+            Structure::Array(array) => array
+                .count_type
+                .iter_mut()
+                .chain(Some(&mut array.elements_type))
+                .collect(),
+            Structure::Container(fields) => fields
+                .iter_mut()
+                .map(|field| &mut field.field_type)
+                .collect(),
+            Structure::Count(count) => vec![&mut count.count_type],
+        }
+    }
+
+    match data_type {
+        DataType::Conditional(conditional) => match conditional.as_mut() {
+            Conditional::Switch(switch) => switch
+                .fields
+                .iter_mut()
+                .map(|(_, data_type)| data_type)
+                .chain(switch.default.as_mut())
+                .collect(),
+            Conditional::Option(data_type) => vec![data_type],
+        },
+        DataType::Structure(structure) => structure_children_mut(structure),
+        DataType::Util(util) => match util.as_mut() {
+            Util::Buffer(buffer) => buffer.count_type.iter_mut().collect(),
+            Util::PrefixedString { count_type } => vec![count_type],
+            Util::Loop(loop_util) => vec![&mut loop_util.data_type],
+            Util::TopBitSetTerminatedArray(structure) => structure_children_mut(structure),
+            Util::Mapper(_) | Util::Bitfield(_) => Vec::new(),
+        },
+        DataType::Numeric(_)
+        | DataType::Primitive(_)
+        | DataType::Parameterized { .. }
+        | DataType::Custom(_) => Vec::new(),
+    }
+}
+
+/// Names of the types a node refers to, with their JSON pointers relative to it.
+pub(crate) fn type_references(data_type: &DataType) -> Vec<(String, &str)> {
+    match data_type {
+        DataType::Custom(name) => vec![(String::new(), name.as_str())],
+        DataType::Parameterized { name, .. } => vec![("/0".to_owned(), name.as_str())],
+        DataType::Util(util) => match util.as_ref() {
+            Util::Mapper(mapper) => match mapper_type(mapper) {
+                DataType::Custom(_) => vec![("/1/type".to_owned(), mapper.mappings_type.as_str())],
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        },
+        DataType::Conditional(_)
+        | DataType::Numeric(_)
+        | DataType::Primitive(_)
+        | DataType::Structure(_) => Vec::new(),
+    }
+}
+
+/// Escapes a key to be used as a JSON pointer segment.
+pub(crate) fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}